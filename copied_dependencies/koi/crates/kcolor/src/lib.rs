@@ -29,6 +29,18 @@ impl kecs::ComponentTrait for Color {
     }
 }
 
+/// Reflected as a list of *encoded* (non-linear) sRGB red, green, blue, and alpha values.
+impl kreflect::ReflectFieldTrait for Color {
+    fn to_reflect_value(&self) -> kreflect::ReflectValue {
+        kreflect::ReflectFieldTrait::to_reflect_value(&self.to_srgb())
+    }
+
+    fn from_reflect_value(value: kreflect::ReflectValue) -> Option<Self> {
+        let srgb = <kmath::Vec4 as kreflect::ReflectFieldTrait>::from_reflect_value(value)?;
+        Some(Self::new(srgb[0], srgb[1], srgb[2], srgb[3]))
+    }
+}
+
 impl Color {
     /// Create a new [Color] from sRGB red, green, blue, and alpha (transparency) values.
     pub fn new(red: FType, green: FType, blue: FType, alpha: FType) -> Self {
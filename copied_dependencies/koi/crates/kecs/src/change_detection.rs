@@ -0,0 +1,106 @@
+use crate::*;
+use std::sync::atomic::Ordering;
+
+// Changes are tracked per archetype channel, not per component.
+// A channel counts as changed whenever a system borrows it mutably,
+// so a `Changed` filter can match entities whose components weren't actually modified.
+// Systems should treat a match as "might have changed".
+
+/// A [Query] filter that only matches [Archetype]s where a `T` was added or borrowed
+/// mutably since the system last ran.
+pub struct Changed<T: ComponentTrait> {
+    phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: ComponentTrait> FilterTrait for Changed<T> {
+    fn append_filters(filters: &mut Vec<(Option<usize>, Filter)>) {
+        With::<T>::append_filters(filters)
+    }
+
+    fn matches_changes(archetype: &Archetype, last_run_tick: u64) -> bool {
+        archetype
+            .get_channel(get_component_id::<T>())
+            .is_some_and(|channel| channel.changed_tick.load(Ordering::Relaxed) > last_run_tick)
+    }
+}
+
+/// A [Query] filter that only matches [Archetype]s where a `T` was added since the system last ran.
+pub struct Added<T: ComponentTrait> {
+    phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: ComponentTrait> FilterTrait for Added<T> {
+    fn append_filters(filters: &mut Vec<(Option<usize>, Filter)>) {
+        With::<T>::append_filters(filters)
+    }
+
+    fn matches_changes(archetype: &Archetype, last_run_tick: u64) -> bool {
+        archetype
+            .get_channel(get_component_id::<T>())
+            .is_some_and(|channel| channel.added_tick.load(Ordering::Relaxed) > last_run_tick)
+    }
+}
+
+/// The [Entity]s that had a `T` removed, or were despawned with one, since the system last ran.
+/// Removals are forgotten after two calls to [World::clear_trackers].
+pub struct RemovedComponents<T: ComponentTrait> {
+    entities: Vec<Entity>,
+    phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: ComponentTrait> RemovedComponents<T> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Entity> {
+        self.entities.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+}
+
+impl<'a, T: ComponentTrait> IntoIterator for &'a RemovedComponents<T> {
+    type Item = &'a Entity;
+    type IntoIter = std::slice::Iter<'a, Entity>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.iter()
+    }
+}
+
+impl<T: ComponentTrait> SystemParameterTrait for RemovedComponents<T> {
+    fn get_meta_data(
+        _world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError> {
+        // Removals are stored on the [World] so no archetypes are accessed.
+        Ok(SystemParameterMetaData {
+            archetypes: Vec::new(),
+            channels: Vec::new(),
+            last_run_tick,
+        })
+    }
+}
+
+impl<'a, T: ComponentTrait> SystemParameterFetchTrait<'a> for RemovedComponents<T> {
+    type FetchResult = Option<RemovedComponents<T>>;
+
+    fn fetch(
+        world: &'a World,
+        meta_data: &SystemParameterMetaData,
+    ) -> Result<Self::FetchResult, KecsError> {
+        Ok(Some(RemovedComponents {
+            entities: world.removed_since(get_component_id::<T>(), meta_data.last_run_tick),
+            phantom: std::marker::PhantomData,
+        }))
+    }
+}
+
+impl<'b, T: ComponentTrait> AsSystemArg<'b> for Option<RemovedComponents<T>> {
+    type Arg = RemovedComponents<T>;
+    fn as_system_arg(&'b mut self) -> Self::Arg {
+        self.take().unwrap()
+    }
+}
@@ -36,6 +36,7 @@ pub(crate) fn add_components_to_entity_inner(
         .ok_or(KecsError::EntityMissing)?;
 
     components_and_component_ids.sort_unstable_by_key(|(_, component_id)| *component_id);
+    let change_tick = world.change_tick();
     let old_archetype = &world.archetypes[entity_location.archetype_index];
     let new_component_ids = merge_sorted_iter(
         old_archetype.channels.len() + components_and_component_ids.len(),
@@ -110,6 +111,7 @@ pub(crate) fn add_components_to_entity_inner(
             let component_and_component_id = &mut components_and_component_ids[component_index];
             if channel.component_id == component_and_component_id.1 {
                 channel.data.push(&mut *component_and_component_id.0);
+                channel.mark_added(change_tick);
                 component_index += 1;
                 if component_index >= components_and_component_ids.len() {
                     break;
@@ -127,6 +129,7 @@ pub(crate) fn add_components_to_entity_inner(
                     entity_location.index_within_archetype,
                     &mut *component_and_component_id.0,
                 );
+                channel.mark_changed(change_tick);
                 component_index += 1;
                 if component_index >= components_and_component_ids.len() {
                     break;
@@ -0,0 +1,144 @@
+use crate::*;
+use std::sync::RwLockWriteGuard;
+
+/// A double-buffered channel of `T` events, stored in the [World] as a singleton.
+///
+/// Add one with [World::add_events], send with an [EventWriter] and read with an [EventReader].
+/// Every call to [World::clear_trackers] swaps the buffers so an event is kept until the second call.
+/// Each [EventReader] only sees events sent since its system last ran, so it reads each event once.
+pub struct Events<T> {
+    /// Events sent since the last [Events::update] and the tick they were sent at.
+    events: Vec<(T, u64)>,
+    /// Events sent before the last [Events::update]. These are dropped by the next update.
+    previous_events: Vec<(T, u64)>,
+}
+
+impl<T: Send + Sync + 'static> ComponentTrait for Events<T> {}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            previous_events: Vec::new(),
+        }
+    }
+
+    /// Sends an event that readers that last ran before `tick` will see.
+    pub fn send(&mut self, tick: u64, event: T) {
+        self.events.push((event, tick))
+    }
+
+    /// Iterates the events sent after `last_run_tick`, oldest first.
+    pub fn iter_since(&self, last_run_tick: u64) -> impl Iterator<Item = &T> {
+        self.previous_events
+            .iter()
+            .chain(self.events.iter())
+            .filter(move |(_, tick)| *tick > last_run_tick)
+            .map(|(event, _)| event)
+    }
+
+    /// Drops the events sent before the previous update.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.events, &mut self.previous_events);
+        self.events.clear();
+    }
+}
+
+pub(crate) fn update_events<T: Send + Sync + 'static>(world: &mut World) {
+    if let Ok(events) = world.get_single_component_mut::<Events<T>>() {
+        events.update();
+    }
+}
+
+/// A system parameter to send `T` events.
+/// Returns [KecsError::NoMatchingComponent] if [World::add_events] wasn't called for `T`.
+pub struct EventWriter<'a, T: Send + Sync + 'static> {
+    events: RwLockWriteGuard<'a, Vec<Events<T>>>,
+    tick: u64,
+}
+
+impl<T: Send + Sync + 'static> EventWriter<'_, T> {
+    pub fn send(&mut self, event: T) {
+        self.events[0].send(self.tick, event)
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParameterTrait for EventWriter<'_, T> {
+    fn get_meta_data(
+        world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError> {
+        <&mut Events<T> as SystemParameterTrait>::get_meta_data(world, last_run_tick)
+    }
+}
+
+impl<'a, T: Send + Sync + 'static> SystemParameterFetchTrait<'a> for EventWriter<'_, T> {
+    type FetchResult = Option<EventWriter<'a, T>>;
+
+    fn fetch(
+        world: &'a World,
+        meta_data: &SystemParameterMetaData,
+    ) -> Result<Self::FetchResult, KecsError> {
+        Ok(Some(EventWriter {
+            events: <&mut Events<T> as SystemParameterFetchTrait>::fetch(world, meta_data)?,
+            // Readers that started running before now haven't seen these events.
+            tick: world.change_tick(),
+        }))
+    }
+}
+
+impl<'a, 'b, T: Send + Sync + 'static> AsSystemArg<'b> for Option<EventWriter<'a, T>> {
+    type Arg = EventWriter<'a, T>;
+    fn as_system_arg(&'b mut self) -> Self::Arg {
+        self.take().unwrap()
+    }
+}
+
+/// A system parameter to read the `T` events sent since the system last ran.
+/// Returns [KecsError::NoMatchingComponent] if [World::add_events] wasn't called for `T`.
+pub struct EventReader<'a, T: Send + Sync + 'static> {
+    events: RwLockReadGuard<'a, Vec<Events<T>>>,
+    last_run_tick: u64,
+}
+
+impl<T: Send + Sync + 'static> EventReader<'_, T> {
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events[0].iter_since(self.last_run_tick)
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParameterTrait for EventReader<'_, T> {
+    fn get_meta_data(
+        world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError> {
+        <&Events<T> as SystemParameterTrait>::get_meta_data(world, last_run_tick)
+    }
+}
+
+impl<'a, T: Send + Sync + 'static> SystemParameterFetchTrait<'a> for EventReader<'_, T> {
+    type FetchResult = Option<EventReader<'a, T>>;
+
+    fn fetch(
+        world: &'a World,
+        meta_data: &SystemParameterMetaData,
+    ) -> Result<Self::FetchResult, KecsError> {
+        Ok(Some(EventReader {
+            events: <&Events<T> as SystemParameterFetchTrait>::fetch(world, meta_data)?,
+            last_run_tick: meta_data.last_run_tick,
+        }))
+    }
+}
+
+impl<'a, 'b, T: Send + Sync + 'static> AsSystemArg<'b> for Option<EventReader<'a, T>> {
+    type Arg = EventReader<'a, T>;
+    fn as_system_arg(&'b mut self) -> Self::Arg {
+        self.take().unwrap()
+    }
+}
@@ -61,6 +61,46 @@ impl ComponentTrait for HierarchyNode {
     }
 }
 
+impl<'w> kserde::Serialize<WorldSerializer<'w>> for HierarchyNode {
+    fn serialize(&self, serializer: &mut WorldSerializer<'w>) {
+        use kserde::Serializer;
+        serializer.begin_object();
+        serializer.property("parent");
+        serializer.value(&self.parent);
+        serializer.property("last_child");
+        serializer.value(&self.last_child);
+        serializer.property("next_sibling");
+        serializer.value(&self.next_sibling);
+        serializer.property("previous_sibling");
+        serializer.value(&self.previous_sibling);
+        serializer.end_object();
+    }
+}
+
+impl<'a, 'w> kserde::Deserialize<'a, WorldDeserializer<'a, 'w>> for HierarchyNode {
+    fn deserialize(deserializer: &mut WorldDeserializer<'a, 'w>) -> Option<Self> {
+        use kserde::Deserializer;
+        let mut hierarchy_node = Self {
+            parent: None,
+            last_child: None,
+            next_sibling: None,
+            previous_sibling: None,
+        };
+        deserializer.begin_object().then_some(())?;
+        while let Some(property) = deserializer.has_property() {
+            let entity = deserialize_optional_entity(deserializer)?;
+            match &*property {
+                "parent" => hierarchy_node.parent = entity,
+                "last_child" => hierarchy_node.last_child = entity,
+                "next_sibling" => hierarchy_node.next_sibling = entity,
+                "previous_sibling" => hierarchy_node.previous_sibling = entity,
+                _ => return None,
+            }
+        }
+        Some(hierarchy_node)
+    }
+}
+
 impl HierarchyNode {
     pub fn set_parent(
         world: &mut World,
@@ -34,11 +34,24 @@ pub use component_bundle::*;
 mod query;
 pub use query::*;
 
+mod change_detection;
+pub use change_detection::*;
+
+mod events;
+pub use events::*;
+
+mod serialization;
+pub use serialization::*;
+
+mod reflection;
+pub use reflection::*;
+
 pub use kecs_derive::*;
-/*
+
+#[cfg(feature = "scheduler")]
 mod scheduler;
+#[cfg(feature = "scheduler")]
 pub use scheduler::*;
-*/
 
 mod entities;
 pub use entities::*;
@@ -69,6 +82,10 @@ pub(crate) fn get_component_id<T: 'static + ComponentTrait>() -> ComponentId {
 }
 
 pub trait ComponentTrait: 'static + Send + Sync + Sized {
+    /// Systems that access this component always run on the thread that calls [run_systems].
+    /// This is for components that wrap things like graphics contexts that only work on one thread.
+    const MAIN_THREAD_ONLY: bool = false;
+
     fn get_component_id(&self) -> ComponentId {
         ComponentId(TypeId::of::<Self>())
     }
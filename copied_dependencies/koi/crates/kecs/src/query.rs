@@ -5,6 +5,11 @@ use crate::*;
 
 pub trait FilterTrait {
     fn append_filters(filters: &mut Vec<(Option<usize>, Filter)>);
+
+    /// Change filters like [Changed] skip [Archetype]s that haven't changed since `last_run_tick`.
+    fn matches_changes(_archetype: &Archetype, _last_run_tick: u64) -> bool {
+        true
+    }
 }
 
 pub struct With<T: ComponentTrait> {
@@ -131,10 +136,11 @@ impl<'a, PARAMETERS: QueryParametersTrait, FILTERS: FilterTrait> Query<'a, PARAM
     }
 }
 
-pub(crate) fn get_meta_data<const CHANNEL_COUNT: usize>(
+pub(crate) fn get_meta_data<const CHANNEL_COUNT: usize, FILTERS: FilterTrait>(
     world: &World,
     filters: &[(Option<usize>, Filter)],
     mutable: [bool; CHANNEL_COUNT],
+    last_run_tick: u64,
 ) -> Result<SystemParameterMetaData, KecsError> {
     let mut archetypes = Vec::new();
     let mut channels = Vec::new();
@@ -143,6 +149,12 @@ pub(crate) fn get_meta_data<const CHANNEL_COUNT: usize>(
         .storage_lookup
         .matching_archetype_iterator::<CHANNEL_COUNT>(filters)
     {
+        if !FILTERS::matches_changes(
+            &world.archetypes[matching_archetype.archetype_index],
+            last_run_tick,
+        ) {
+            continue;
+        }
         archetypes.push(matching_archetype.archetype_index);
         for (matching_archetype_channel, mutable) in
             matching_archetype.channels.iter().zip(mutable.iter())
@@ -154,17 +166,21 @@ pub(crate) fn get_meta_data<const CHANNEL_COUNT: usize>(
     Ok(SystemParameterMetaData {
         archetypes,
         channels,
+        last_run_tick,
     })
 }
 
 // Manual implementations for Query<'_, A> because it's easier to type Query<'a, A> instead of
 // Query<'a, (A,)> to get all entities with a single component.
 impl<A: QueryParameterTrait, FILTERS: FilterTrait> SystemParameterTrait for Query<'_, A, FILTERS> {
-    fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError> {
+    fn get_meta_data(
+        world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError> {
         let mut filters = vec![(Some(0), A::filter())];
         FILTERS::append_filters(&mut filters);
         let mutable = [A::mutable()];
-        get_meta_data(world, &filters, mutable)
+        get_meta_data::<1, FILTERS>(world, &filters, mutable, last_run_tick)
     }
 }
 
@@ -209,15 +225,20 @@ macro_rules! query_impls {
                     $tuple::append_filters(filters);
                  )*
             }
+
+            #[allow(unused)]
+            fn matches_changes(archetype: &Archetype, last_run_tick: u64) -> bool {
+                true $(&& $tuple::matches_changes(archetype, last_run_tick))*
+            }
         }
 
         #[allow(unused_mut, unused)]
         impl<FILTERS: FilterTrait, $( $tuple: QueryParameterTrait,)*> SystemParameterTrait for Query<'_, ($( $tuple,)*), FILTERS> {
-            fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError> {
+            fn get_meta_data(world: &World, last_run_tick: u64) -> Result<SystemParameterMetaData, KecsError> {
                 let mut filters = vec![$( (Some($index), $tuple::filter()),)*];
                 FILTERS::append_filters(&mut filters);
                 let mutable = [$( $tuple::mutable(),)*];
-                get_meta_data(world, &filters, mutable)
+                get_meta_data::<$count, FILTERS>(world, &filters, mutable, last_run_tick)
             }
         }
 
@@ -0,0 +1,101 @@
+use crate::*;
+use kreflect::Reflect;
+use std::sync::RwLock;
+
+/// Gets a component from its channel and its index in the channel.
+type GetReflectMut = fn(&mut dyn Any, usize) -> &mut dyn Reflect;
+
+/// The component types that can be read and edited at runtime through [Reflect],
+/// for tools like world inspectors that don't know about every component type.
+#[derive(Clone, Default)]
+pub struct ReflectRegistry {
+    registrations: HashMap<ComponentId, GetReflectMut>,
+}
+
+impl ComponentTrait for ReflectRegistry {}
+
+impl ReflectRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: ComponentTrait + Reflect>(&mut self) {
+        self.registrations
+            .insert(get_component_id::<T>(), get_reflect_mut::<T>);
+    }
+
+    /// The registered components of an [Entity].
+    /// Each component is marked as changed, as if it were borrowed mutably by a system.
+    pub fn components_mut<'a>(
+        &self,
+        world: &'a mut World,
+        entity: Entity,
+    ) -> Result<Vec<(ComponentId, &'a mut dyn Reflect)>, KecsError> {
+        let change_tick = world.change_tick();
+        let entity_location = world
+            .entities
+            .get_entity_location(entity)
+            .ok_or(KecsError::EntityMissing)?;
+        let archetype = &mut world.archetypes[entity_location.archetype_index];
+
+        Ok(archetype
+            .channels
+            .iter_mut()
+            .filter_map(|channel| {
+                let get_reflect_mut = self.registrations.get(&channel.component_id)?;
+                channel.mark_changed(change_tick);
+                Some((
+                    channel.component_id,
+                    get_reflect_mut(
+                        channel.data.as_any_mut(),
+                        entity_location.index_within_archetype,
+                    ),
+                ))
+            })
+            .collect())
+    }
+
+    /// Gets one of an [Entity]'s components and marks it as changed.
+    /// Returns `Ok(None)` if the [Entity] doesn't have the component or its type isn't registered.
+    pub fn component_mut<'a>(
+        &self,
+        world: &'a mut World,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> Result<Option<&'a mut dyn Reflect>, KecsError> {
+        let change_tick = world.change_tick();
+        let entity_location = world
+            .entities
+            .get_entity_location(entity)
+            .ok_or(KecsError::EntityMissing)?;
+        let archetype = &mut world.archetypes[entity_location.archetype_index];
+
+        let get_reflect_mut = match self.registrations.get(&component_id) {
+            Some(get_reflect_mut) => get_reflect_mut,
+            None => return Ok(None),
+        };
+        let channel = match archetype
+            .channels
+            .binary_search_by_key(&component_id, |channel| channel.component_id)
+        {
+            Ok(channel_index) => &mut archetype.channels[channel_index],
+            Err(_) => return Ok(None),
+        };
+        channel.mark_changed(change_tick);
+        Ok(Some(get_reflect_mut(
+            channel.data.as_any_mut(),
+            entity_location.index_within_archetype,
+        )))
+    }
+}
+
+fn get_reflect_mut<T: ComponentTrait + Reflect>(
+    channel: &mut dyn Any,
+    index: usize,
+) -> &mut dyn Reflect {
+    &mut channel
+        .downcast_mut::<RwLock<Vec<T>>>()
+        .unwrap()
+        .get_mut()
+        .unwrap()[index]
+}
@@ -1,308 +1,293 @@
 use std::{
     collections::HashSet,
-    sync::{mpsc::channel, Arc},
+    panic::AssertUnwindSafe,
+    sync::mpsc::{channel, Sender},
 };
 
 use crate::*;
 
-struct SystemBeingScheduled {
-    need_to_wake_up: HashSet<usize>,
-    waiting_on_count: usize,
+/// Runs systems in parallel on `ktasks` workers.
+///
+/// Systems that access the same archetype channel, where at least one of them writes to it,
+/// run in the order they were added. Systems with disjoint access may run at the same time.
+///
+/// Exclusive `&mut World` systems run alone on the calling thread,
+/// after every system added before them has finished.
+/// Systems that access a [ComponentTrait::MAIN_THREAD_ONLY] component also run on the calling thread.
+///
+/// If workers haven't been created with `ktasks::create_workers` everything runs on the calling thread.
+#[derive(Default)]
+pub struct Scheduler {
+    systems: Vec<System>,
 }
 
-struct ResourceAccessGroup {
-    /// If this group of systems requires mutable access to the archetype channel.
-    mutable: bool,
-    /// If this is waiting on a mutable system, which mutable system?
-    waiting_on_index: usize,
-    /// Which systems are in this group.
-    /// For a read operation this may be any number, for a write system this will always be 1 system.
-    systems: Vec<usize>,
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system<PARAMS, S: IntoSystemTrait<PARAMS>>(&mut self, system: S) {
+        self.systems.push(system.system());
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        run_systems_in_parallel(world, &mut self.systems);
+    }
 }
 
-struct SubSchedule {
-    exclusive_system: Option<usize>,
-    systems_being_scheduled: Vec<SystemBeingScheduled>,
-    /// Key is archetype index and channel
-    /// Value is an index into systems
-    resources: HashMap<(usize, usize), ResourceAccessGroup>,
-    last_exclusive_system_index: usize,
-    systems_to_schedule: Vec<usize>,
+struct SystemDependencies {
+    /// Systems that can start once this system finishes.
+    need_to_wake_up: Vec<usize>,
+    waiting_on_count: usize,
+    main_thread: bool,
 }
 
-impl SubSchedule {
-    pub fn new(exclusive_system: Option<usize>) -> Self {
-        Self {
-            exclusive_system,
-            // The first system is the one that starts the whole thing, but it waits on nothing.
-            systems_being_scheduled: vec![SystemBeingScheduled {
-                need_to_wake_up: HashSet::new(),
-                waiting_on_count: 0,
-            }],
-            resources: HashMap::new(),
-            last_exclusive_system_index: 0,
-            systems_to_schedule: Vec::new(),
-        }
-    }
+/// Which systems last accessed an archetype channel.
+#[derive(Default)]
+struct ResourceAccessGroup {
+    writer: Option<usize>,
+    /// Systems that have read the channel since `writer` wrote to it.
+    readers: Vec<usize>,
+}
 
-    /// The `system_index` refers to the index within systems passed in during `schedule`.
-    pub fn add_system(&mut self, system_index: usize) {
-        // Add 1 because there's always a default system.
-        self.systems_to_schedule.push(system_index);
-    }
+/// Builds the dependencies between non-exclusive `systems` for the world's current archetypes.
+fn schedule(world: &World, systems: &[System]) -> Result<Vec<SystemDependencies>, KecsError> {
+    let mut dependencies: Vec<SystemDependencies> = Vec::with_capacity(systems.len());
+    // Key is archetype index and channel
+    let mut resources: HashMap<(usize, usize), ResourceAccessGroup> = HashMap::new();
+
+    for (system_index, system) in systems.iter().enumerate() {
+        let meta_data = match system {
+            System::NonExclusive { meta_data, .. } => meta_data,
+            System::Exclusive(_) => unreachable!(),
+        };
+
+        // A system can access the same channel through multiple parameters.
+        let mut accesses: HashMap<(usize, usize), bool> = HashMap::new();
+        for archetype_access in meta_data(world)? {
+            *accesses
+                .entry((
+                    archetype_access.archetype_index,
+                    archetype_access.channel_index,
+                ))
+                .or_insert(false) |= archetype_access.mutable;
+        }
 
-    pub fn schedule(&mut self, world: &World, systems: &Vec<System>) {
-        for system_index in self.systems_to_schedule.drain(..) {
-            let system = &systems[system_index];
-            let new_system_index = self.systems_being_scheduled.len();
-
-            let mut waiting_on_count = 0;
-
-            match &system.function {
-                SystemFunction::Exclusive(_) => {
-                    // Exclusive systems create a new sub-schedule.
-                    for current_systems in self.resources.values_mut() {
-                        for system_index in &current_systems.systems {
-                            if self.systems_being_scheduled[*system_index]
-                                .need_to_wake_up
-                                .insert(new_system_index)
-                            {
-                                waiting_on_count += 1;
-                            }
-                        }
-                        current_systems.systems.clear();
-                        current_systems.systems.push(new_system_index);
-                        current_systems.mutable = true;
-                    }
-                    self.last_exclusive_system_index = new_system_index;
-                }
-                SystemFunction::NonExclusive { meta_data, .. } => {
-                    let archetype_access = meta_data(world).unwrap();
-                    for archetype_access in archetype_access {
-                        /*
-                        println!(
-                            "ARCHETYPE ACCESS: {:?}",
-                            archetype_access.archetype_index_and_channel
-                        );
-                        println!("MUTABLE: {:?}", archetype_access.mutable);
-                        */
-                        let last_exclusive_system_index = self.last_exclusive_system_index;
-                        let current_systems = self
-                            .resources
-                            .entry((
-                                archetype_access.archetype_index,
-                                archetype_access.channel_index,
-                            ))
-                            .or_insert_with(|| ResourceAccessGroup {
-                                waiting_on_index: 0,
-                                mutable: true,
-                                systems: vec![last_exclusive_system_index],
-                            });
-
-                        if archetype_access.mutable {
-                            for system_index in &current_systems.systems {
-                                // Check that we're not already waiting on this system.
-                                if self.systems_being_scheduled[*system_index]
-                                    .need_to_wake_up
-                                    .insert(new_system_index)
-                                {
-                                    // println!("{:?} will wake {:?}", system_index, new_system_index);
-                                    waiting_on_count += 1;
-                                }
-                            }
-
-                            current_systems.systems.clear();
-                            current_systems.systems.push(new_system_index);
-                            current_systems.mutable = true;
-                        } else {
-                            if current_systems.mutable {
-                                // There should only be one of these.
-                                for system_index in &current_systems.systems {
-                                    // Check that we're not already waiting on this system.
-                                    if self.systems_being_scheduled[*system_index]
-                                        .need_to_wake_up
-                                        .insert(new_system_index)
-                                    {
-                                        // println!("{:?} will wake {:?}", system_index, new_system_index);
-
-                                        waiting_on_count += 1;
-                                        current_systems.waiting_on_index = *system_index;
-                                    }
-                                }
-                                current_systems.systems.clear();
-                                current_systems.systems.push(new_system_index);
-                            } else {
-                                // Check that we're not already waiting on this system.
-                                if self.systems_being_scheduled[current_systems.waiting_on_index]
-                                    .need_to_wake_up
-                                    .insert(new_system_index)
-                                {
-                                    /*
-                                    println!(
-                                        "{:?} will wake {:?}",
-                                        current_systems.waiting_on_index, new_system_index
-                                    );
-                                    */
-
-                                    waiting_on_count += 1;
-                                }
-                                current_systems.systems.push(new_system_index);
-                            }
-                            current_systems.mutable = false;
-                        }
-                    }
-                }
+        let mut waiting_on = HashSet::new();
+        let mut main_thread = false;
+        for ((archetype_index, channel_index), mutable) in accesses {
+            main_thread |=
+                world.archetypes[archetype_index].channels[channel_index].main_thread_only;
+
+            let group = resources
+                .entry((archetype_index, channel_index))
+                .or_default();
+            waiting_on.extend(group.writer);
+            if mutable {
+                waiting_on.extend(group.readers.drain(..));
+                group.writer = Some(system_index);
+            } else {
+                group.readers.push(system_index);
             }
+        }
 
-            self.systems_being_scheduled.push(SystemBeingScheduled {
-                need_to_wake_up: HashSet::new(),
-                waiting_on_count,
-            });
+        for &other_system in &waiting_on {
+            dependencies[other_system]
+                .need_to_wake_up
+                .push(system_index);
         }
+        dependencies.push(SystemDependencies {
+            need_to_wake_up: Vec::new(),
+            waiting_on_count: waiting_on.len(),
+            main_thread,
+        });
     }
+    Ok(dependencies)
+}
 
-    #[cfg(test)]
-    /// Returns schedule groups for verification in testing.
-    fn generate_schedule(&self) -> Vec<Vec<usize>> {
-        let mut schedule = Vec::new();
-
-        let mut counts: Vec<_> = self
-            .systems_being_scheduled
+/// Splits `systems` at exclusive systems and runs the non-exclusive systems between them in parallel.
+pub(crate) fn run_systems_in_parallel(world: &mut World, systems: &mut [System]) {
+    let mut start = 0;
+    while start < systems.len() {
+        let end = systems[start..]
             .iter()
-            .map(|s| Some(s.waiting_on_count))
-            .collect();
-
-        let mut systems_to_run_next: Vec<usize> = vec![0];
-        let mut systems_ran = 0;
-        loop {
-            let mut group = Vec::new();
-
-            for system_to_run in systems_to_run_next.drain(..) {
-                for need_to_wake in self.systems_being_scheduled[system_to_run]
-                    .need_to_wake_up
-                    .iter()
-                {
-                    *counts[*need_to_wake].as_mut().unwrap() -= 1;
-                }
-                systems_ran += 1;
-                counts[system_to_run] = None;
-                group.push(system_to_run);
-            }
-            for (index, count) in counts.iter_mut().enumerate() {
-                if *count == Some(0) {
-                    systems_to_run_next.push(index);
-                }
-            }
+            .position(|system| matches!(system, System::Exclusive(_)))
+            .map_or(systems.len(), |i| start + i);
 
-            schedule.push(group);
-            if systems_ran == counts.len() {
-                break;
-            }
+        if end == start {
+            systems[start].run(world);
+            start += 1;
+            continue;
+        }
+        match &mut systems[start..end] {
+            // Not worth sending to a worker.
+            [system] => system.run(world),
+            non_exclusive_systems => run_sub_schedule(world, non_exclusive_systems).unwrap(),
         }
-        schedule
+        start = end;
     }
 }
 
-pub struct Scheduler {
-    sub_schedules: Vec<SubSchedule>,
-    systems: Vec<System>,
-}
+type SystemResult = std::thread::Result<Result<(), KecsError>>;
 
-impl Scheduler {
-    pub fn new() -> Self {
-        Self {
-            sub_schedules: vec![SubSchedule::new(None)],
-            systems: Vec::new(),
+fn run_non_exclusive(world: &World, system: &mut System) -> SystemResult {
+    match system {
+        System::NonExclusive { system, .. } => {
+            std::panic::catch_unwind(AssertUnwindSafe(|| system(world)))
         }
+        System::Exclusive(_) => unreachable!(),
     }
-    pub fn add_system<PARAMS, S: IntoSystemTrait<PARAMS>>(
-        &mut self,
-        system: S,
-    ) -> Result<(), KecsError> {
-        let system = system.system()?;
-        let system_index = self.systems.len();
-        match &system.function {
-            SystemFunction::Exclusive(_) => {
-                // Todo: Also need to hook up waking up the next schedule somehow
-                self.sub_schedules
-                    .push(SubSchedule::new(Some(system_index)));
-            }
-            SystemFunction::NonExclusive { .. } => {
-                self.sub_schedules
-                    .last_mut()
-                    .unwrap()
-                    .add_system(system_index);
+}
+
+/// # Safety
+/// The caller must wait for the system to finish before `world` or `system` are dropped or borrowed again.
+/// `system` must not be accessed by anything else while it runs.
+unsafe fn spawn_system(
+    world: &World,
+    system: *mut System,
+    system_index: usize,
+    sender: &Sender<(usize, SystemResult)>,
+) {
+    let world: &'static World = &*(world as *const World);
+    let system: &'static mut System = &mut *system;
+    let sender = sender.clone();
+    ktasks::spawn(async move {
+        let result = run_non_exclusive(world, system);
+        let _ = sender.send((system_index, result));
+    })
+    .run();
+}
+
+/// Runs non-exclusive systems, starting each one as soon as the systems it depends on finish.
+fn run_sub_schedule(world: &World, systems: &mut [System]) -> Result<(), KecsError> {
+    let mut dependencies = schedule(world, systems)?;
+    let system_count = systems.len();
+    let systems_pointer = systems.as_mut_ptr();
+    let (sender, receiver) = channel();
+
+    let mut ready: Vec<usize> = (0..system_count)
+        .filter(|i| dependencies[*i].waiting_on_count == 0)
+        .collect();
+    let mut main_thread_ready = Vec::new();
+    let mut running = 0;
+    let mut finished = 0;
+    // The first error or panic. Once one happens no more systems are started.
+    let mut failure: Option<SystemResult> = None;
+
+    while finished < system_count {
+        if failure.is_none() {
+            for system_index in ready.drain(..) {
+                if dependencies[system_index].main_thread {
+                    main_thread_ready.push(system_index);
+                } else {
+                    // Safety: Every spawned system finishes before this function returns
+                    // and each system is only started once.
+                    unsafe {
+                        spawn_system(
+                            world,
+                            systems_pointer.add(system_index),
+                            system_index,
+                            &sender,
+                        )
+                    };
+                    running += 1;
+                }
             }
         }
-        self.systems.push(system);
-
-        Ok(())
-    }
 
-    fn schedule(&mut self, world: &World) {
-        for sub_schedule in &mut self.sub_schedules {
-            sub_schedule.schedule(world, &self.systems)
+        let (system_index, result) = match main_thread_ready.pop() {
+            Some(system_index) if failure.is_none() => {
+                let system = unsafe { &mut *systems_pointer.add(system_index) };
+                (system_index, run_non_exclusive(world, system))
+            }
+            _ if running > 0 => {
+                // Without worker threads the spawned systems run here.
+                ktasks::run_tasks_unless_there_are_workers();
+                running -= 1;
+                receiver.recv().unwrap()
+            }
+            _ => break,
+        };
+
+        finished += 1;
+        match result {
+            Ok(Ok(())) => {
+                for waiting_system in
+                    std::mem::take(&mut dependencies[system_index].need_to_wake_up)
+                {
+                    dependencies[waiting_system].waiting_on_count -= 1;
+                    if dependencies[waiting_system].waiting_on_count == 0 {
+                        ready.push(waiting_system);
+                    }
+                }
+            }
+            result => {
+                failure.get_or_insert(result);
+            }
         }
     }
 
-    pub fn run(&mut self, world: World) -> World {
-        let sub_schedules = std::mem::take(&mut self.sub_schedules);
-        ktasks::spawn(async move {});
-        world
+    match failure {
+        Some(Err(panic)) => std::panic::resume_unwind(panic),
+        Some(Ok(result)) => result,
+        None => Ok(()),
     }
 }
 
-enum SubSchedulerMessage {
-    SystemFinished((usize, System)),
+#[cfg(test)]
+fn wait_counts(world: &World, systems: &[System]) -> Vec<usize> {
+    schedule(world, systems)
+        .unwrap()
+        .iter()
+        .map(|dependencies| dependencies.waiting_on_count)
+        .collect()
 }
 
 #[test]
-fn schedule0() {
+fn schedule_reads_and_writes() {
     struct A;
     impl ComponentTrait for A {}
-    struct B;
-    impl ComponentTrait for B {}
 
     let mut world = World::new();
     world.spawn(A);
 
-    let mut scheduler = Scheduler::new();
-    scheduler.add_system(|_: &A| {}).unwrap();
-    scheduler.add_system(|_: &A| {}).unwrap();
-    scheduler.add_system(|_: &mut A| {}).unwrap();
-    scheduler.add_system(|_: &mut A| {}).unwrap();
-    scheduler.schedule(&world);
-
-    let schedule = scheduler.sub_schedules[0].generate_schedule();
-    println!("{:#?}", schedule);
-    assert!(schedule == vec![vec![0,], vec![1, 2,], vec![3,], vec![4,],]);
+    let systems = vec![
+        (|_: &A| {}).system(),
+        (|_: &A| {}).system(),
+        (|_: &mut A| {}).system(),
+        (|_: &mut A| {}).system(),
+        (|_: &A| {}).system(),
+    ];
+    assert_eq!(wait_counts(&world, &systems), vec![0, 0, 2, 1, 1]);
 }
 
 #[test]
-fn schedule1() {
+fn schedule_archetypes() {
     struct A;
     impl ComponentTrait for A {}
     struct B;
     impl ComponentTrait for B {}
+    struct C;
+    impl ComponentTrait for C {}
 
     let mut world = World::new();
     world.spawn(A);
     world.spawn(B);
     world.spawn((A, B));
-
-    let mut scheduler = Scheduler::new();
-    scheduler.add_system(|_: Query<(&A,)>| {}).unwrap(); // 1
-    scheduler.add_system(|_: Query<(&A, &mut B)>| {}).unwrap(); // 2
-    scheduler.add_system(|_: Query<(&mut B,)>| {}).unwrap(); // 3
-    scheduler.schedule(&world);
-
-    let schedule = scheduler.sub_schedules[0].generate_schedule();
-    println!("{:#?}", schedule);
-    assert!(schedule == vec![vec![0,], vec![1, 2,], vec![3,],])
+    world.spawn(C);
+
+    let systems = vec![
+        (|_: Query<&A>| {}).system(),
+        (|_: Query<(&A, &mut B)>| {}).system(),
+        (|_: Query<&mut B>| {}).system(),
+        (|_: Query<&C>| {}).system(),
+    ];
+    // The third system waits for the second to finish writing to the `B`s that also have an `A`.
+    assert_eq!(wait_counts(&world, &systems), vec![0, 0, 1, 0]);
 }
 
 #[test]
-fn schedule_exclusive0() {
+fn schedule_errors() {
     struct A;
     impl ComponentTrait for A {}
     struct B;
@@ -310,83 +295,78 @@ fn schedule_exclusive0() {
 
     let mut world = World::new();
     world.spawn(A);
-    world.spawn(B);
 
-    let mut scheduler = Scheduler::new();
-    scheduler.add_system(|_: Query<(&A,)>| {}).unwrap(); // 1
-    scheduler.add_system(|_: Query<(&A,)>| {}).unwrap(); // 2
-    scheduler.add_system(|_: &mut World| {}).unwrap(); // 3
-    scheduler.add_system(|_: Query<(&A,)>| {}).unwrap(); // 4
-    scheduler.add_system(|_: Query<(&mut B,)>| {}).unwrap(); // 5
-
-    scheduler.schedule(&world);
-    let schedule0 = scheduler.sub_schedules[0].generate_schedule();
-    let schedule1 = scheduler.sub_schedules[1].generate_schedule();
-
-    println!("{:#?}", schedule0);
-    // This demonstrates how extra parallelization can sometimes be gained
-    // because systems are disjoint within a group.
-    assert!(schedule0 == vec![vec![0,], vec![1, 2,]]);
-    assert!(schedule1 == vec![vec![0,], vec![1, 2]]);
+    let mut systems = vec![(|_: &A| {}).system(), (|_: &B| {}).system()];
+    assert_eq!(
+        run_sub_schedule(&world, &mut systems),
+        Err(KecsError::NoMatchingComponent(std::any::type_name::<B>()))
+    );
 }
 
+// Everything that needs worker threads is in this one test
+// because only the thread that creates the workers can hand them work.
 #[test]
-fn run_schedule0() {
-    struct A;
+fn conflicting_systems_never_overlap() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct A(Vec<usize>);
     impl ComponentTrait for A {}
     struct B;
     impl ComponentTrait for B {}
+    struct C;
+    impl ComponentTrait for C {}
+
+    static ACTIVE_READERS: AtomicUsize = AtomicUsize::new(0);
+    static ACTIVE_WRITERS: AtomicUsize = AtomicUsize::new(0);
+    static ACTIVE_DISJOINT: AtomicUsize = AtomicUsize::new(0);
+    static MOST_ACTIVE_DISJOINT: AtomicUsize = AtomicUsize::new(0);
+
+    fn write(a: &mut A, id: usize) {
+        assert_eq!(ACTIVE_WRITERS.fetch_add(1, Ordering::SeqCst), 0);
+        assert_eq!(ACTIVE_READERS.load(Ordering::SeqCst), 0);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        a.0.push(id);
+        ACTIVE_WRITERS.fetch_sub(1, Ordering::SeqCst);
+    }
 
-    let mut world = World::new();
-    world.spawn(A);
+    fn read() {
+        ACTIVE_READERS.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(ACTIVE_WRITERS.load(Ordering::SeqCst), 0);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        ACTIVE_READERS.fetch_sub(1, Ordering::SeqCst);
+    }
 
-    let mut scheduler = Scheduler::new();
-    scheduler.add_system(|_: &A| {}).unwrap();
-    scheduler.add_system(|_: &A| {}).unwrap();
-    scheduler.add_system(|_: &mut A| {}).unwrap();
-    scheduler.add_system(|_: &mut A| {}).unwrap();
-    scheduler.schedule(&world);
+    fn disjoint() {
+        let active = ACTIVE_DISJOINT.fetch_add(1, Ordering::SeqCst) + 1;
+        MOST_ACTIVE_DISJOINT.fetch_max(active, Ordering::SeqCst);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        ACTIVE_DISJOINT.fetch_sub(1, Ordering::SeqCst);
+    }
 
-    ktasks::create_workers(3);
-}
+    ktasks::create_workers_with_count(4);
 
-/*
+    let mut world = World::new();
+    world.spawn(A(Vec::new()));
+    world.spawn(B);
+    world.spawn(C);
 
-for mut sub_schedule in &mut self.sub_schedules {
-    if let Some(exclusive_system) = sub_schedule.exclusive_system {
-        match exclusive_system.function {
-            SystemFunction::Exclusive(mut system) => system(self.world).unwrap(),
-            _ => unreachable!(),
-        }
+    let mut scheduler = Scheduler::new();
+    scheduler.add_system(|_: &B| disjoint());
+    scheduler.add_system(|_: &C| disjoint());
+    scheduler.add_system(|a: &mut A| write(a, 0));
+    scheduler.add_system(|_: &A| read());
+    scheduler.add_system(|_: &A| read());
+    scheduler.add_system(|a: &mut A| write(a, 1));
+    scheduler.add_system(|world: &mut World| world.get_singleton::<A>().0.push(2));
+    scheduler.add_system(|_: &A| read());
+    scheduler.add_system(|a: &mut A| write(a, 3));
+    scheduler.add_system(|_: &A| read());
+
+    for _ in 0..10 {
+        world.get_singleton::<A>().0.clear();
+        scheduler.run(&mut world);
+        assert_eq!(world.get_singleton::<A>().0, vec![0, 1, 2, 3]);
     }
-    sub_schedule.schedule();
-    let (sender, receiver) = channel::<SchedulerMessage>();
-
-    let join_handle = std::thread::spawn(move || {
-        while let Ok(message) = receiver.recv() {
-            match message {
-                SchedulerMessage::SystemDone(system) => {
-                    let mut need_to_wake_up = HashSet::new();
-                    std::mem::swap(
-                        &mut need_to_wake_up,
-                        &mut sub_schedule.systems[system].need_to_wake_up,
-                    );
-                    for waiting_system in need_to_wake_up {
-                        sub_schedule.systems[waiting_system].waiting_on_count -= 1;
-                        if sub_schedule.systems[waiting_system].waiting_on_count == 0 {
-                            // Schedule this as a new task.
-                            let sender = sender.clone();
-                            ktasks::spawn(async move {
-                                println!("RUNNING TASK: {:?}", waiting_system);
-                                sender.send(SchedulerMessage::SystemDone(waiting_system))
-                            });
-                        }
-                    }
-                }
-            }
-        }
-    });
-
-    join_handle.join().unwrap();
+    // Systems with disjoint access ran at the same time.
+    assert_eq!(MOST_ACTIVE_DISJOINT.load(Ordering::SeqCst), 2);
 }
-*/
@@ -0,0 +1,280 @@
+use crate::*;
+use kserde::*;
+use std::sync::RwLock;
+
+pub type WorldSerializer<'w> = JSONSerializer<WorldSerializeContext<'w>>;
+pub type WorldDeserializer<'a, 'w> = JSONDeserializer<'a, WorldDeserializeContext<'w>>;
+
+/// Passed to components as they're serialized by [World::serialize].
+pub struct WorldSerializeContext<'w> {
+    /// Each [Entity] is written as its position in the serialized [World].
+    entity_indices: HashMap<Entity, usize>,
+    /// Where singletons, like asset storage, are looked up.
+    pub resources: &'w World,
+}
+
+/// Passed to components as they're deserialized by [World::deserialize].
+pub struct WorldDeserializeContext<'w> {
+    entity_migrator: EntityMigrator,
+    /// Where singletons, like asset storage, are looked up.
+    pub resources: &'w mut World,
+}
+
+#[derive(Clone)]
+struct ComponentRegistration {
+    name: &'static str,
+    serialize: fn(&dyn Any, usize, &mut WorldSerializer),
+    deserialize: fn(&mut WorldDeserializer, &mut World, Entity) -> Option<()>,
+}
+
+/// The component types that [World::serialize] writes and [World::deserialize] reads.
+/// Components that aren't registered are skipped when serializing.
+///
+/// Each type is registered with a name that identifies it in the serialized data,
+/// so the name should stay the same if the type is renamed or moved.
+#[derive(Clone)]
+pub struct ComponentRegistry {
+    registrations: HashMap<ComponentId, ComponentRegistration>,
+    names: HashMap<&'static str, ComponentId>,
+}
+
+impl ComponentTrait for ComponentRegistry {}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComponentRegistry {
+    /// Creates a [ComponentRegistry] with [HierarchyNode] already registered.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            registrations: HashMap::new(),
+            names: HashMap::new(),
+        };
+        registry.register::<hierarchy::HierarchyNode>("HierarchyNode");
+        registry
+    }
+
+    /// Registers `T` to be serialized as `name`.
+    /// Registering a type again replaces its name.
+    pub fn register<T>(&mut self, name: &'static str)
+    where
+        T: ComponentTrait
+            + for<'w> Serialize<WorldSerializer<'w>>
+            + for<'a, 'w> Deserialize<'a, WorldDeserializer<'a, 'w>>,
+    {
+        let component_id = get_component_id::<T>();
+        if let Some(old_registration) = self.registrations.insert(
+            component_id,
+            ComponentRegistration {
+                name,
+                serialize: serialize_component::<T>,
+                deserialize: deserialize_component::<T>,
+            },
+        ) {
+            self.names.remove(old_registration.name);
+        }
+        self.names.insert(name, component_id);
+    }
+}
+
+fn serialize_component<T: ComponentTrait + for<'w> Serialize<WorldSerializer<'w>>>(
+    channel: &dyn Any,
+    index: usize,
+    serializer: &mut WorldSerializer,
+) {
+    let channel = channel
+        .downcast_ref::<RwLock<Vec<T>>>()
+        .unwrap()
+        .read()
+        .unwrap();
+    channel[index].serialize(serializer)
+}
+
+fn deserialize_component<
+    T: ComponentTrait + for<'a, 'w> Deserialize<'a, WorldDeserializer<'a, 'w>>,
+>(
+    deserializer: &mut WorldDeserializer,
+    world: &mut World,
+    entity: Entity,
+) -> Option<()> {
+    let component = T::deserialize(deserializer)?;
+    world.add_component(entity, component).ok()
+}
+
+struct SerializedComponent<'a> {
+    channel: &'a ArchetypeChannel,
+    index_within_archetype: usize,
+    serialize: fn(&dyn Any, usize, &mut WorldSerializer),
+}
+
+impl<'w> Serialize<WorldSerializer<'w>> for SerializedComponent<'_> {
+    fn serialize(&self, serializer: &mut WorldSerializer<'w>) {
+        (self.serialize)(
+            self.channel.data.as_any(),
+            self.index_within_archetype,
+            serializer,
+        )
+    }
+}
+
+struct SerializedEntity<'a> {
+    archetype: &'a Archetype,
+    index_within_archetype: usize,
+    registry: &'a ComponentRegistry,
+}
+
+impl<'w> Serialize<WorldSerializer<'w>> for SerializedEntity<'_> {
+    fn serialize(&self, serializer: &mut WorldSerializer<'w>) {
+        serializer.begin_object();
+        for channel in &self.archetype.channels {
+            if let Some(registration) = self.registry.registrations.get(&channel.component_id) {
+                serializer.property(registration.name);
+                serializer.value(&SerializedComponent {
+                    channel,
+                    index_within_archetype: self.index_within_archetype,
+                    serialize: registration.serialize,
+                });
+            }
+        }
+        serializer.end_object();
+    }
+}
+
+struct SerializedEntities<'a> {
+    world: &'a World,
+    registry: &'a ComponentRegistry,
+}
+
+impl<'w> Serialize<WorldSerializer<'w>> for SerializedEntities<'_> {
+    fn serialize(&self, serializer: &mut WorldSerializer<'w>) {
+        serializer.begin_array();
+        for archetype in &self.world.archetypes {
+            for index_within_archetype in 0..archetype.entities.len() {
+                serializer.value(&SerializedEntity {
+                    archetype,
+                    index_within_archetype,
+                    registry: self.registry,
+                });
+            }
+        }
+        serializer.end_array();
+    }
+}
+
+impl<'w> Serialize<WorldSerializer<'w>> for Entity {
+    /// An [Entity] that isn't in the [World] being serialized is written as `null`,
+    /// which will fail to deserialize.
+    fn serialize(&self, serializer: &mut WorldSerializer<'w>) {
+        match serializer.get_context().entity_indices.get(self) {
+            Some(index) => serializer.i64(*index as i64),
+            None => serializer.null(),
+        }
+    }
+}
+
+impl<'a, 'w> Deserialize<'a, WorldDeserializer<'a, 'w>> for Entity {
+    fn deserialize(deserializer: &mut WorldDeserializer<'a, 'w>) -> Option<Self> {
+        let index = deserializer.i64()?;
+        Some(
+            deserializer
+                .get_context_mut()
+                .entity_migrator
+                .migrate(Entity {
+                    index: index as u32,
+                    generation: 0,
+                }),
+        )
+    }
+}
+
+/// Deserializes an [Entity] that may be `null`.
+pub fn deserialize_optional_entity(deserializer: &mut WorldDeserializer) -> Option<Option<Entity>> {
+    match deserializer.any()? {
+        AnyValue::Number(index) => Some(Some(
+            deserializer
+                .get_context_mut()
+                .entity_migrator
+                .migrate(Entity {
+                    index: index as u32,
+                    generation: 0,
+                }),
+        )),
+        AnyValue::Null => Some(None),
+        _ => None,
+    }
+}
+
+impl World {
+    /// Serializes the components registered with `registry` to JSON.
+    /// `resources` is where components look up singletons they need, like asset storage.
+    /// It can be this [World].
+    ///
+    /// [Entity]s are written in an order that [World::deserialize] can remap them from
+    /// so components that refer to other [Entity]s, like [HierarchyNode](hierarchy::HierarchyNode), stay connected.
+    pub fn serialize(&self, registry: &ComponentRegistry, resources: &World) -> String {
+        let entity_indices = self
+            .archetypes
+            .iter()
+            .flat_map(|archetype| archetype.entities.iter())
+            .enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect();
+
+        let mut serializer = JSONSerializer::new_with_context(WorldSerializeContext {
+            entity_indices,
+            resources,
+        });
+        serializer.begin_object();
+        serializer.property("entities");
+        serializer.value(&SerializedEntities {
+            world: self,
+            registry,
+        });
+        serializer.end_object();
+        serializer.done()
+    }
+
+    /// Creates a new [World] from JSON written by [World::serialize].
+    /// `resources` is where components look up singletons they need, like asset storage.
+    /// Returns `None` if the JSON is malformed or names a component that isn't in `registry`.
+    pub fn deserialize(
+        source: &str,
+        registry: &ComponentRegistry,
+        resources: &mut World,
+    ) -> Option<World> {
+        let mut world = World::new();
+
+        // A new [World] spawns [Entity]s with increasing indices starting at 0,
+        // so the nth serialized [Entity] is spawned at index `n`.
+        let mut deserializer = JSONDeserializer::new_with_context(
+            source,
+            WorldDeserializeContext {
+                entity_migrator: EntityMigrator::new(&[], 0),
+                resources,
+            },
+        );
+
+        deserializer.begin_object().then_some(())?;
+        while let Some(property) = deserializer.has_property() {
+            match &*property {
+                "entities" => {
+                    deserializer.begin_array().then_some(())?;
+                    while deserializer.has_array_value() {
+                        let entity = world.spawn(());
+                        deserializer.begin_object().then_some(())?;
+                        while let Some(name) = deserializer.has_property() {
+                            let component_id = registry.names.get(&*name)?;
+                            let registration = &registry.registrations[component_id];
+                            (registration.deserialize)(&mut deserializer, &mut world, entity)?;
+                        }
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(world)
+    }
+}
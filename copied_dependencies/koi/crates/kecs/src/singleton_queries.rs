@@ -3,7 +3,10 @@ use std::sync::RwLockWriteGuard;
 
 // Get an arbitrary instance of T from the [World].
 impl<T: ComponentTrait> SystemParameterTrait for &T {
-    fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError> {
+    fn get_meta_data(
+        world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError> {
         let mut archetypes = Vec::new();
         let mut channels = Vec::new();
 
@@ -21,6 +24,7 @@ impl<T: ComponentTrait> SystemParameterTrait for &T {
         Ok(SystemParameterMetaData {
             archetypes,
             channels,
+            last_run_tick,
         })
     }
 }
@@ -53,7 +57,10 @@ impl<'b, T: ComponentTrait> AsSystemArg<'b> for RwLockReadGuard<'_, Vec<T>> {
 }
 
 impl<T: ComponentTrait> SystemParameterTrait for &mut T {
-    fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError> {
+    fn get_meta_data(
+        world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError> {
         let mut archetypes = Vec::new();
         let mut channels = Vec::new();
 
@@ -71,6 +78,7 @@ impl<T: ComponentTrait> SystemParameterTrait for &mut T {
         Ok(SystemParameterMetaData {
             archetypes,
             channels,
+            last_run_tick,
         })
     }
 }
@@ -144,7 +152,7 @@ impl<A: ComponentTrait> SingletonQuery for &mut A {
 
 /*
 impl<A: SingletonQuery, B: SingletonQuery> SystemParameterTrait for (A, B) {
-    fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError> {
+    fn get_meta_data(world: &World, last_run_tick: u64) -> Result<SystemParameterMetaData, KecsError> {
         let mut archetypes = Vec::new();
         let mut channels = Vec::new();
 
@@ -172,6 +180,7 @@ impl<A: SingletonQuery, B: SingletonQuery> SystemParameterTrait for (A, B) {
         Ok(SystemParameterMetaData {
             archetypes,
             channels,
+            last_run_tick,
         })
     }
 }
@@ -221,7 +230,7 @@ macro_rules! singleton_impls {
     ( $count: tt, $( ($index: tt, $tuple:ident) ),* ) => {
         impl<$( $tuple: SingletonQuery,)*> SystemParameterTrait for ($( $tuple,)*)  {
             #[allow(unused)]
-            fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError> {
+            fn get_meta_data(world: &World, last_run_tick: u64) -> Result<SystemParameterMetaData, KecsError> {
                 let mut archetypes = Vec::new();
                 let mut channels = Vec::new();
 
@@ -242,6 +251,7 @@ macro_rules! singleton_impls {
                 Ok(SystemParameterMetaData {
                     archetypes,
                     channels,
+                    last_run_tick,
                 })
             }
         }
@@ -9,9 +9,30 @@ pub struct ArchetypeAccess {
 pub struct SystemParameterMetaData {
     pub archetypes: Vec<usize>,
     pub channels: Vec<Option<(usize, bool)>>,
+    /// The tick the system last ran at. Changes made after this are new to the system.
+    pub last_run_tick: u64,
 }
 
 impl SystemParameterMetaData {
+    /// Marks every channel this parameter borrows mutably as changed at `tick`.
+    pub fn mark_changed(&self, world: &World, tick: u64) {
+        if !self.archetypes.is_empty() {
+            let channel_count = self.channels.len() / self.archetypes.len();
+            for (archetype_index, channels) in self
+                .archetypes
+                .iter()
+                .zip(self.channels.chunks_exact(channel_count))
+            {
+                for (channel_index, mutable) in channels.iter().flatten() {
+                    if *mutable {
+                        world.archetypes[*archetype_index].channels[*channel_index]
+                            .mark_changed(tick);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn append_meta_data(&self, archetype_access: &mut Vec<ArchetypeAccess>) {
         if !self.archetypes.is_empty() {
             let channel_count = self.channels.len() / self.archetypes.len();
@@ -36,9 +57,32 @@ pub trait IntoSystemTrait<PARAMETERS> {
     fn system(self) -> System;
 }
 
+/// The ticks a system uses to decide which changes it hasn't seen yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SystemTicks {
+    /// The tick the system last ran at, or 0 if it hasn't run before.
+    pub last_run: u64,
+    /// The tick the system is running at now. Its mutable borrows are marked with this tick.
+    pub this_run: u64,
+}
+
 pub trait RunSystemTrait<'return_lifetime, PARAMETERS, RETURN: 'return_lifetime> {
-    fn try_run(self, world: &'return_lifetime World) -> Result<RETURN, KecsError>;
-    // fn run(self, world: &'return_lifetime World) -> RETURN;
+    fn try_run_with_ticks(
+        self,
+        world: &'return_lifetime World,
+        ticks: SystemTicks,
+    ) -> Result<RETURN, KecsError>;
+
+    /// Filters like [Changed] only match changes made since this was last called
+    /// from the same place in the code.
+    #[track_caller]
+    fn try_run(self, world: &'return_lifetime World) -> Result<RETURN, KecsError>
+    where
+        Self: Sized,
+    {
+        let ticks = world.call_site_ticks(std::panic::Location::caller());
+        self.try_run_with_ticks(world, ticks)
+    }
 
     #[track_caller]
     fn run(self, world: &'return_lifetime World) -> RETURN
@@ -77,7 +121,7 @@ macro_rules! system_tuple_impls {
             FnMut( $( <<$tuple as SystemParameterFetchTrait<'return_lifetime>>::FetchResult as AsSystemArg>::Arg ),*) -> RETURN,
         {
             #[allow(non_snake_case, unused_variables, clippy::too_many_arguments)]
-            fn try_run(mut self, world: &'return_lifetime World) -> Result<RETURN, KecsError> {
+            fn try_run_with_ticks(mut self, world: &'return_lifetime World, ticks: SystemTicks) -> Result<RETURN, KecsError> {
                 #[allow(clippy::too_many_arguments)]
                 fn call_inner<$($tuple,)* RETURN>(
                     mut f: impl FnMut($($tuple,)*) -> RETURN,
@@ -86,7 +130,8 @@ macro_rules! system_tuple_impls {
                     f($($tuple,)*)
                 }
 
-                $(let $tuple = $tuple::get_meta_data(world)?;)*
+                $(let $tuple = $tuple::get_meta_data(world, ticks.last_run)?;)*
+                $($tuple.mark_changed(world, ticks.this_run);)*
                 $(let mut $tuple = <$tuple as SystemParameterFetchTrait<'return_lifetime>>::fetch(world, &$tuple)?;)*
                 $(let $tuple = $tuple.as_system_arg();)*
                 let result = call_inner(&mut self, $( $tuple ),*);
@@ -112,12 +157,16 @@ macro_rules! system_tuple_impls {
                     f($($tuple,)*)
                 }
 
+                let mut last_run_tick = 0;
                 System::NonExclusive{
                     system: Box::new(
                         move |world: &World| {
+                            let this_run_tick = world.increment_change_tick();
                             let mut archetype_access = Vec::new();
-                            $(let $tuple = $tuple::get_meta_data(world)?;)*
+                            $(let $tuple = $tuple::get_meta_data(world, last_run_tick)?;)*
                             $($tuple.append_meta_data(&mut archetype_access);)*
+                            $($tuple.mark_changed(world, this_run_tick);)*
+                            last_run_tick = this_run_tick;
                             $(let mut $tuple = <$tuple as SystemParameterFetchTrait>::fetch(world, &$tuple)?;)*
                             $(let $tuple = $tuple.as_system_arg();)*
                             call_inner(&mut self, $( $tuple ),*);
@@ -125,7 +174,8 @@ macro_rules! system_tuple_impls {
                     }),
                     meta_data: Box::new( |world: &World| {
                         let mut archetype_access = Vec::new();
-                        $(let $tuple = $tuple::get_meta_data(world)?;)*
+                        // Schedule around every archetype the system could access, changed or not.
+                        $(let $tuple = $tuple::get_meta_data(world, 0)?;)*
                         $($tuple.append_meta_data(&mut archetype_access);)*
                         Ok(archetype_access)
                     })
@@ -157,7 +207,11 @@ impl System {
 }
 
 pub trait SystemParameterTrait: for<'a> SystemParameterFetchTrait<'a> {
-    fn get_meta_data(world: &World) -> Result<SystemParameterMetaData, KecsError>;
+    /// Archetypes that don't match change filters like [Changed] since `last_run_tick` are left out.
+    fn get_meta_data(
+        world: &World,
+        last_run_tick: u64,
+    ) -> Result<SystemParameterMetaData, KecsError>;
 }
 
 pub trait SystemParameterFetchTrait<'a> {
@@ -182,8 +236,12 @@ where
 }
 
 /// Run a number of systems.
-/// In the future this could be extended to run systems in parallel, but for now it does not.
+/// With the `scheduler` feature systems with disjoint access run in parallel. See [Scheduler].
 pub fn run_systems(world: &mut World, systems: &mut [System]) {
+    #[cfg(feature = "scheduler")]
+    scheduler::run_systems_in_parallel(world, systems);
+
+    #[cfg(not(feature = "scheduler"))]
     for system in systems {
         system.run(world);
     }
@@ -434,3 +434,212 @@ fn despawn_and_add_world() {
     world.despawn(entity_a).unwrap();
     world.add_world(&mut world_b);
 }
+
+#[test]
+fn changed_filter() {
+    fn count_changed(world: &World) -> usize {
+        (|query: Query<&A, Changed<A>>| query.iter().count()).run(world)
+    }
+
+    let mut world = World::new();
+    let entity = world.spawn(A);
+    world.spawn((A, B));
+    assert_eq!(count_changed(&world), 2);
+    assert_eq!(count_changed(&world), 0);
+
+    let _ = world.get_component_mut::<A>(entity);
+    assert_eq!(count_changed(&world), 1);
+
+    (|_: Query<&mut A, With<B>>| {}).run(&world);
+    assert_eq!(count_changed(&world), 1);
+    assert_eq!(count_changed(&world), 0);
+}
+
+#[test]
+fn changed_filter_ignores_own_changes() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static CHANGED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut world = World::new();
+    let entity = world.spawn(A);
+
+    let mut system = (|query: Query<&mut A, Changed<A>>| {
+        CHANGED.store(query.iter().count(), Ordering::SeqCst);
+    })
+    .system();
+    system.run(&mut world);
+    assert_eq!(CHANGED.load(Ordering::SeqCst), 1);
+
+    // The system's own mutable borrow isn't a change it hasn't seen.
+    system.run(&mut world);
+    assert_eq!(CHANGED.load(Ordering::SeqCst), 0);
+
+    let _ = world.get_component_mut::<A>(entity);
+    system.run(&mut world);
+    assert_eq!(CHANGED.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn added_filter() {
+    fn count_added(world: &World) -> usize {
+        (|query: Query<&A, Added<A>>| query.iter().count()).run(world)
+    }
+
+    let mut world = World::new();
+    let entity = world.spawn(A);
+    assert_eq!(count_added(&world), 1);
+    assert_eq!(count_added(&world), 0);
+
+    let _ = world.get_component_mut::<A>(entity);
+    assert_eq!(count_added(&world), 0);
+
+    world.spawn((A, B));
+    assert_eq!(count_added(&world), 1);
+}
+
+#[test]
+fn removed_components() {
+    fn removed(world: &World) -> Vec<Entity> {
+        (|removed: RemovedComponents<B>| removed.iter().copied().collect()).run(world)
+    }
+
+    let mut world = World::new();
+    let entity_a = world.spawn((A, B));
+    let entity_b = world.spawn((A, B));
+    assert!(removed(&world).is_empty());
+
+    world.remove_component::<B>(entity_a).unwrap();
+    assert_eq!(removed(&world), vec![entity_a]);
+    assert!(removed(&world).is_empty());
+
+    world.despawn(entity_b).unwrap();
+    assert_eq!(removed(&world), vec![entity_b]);
+
+    // Removals are kept until the second call to `clear_trackers`.
+    world.clear_trackers();
+    assert_eq!(
+        (|removed: RemovedComponents<B>| removed.len()).run(&world),
+        2
+    );
+    world.clear_trackers();
+    assert!((|removed: RemovedComponents<B>| removed.is_empty()).run(&world));
+}
+
+#[test]
+fn events_are_read_once() {
+    fn read(world: &World) -> Vec<u32> {
+        (|reader: EventReader<u32>| reader.iter().copied().collect()).run(world)
+    }
+
+    let mut world = World::new();
+    world.add_events::<u32>();
+    (|mut writer: EventWriter<u32>| writer.send(1)).run(&world);
+    assert_eq!(read(&world), vec![1]);
+    assert!(read(&world).is_empty());
+
+    (|mut writer: EventWriter<u32>| {
+        writer.send(2);
+        writer.send(3);
+    })
+    .run(&world);
+    world.send_event(4_u32);
+    assert_eq!(read(&world), vec![2, 3, 4]);
+}
+
+#[test]
+fn events_are_dropped_after_two_clears() {
+    let mut world = World::new();
+    world.add_events::<u32>();
+    world.send_event(1_u32);
+
+    world.clear_trackers();
+    world.send_event(2_u32);
+    assert_eq!(
+        (|reader: EventReader<u32>| reader.iter().copied().collect::<Vec<_>>()).run(&world),
+        vec![1, 2]
+    );
+
+    world.clear_trackers();
+    assert_eq!(
+        (|reader: EventReader<u32>| reader.iter().copied().collect::<Vec<_>>()).run(&world),
+        vec![2]
+    );
+}
+
+#[test]
+fn serialize_world() {
+    use hierarchy::HierarchyNode;
+
+    #[derive(Clone, SerdeComponent, Debug, PartialEq)]
+    struct Health {
+        amount: i32,
+    }
+
+    let mut world = World::new();
+    let despawned = world.spawn(A);
+    let parent = world.spawn(Health { amount: 3 });
+    let child = world.spawn((A, Health { amount: 5 }));
+    world.despawn(despawned).unwrap();
+    HierarchyNode::set_parent(&mut world, Some(parent), child).unwrap();
+
+    let mut registry = ComponentRegistry::new();
+    registry.register::<Health>("Health");
+    let json = world.serialize(&registry, &world);
+    let new_world = World::deserialize(&json, &registry, &mut World::new()).unwrap();
+    assert_eq!(new_world.len(), 2);
+
+    // `A` isn't registered so it's left out.
+    (|a: Query<&A>| assert_eq!(a.iter().count(), 0)).run(&new_world);
+    (|query: Query<(&Health, &HierarchyNode)>| {
+        let (child_health, child_node) = query
+            .iter()
+            .find(|(_, node)| node.parent().is_some())
+            .unwrap();
+        assert_eq!(child_health, &Health { amount: 5 });
+        let (parent_health, parent_node) = query
+            .get_entity_components(child_node.parent().unwrap())
+            .unwrap();
+        assert_eq!(parent_health, &Health { amount: 3 });
+        assert!(parent_node.last_child().is_some());
+    })
+    .run(&new_world);
+}
+
+#[test]
+fn reflect_components() {
+    use kreflect::{Reflect, ReflectValue};
+
+    #[derive(Clone, Component, Reflect)]
+    struct Health {
+        amount: i32,
+        #[reflect(skip)]
+        _regenerating: bool,
+    }
+
+    let mut world = World::new();
+    let entity = world.spawn((
+        A,
+        Health {
+            amount: 3,
+            _regenerating: false,
+        },
+    ));
+    let mut registry = ReflectRegistry::new();
+    registry.register::<Health>();
+
+    // `A` isn't registered so it's left out.
+    let mut components = registry.components_mut(&mut world, entity).unwrap();
+    assert_eq!(components.len(), 1);
+    let (component_id, health) = components.pop().unwrap();
+    assert_eq!(health.type_name(), "Health");
+    assert_eq!(health.fields().len(), 1);
+    assert_eq!(health.get_field("amount"), Some(ReflectValue::Int(3)));
+    health.set_field("amount", ReflectValue::Int(5)).unwrap();
+    assert_eq!(world.get_component_mut::<Health>(entity).unwrap().amount, 5);
+
+    let health = registry
+        .component_mut(&mut world, entity, component_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(health.get_field("amount"), Some(ReflectValue::Int(5)));
+}
@@ -2,7 +2,11 @@ use crate::storage_lookup::*;
 use crate::*;
 use std::{
     any::Any,
-    sync::{RwLock, RwLockWriteGuard},
+    panic::Location,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, RwLock, RwLockWriteGuard,
+    },
 };
 
 pub(crate) trait ComponentChannelVecTrait: Send + Sync {
@@ -91,6 +95,12 @@ impl<T: ComponentTrait> ComponentChannelVecTrait for RwLock<Vec<T>> {
 pub(crate) struct ArchetypeChannel {
     pub(crate) component_id: ComponentId,
     pub(crate) data: Box<dyn ComponentChannelVecTrait>,
+    /// See [ComponentTrait::MAIN_THREAD_ONLY]
+    pub(crate) main_thread_only: bool,
+    /// The last tick this channel was mutably borrowed or had components added.
+    pub(crate) changed_tick: AtomicU64,
+    /// The last tick a component was added to this channel.
+    pub(crate) added_tick: AtomicU64,
 }
 
 impl ArchetypeChannel {
@@ -99,6 +109,9 @@ impl ArchetypeChannel {
             component_id: ComponentId(TypeId::of::<Component>()),
             data: Box::new(RwLock::new(Vec::<Component>::with_capacity(1)))
                 as Box<dyn ComponentChannelVecTrait>,
+            main_thread_only: Component::MAIN_THREAD_ONLY,
+            changed_tick: AtomicU64::new(0),
+            added_tick: AtomicU64::new(0),
         }
     }
 
@@ -106,6 +119,9 @@ impl ArchetypeChannel {
         Self {
             component_id: self.component_id,
             data: self.data.new_same_type(),
+            main_thread_only: self.main_thread_only,
+            changed_tick: AtomicU64::new(0),
+            added_tick: AtomicU64::new(0),
         }
     }
 
@@ -113,9 +129,29 @@ impl ArchetypeChannel {
         Some(Self {
             component_id: self.component_id,
             data: self.data.clone_channel(entity_migrator)?,
+            main_thread_only: self.main_thread_only,
+            changed_tick: AtomicU64::new(0),
+            added_tick: AtomicU64::new(0),
         })
     }
 
+    pub(crate) fn mark_changed(&self, tick: u64) {
+        self.changed_tick.fetch_max(tick, Ordering::Relaxed);
+    }
+
+    pub(crate) fn mark_added(&self, tick: u64) {
+        self.added_tick.fetch_max(tick, Ordering::Relaxed);
+        self.mark_changed(tick);
+    }
+
+    /// Components migrating from `other` keep the changes made to them.
+    fn inherit_ticks(&mut self, other: &ArchetypeChannel) {
+        let changed_tick = self.changed_tick.get_mut();
+        *changed_tick = (*changed_tick).max(other.changed_tick.load(Ordering::Relaxed));
+        let added_tick = self.added_tick.get_mut();
+        *added_tick = (*added_tick).max(other.added_tick.load(Ordering::Relaxed));
+    }
+
     pub(crate) fn as_mut_vec<T: 'static>(&mut self) -> &mut Vec<T> {
         self.data
             .as_any_mut()
@@ -156,6 +192,13 @@ impl Archetype {
             .map_err(|_| KecsError::ChannelExclusivelyLocked)
     }
 
+    pub(crate) fn get_channel(&self, component_id: ComponentId) -> Option<&ArchetypeChannel> {
+        self.channels
+            .binary_search_by_key(&component_id, |channel| channel.component_id)
+            .ok()
+            .map(|channel_index| &self.channels[channel_index])
+    }
+
     pub(crate) fn get_write_channel<T: 'static>(
         &self,
         channel_index: usize,
@@ -176,6 +219,16 @@ pub struct World {
     pub(crate) components_ids_to_archetype_index: HashMap<Vec<ComponentId>, usize>,
     pub(crate) storage_lookup: StorageLookup,
     pub(crate) entities: Entities,
+    /// Incremented every time a system runs. See [World::change_tick].
+    change_tick: AtomicU64,
+    /// The [Entity]s that had a component removed and the tick it was removed at.
+    removed_components: HashMap<ComponentId, Vec<(Entity, u64)>>,
+    /// The tick [World::clear_trackers] was last called at.
+    last_clear_tick: u64,
+    /// The tick each call site of [RunSystemTrait::run] last ran at.
+    call_site_ticks: Mutex<HashMap<&'static Location<'static>, u64>>,
+    /// Swap the buffers of each [Events] added with [World::add_events].
+    event_updaters: Vec<fn(&mut World)>,
 }
 
 struct RemoveInfo {
@@ -197,6 +250,11 @@ impl World {
             components_ids_to_archetype_index: HashMap::new(),
             storage_lookup: StorageLookup::new(),
             entities: Entities::new(),
+            change_tick: AtomicU64::new(1),
+            removed_components: HashMap::new(),
+            last_clear_tick: 0,
+            call_site_ticks: Mutex::new(HashMap::new()),
+            event_updaters: Vec::new(),
         };
 
         // Insert the empty [Archetype]
@@ -217,6 +275,77 @@ impl World {
         self.entities.len()
     }
 
+    /// The tick that changes made directly through the [World] are marked with.
+    /// Systems see a change if it was made at or after the tick they started running at
+    /// and before the next time they run.
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    /// Returns the tick for a system that's about to run and advances the [World]'s tick.
+    pub(crate) fn increment_change_tick(&self) -> u64 {
+        self.change_tick.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Ticks for a system run by [RunSystemTrait::run] from `location`.
+    /// Running from the same place again only sees changes made since the last run.
+    pub(crate) fn call_site_ticks(&self, location: &'static Location<'static>) -> SystemTicks {
+        let this_run = self.increment_change_tick();
+        let last_run = self
+            .call_site_ticks
+            .lock()
+            .unwrap()
+            .insert(location, this_run)
+            .unwrap_or(0);
+        SystemTicks { last_run, this_run }
+    }
+
+    /// The [Entity]s that had a `Component` removed, or were despawned with it, after `last_run_tick`.
+    pub(crate) fn removed_since(
+        &self,
+        component_id: ComponentId,
+        last_run_tick: u64,
+    ) -> Vec<Entity> {
+        self.removed_components
+            .get(&component_id)
+            .map_or_else(Vec::new, |removed| {
+                removed
+                    .iter()
+                    .filter(|(_, tick)| *tick > last_run_tick)
+                    .map(|(entity, _)| *entity)
+                    .collect()
+            })
+    }
+
+    /// Forgets removed components and [Events] that were recorded before the previous call to [World::clear_trackers].
+    /// Call this once per frame so every system gets a chance to see them before they're forgotten.
+    pub fn clear_trackers(&mut self) {
+        let last_clear_tick = self.last_clear_tick;
+        for removed in self.removed_components.values_mut() {
+            removed.retain(|(_, tick)| *tick >= last_clear_tick);
+        }
+        for update_events in self.event_updaters.clone() {
+            update_events(self);
+        }
+        self.last_clear_tick = self.change_tick();
+    }
+
+    /// Spawns an [Events] channel for `T` if there isn't one already.
+    /// Its events are dropped by the second [World::clear_trackers] after they're sent.
+    pub fn add_events<T: Send + Sync + 'static>(&mut self) {
+        if self.get_single_component_mut::<Events<T>>().is_err() {
+            self.spawn(Events::<T>::new());
+            self.event_updaters.push(update_events::<T>);
+        }
+    }
+
+    /// Sends an event from outside of a system. See [EventWriter].
+    /// Panics if [World::add_events] wasn't called for `T`.
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        let change_tick = self.change_tick();
+        self.get_singleton::<Events<T>>().send(change_tick, event)
+    }
+
     pub(crate) fn spawn_reserved_entities(&mut self) {
         let empty_archetype = &mut self.archetypes[0];
         while let Some(entity) = self
@@ -251,6 +380,7 @@ impl World {
         self.spawn_reserved_entities();
 
         let entity_location = self.entities.free(entity)?;
+        let change_tick = self.change_tick();
 
         // Remove the [Entity]'s components from the [Archetype]
         let archetype = &mut self.archetypes[entity_location.archetype_index];
@@ -258,6 +388,10 @@ impl World {
             channel
                 .data
                 .swap_remove(entity_location.index_within_archetype);
+            self.removed_components
+                .entry(channel.component_id)
+                .or_default()
+                .push((entity, change_tick));
         }
         archetype
             .entities
@@ -328,6 +462,12 @@ impl World {
             std::any::type_name::<Component>(),
         )?;
 
+        let change_tick = self.change_tick();
+        self.removed_components
+            .entry(removing_component_id)
+            .or_default()
+            .push((entity, change_tick));
+
         // Is this swap-removing the wrong entity?
         let removed_component = self.archetypes[archetype_index].channels[archetype_channel]
             .as_mut_vec()
@@ -448,6 +588,7 @@ impl World {
                             index_within_archetype,
                             &mut *destination_channel.data,
                         );
+                        destination_channel.inherit_ticks(source_channel);
                         source_channel_index += 1;
                         destination_channel_index += 1;
                     }
@@ -494,10 +635,12 @@ impl World {
             .ok_or(KecsError::EntityMissing)?;
 
         let removing_component_id = get_component_id::<Component>();
+        let change_tick = self.change_tick();
 
         let archetype = &mut self.archetypes[entity_location.archetype_index as usize];
         for channel in &mut archetype.channels {
             if channel.component_id == removing_component_id {
+                channel.mark_changed(change_tick);
                 let component = &mut channel.as_mut_vec()[entity_location.index_within_archetype];
                 return Ok(component);
             }
@@ -530,11 +673,14 @@ impl World {
             .matching_archetype_iterator::<1>(&filters)
             .next()
             .ok_or_else(KecsError::no_matching_component::<Component>)?;
-        self.archetypes[matching_archetype.archetype_index].channels
-            [matching_archetype.channels[0].unwrap()]
-        .as_mut_vec()
-        .get_mut(0)
-        .ok_or_else(KecsError::no_matching_component::<Component>)
+        let change_tick = self.change_tick();
+        let channel = &mut self.archetypes[matching_archetype.archetype_index].channels
+            [matching_archetype.channels[0].unwrap()];
+        channel.mark_changed(change_tick);
+        channel
+            .as_mut_vec()
+            .get_mut(0)
+            .ok_or_else(KecsError::no_matching_component::<Component>)
     }
 
     /// Clones the components and [Entity]s of the other [World] and adds them to this [World].
@@ -564,6 +710,7 @@ impl World {
         } = source;
 
         let migrator_offset = destination.entities.len() as u32;
+        let change_tick = destination.change_tick();
         destination
             .entities
             .reserve_space_for_entity_cloning(old_entities);
@@ -614,7 +761,8 @@ impl World {
                         {
                             desination_channel
                                 .data
-                                .append_channel(&mut *source_channel.data)
+                                .append_channel(&mut *source_channel.data);
+                            desination_channel.mark_added(change_tick);
                         }
 
                         // Append entities to this [Archetype] and update the [Entity] location
@@ -635,6 +783,9 @@ impl World {
                         // Create a new [Archetype]
                         let mut new_archetype = Archetype::new(new_archetype_index);
                         new_archetype.channels.append(&mut new_channels);
+                        for channel in &new_archetype.channels {
+                            channel.mark_added(change_tick);
+                        }
 
                         // Append entities to this [Archetype] and update the [Entity] location
                         new_archetype.entities.reserve(old_archetype.entities.len());
@@ -668,7 +819,8 @@ impl World {
     where
         Query<'a, PARAMS>: SystemParameterTrait,
     {
-        let meta_data = <Query<PARAMS> as SystemParameterTrait>::get_meta_data(self)?;
+        let meta_data = <Query<PARAMS> as SystemParameterTrait>::get_meta_data(self, 0)?;
+        meta_data.mark_changed(self, self.change_tick());
         <Query<PARAMS> as SystemParameterFetchTrait>::fetch(self, &meta_data)
     }
     /// Get a [Query] from the [World] without running a system
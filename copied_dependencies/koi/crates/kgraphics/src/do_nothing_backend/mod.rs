@@ -67,6 +67,12 @@ impl RenderPassTrait for RenderPass<'_> {
         buffer: Option<&DataBuffer<T>>,
     ) {
     }
+    fn set_instance_attribute<T>(
+        &mut self,
+        vertex_attribute: &VertexAttribute<T>,
+        buffer: Option<&DataBuffer<T>>,
+    ) {
+    }
     fn set_float_property(&mut self, property: &FloatProperty, value: f32) {}
 
     fn set_int_property(&mut self, property: &IntProperty, value: i32) {}
@@ -88,6 +94,13 @@ impl RenderPassTrait for RenderPass<'_> {
     }
 
     fn draw_triangles(&mut self, count: u32, buffer: Option<&IndexBuffer>) {}
+    fn draw_triangles_instanced(
+        &mut self,
+        count: u32,
+        buffer: &IndexBuffer,
+        instance_count: u32,
+    ) {
+    }
 }
 
 impl CommandBufferTrait for CommandBuffer {
@@ -144,6 +157,7 @@ impl GraphicsContextTrait for GraphicsContext {
         })
     }
     fn delete_data_buffer<T>(&self, data_buffer: DataBuffer<T>) {}
+    fn update_data_buffer<T>(&self, data_buffer: &DataBuffer<T>, data: &[T]) {}
 
     fn new_index_buffer(&self, data: &[u32]) -> Result<IndexBuffer, ()> {
         Ok(IndexBuffer)
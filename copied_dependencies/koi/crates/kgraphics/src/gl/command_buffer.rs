@@ -16,6 +16,7 @@ pub(super) enum CommandBufferAction {
     BindFramebuffer(Framebuffer),
     ChangePipeline(Pipeline),
     SetVertexAttribute((VertexAttributeInfo, Option<gl_native::Buffer>)),
+    SetInstanceAttribute((VertexAttributeInfo, Option<gl_native::Buffer>)),
     SetVertexAttributeToConstant {
         attribute: VertexAttributeInfo,
         length: u8,
@@ -35,6 +36,7 @@ pub(super) enum CommandBufferAction {
     SetTextureUnitToCubeMap((UniformLocation, u8, Option<gl_native::TextureNative>)),
     SetViewport((u32, u32, u32, u32)),
     DrawTriangles(u32),
+    DrawTrianglesInstanced((u32, u32)),
     DrawTriangleArrays(u32),
     SetDepthMask(bool),
     BlitFramebuffer {
@@ -179,6 +181,22 @@ impl<'a> RenderPassTrait for RenderPass<'a> {
         }
     }
 
+    /// Instance attributes are arrays of data for each instance.
+    fn set_instance_attribute<T>(
+        &mut self,
+        vertex_attribute: &VertexAttribute<T>,
+        buffer: Option<&DataBuffer<T>>,
+    ) {
+        if let Some(info) = vertex_attribute.info.clone() {
+            self.command_buffer
+                .actions
+                .push(CommandBufferAction::SetInstanceAttribute((
+                    info,
+                    buffer.map(|b| b.buffer),
+                )))
+        }
+    }
+
     /// Vertex attributes are arrays of data for each vertex.
     fn set_vertex_attribute_to_constant<T>(
         &mut self,
@@ -345,6 +363,18 @@ impl<'a> RenderPassTrait for RenderPass<'a> {
             .push(CommandBufferAction::DrawTriangles(count))
     }
 
+    fn draw_triangles_instanced(&mut self, count: u32, buffer: &IndexBuffer, instance_count: u32) {
+        self.command_buffer
+            .actions
+            .push(CommandBufferAction::SetIndexBuffer(buffer.clone()));
+        self.command_buffer
+            .actions
+            .push(CommandBufferAction::DrawTrianglesInstanced((
+                count,
+                instance_count,
+            )))
+    }
+
     fn draw_triangles_without_buffer(&mut self, count: u32) {
         self.command_buffer
             .actions
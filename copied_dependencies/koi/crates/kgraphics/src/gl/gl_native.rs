@@ -181,6 +181,15 @@ impl GL {
         );
     }
 
+    pub unsafe fn buffer_sub_data_u8_slice(&self, target: u32, offset: isize, data: &[u8]) {
+        self.gl.BufferSubData(
+            GLenum(target),
+            offset,
+            data.len() as isize,
+            data.as_ptr() as *const std::ffi::c_void,
+        );
+    }
+
     pub unsafe fn create_buffer(&self) -> Result<Buffer, String> {
         let mut buffer = 0;
         self.gl.GenBuffers(1, &mut buffer);
@@ -417,6 +426,10 @@ impl GL {
         self.gl.EnableVertexAttribArray(index);
     }
 
+    pub unsafe fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        self.gl.VertexAttribDivisor(index, divisor);
+    }
+
     pub unsafe fn uniform_1_i32(&self, location: Option<UniformLocation>, x: i32) {
         if let Some(location) = location {
             self.gl.Uniform1i(location.0 as i32, x);
@@ -494,6 +507,23 @@ impl GL {
             .DrawElements(mode, count, element_type, offset as *const std::ffi::c_void);
     }
 
+    pub unsafe fn draw_elements_instanced(
+        &self,
+        mode: GLenum,
+        count: i32,
+        element_type: GLenum,
+        offset: i32,
+        instance_count: i32,
+    ) {
+        self.gl.DrawElementsInstanced(
+            mode,
+            count,
+            element_type,
+            offset as *const std::ffi::c_void,
+            instance_count,
+        );
+    }
+
     pub unsafe fn create_shader(&self, shader_type: GLenum) -> Result<Shader, String> {
         Ok(Shader(self.gl.CreateShader(shader_type)))
     }
@@ -508,6 +508,15 @@ impl GraphicsContextTrait for GraphicsContext {
         unsafe { self.gl.delete_buffer(data_buffer.buffer) }
     }
 
+    fn update_data_buffer<T>(&mut self, data_buffer: &DataBuffer<T>, data: &[T]) {
+        unsafe {
+            self.gl
+                .bind_buffer(GL_ARRAY_BUFFER, Some(data_buffer.buffer));
+            self.gl
+                .buffer_sub_data_u8_slice(GL_ARRAY_BUFFER.0, 0, slice_to_bytes(data));
+        }
+    }
+
     fn new_index_buffer(&mut self, data: &[u32]) -> Result<IndexBuffer, ()> {
         unsafe {
             let buffer = self.gl.create_buffer().unwrap();
@@ -903,6 +912,28 @@ impl GraphicsContextTrait for GraphicsContext {
 
                             self.gl.enable_vertex_attrib_array(attribute.index);
                         }
+                        // The attribute may have been used as an instance attribute by a previous draw.
+                        self.gl.vertex_attrib_divisor(attribute.index, 0);
+                    }
+                    SetInstanceAttribute((attribute, buffer)) => {
+                        if buffer.is_none() {
+                            self.gl.disable_vertex_attrib_array(attribute.index);
+                            self.gl.vertex_attrib_divisor(attribute.index, 0);
+                        } else {
+                            self.gl.bind_buffer(GL_ARRAY_BUFFER, buffer);
+                            self.gl.vertex_attrib_pointer_f32(
+                                attribute.index,
+                                attribute.byte_size as i32 / 4,
+                                GL_FLOAT,
+                                false,
+                                0,
+                                0,
+                            );
+
+                            self.gl.enable_vertex_attrib_array(attribute.index);
+                            // Advance the attribute once per instance instead of once per vertex.
+                            self.gl.vertex_attrib_divisor(attribute.index, 1);
+                        }
                     }
                     SetVertexAttributeToConstant {
                         attribute,
@@ -965,6 +996,15 @@ impl GraphicsContextTrait for GraphicsContext {
                         self.gl
                             .draw_elements(GL_TRIANGLES, (count * 3) as i32, GL_UNSIGNED_INT, 0);
                     }
+                    DrawTrianglesInstanced((count, instance_count)) => {
+                        self.gl.draw_elements_instanced(
+                            GL_TRIANGLES,
+                            (count * 3) as i32,
+                            GL_UNSIGNED_INT,
+                            0,
+                            instance_count as i32,
+                        );
+                    }
                     DrawTriangleArrays(count) => {
                         self.gl.draw_arrays(GL_TRIANGLES, 0, (count * 3) as i32);
                     }
@@ -36,6 +36,12 @@ pub trait RenderPassTrait {
         vertex_attribute: &VertexAttribute<T>,
         value: &[f32],
     );
+    /// Instance attributes are arrays of data for each instance drawn with `draw_triangles_instanced`.
+    fn set_instance_attribute<T>(
+        &mut self,
+        vertex_attribute: &VertexAttribute<T>,
+        buffer: Option<&DataBuffer<T>>,
+    );
     fn set_float_property(&mut self, property: &FloatProperty, value: f32);
 
     fn set_int_property(&mut self, property: &IntProperty, value: i32);
@@ -66,6 +72,12 @@ pub trait RenderPassTrait {
     );
 
     fn draw_triangles(&mut self, count: u32, index_buffer: &IndexBuffer);
+    fn draw_triangles_instanced(
+        &mut self,
+        count: u32,
+        index_buffer: &IndexBuffer,
+        instance_count: u32,
+    );
     fn draw_triangles_without_buffer(&mut self, count: u32);
     fn set_depth_mask(&mut self, depth_mask: bool);
     fn blit_framebuffer(
@@ -131,6 +143,9 @@ pub trait GraphicsContextTrait: Sized {
 
     fn new_data_buffer<T>(&mut self, data: &[T]) -> Result<DataBuffer<T>, ()>;
     fn delete_data_buffer<T>(&mut self, data_buffer: DataBuffer<T>);
+    /// Overwrites the start of `data_buffer` with `data`.
+    /// `data` must fit in the size the buffer was created with.
+    fn update_data_buffer<T>(&mut self, data_buffer: &DataBuffer<T>, data: &[T]);
 
     fn new_index_buffer(&mut self, data: &[u32]) -> Result<IndexBuffer, ()>;
     fn delete_index_buffer(&mut self, index_buffer: IndexBuffer);
@@ -1,17 +1,35 @@
-#[cfg(all(not(target_arch = "wasm32"), feature = "gl"))]
+// `do_nothing_backend` replaces the GL backends when both are enabled.
+// This lets headless builds enable it without turning off default features everywhere.
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "gl",
+    not(feature = "do_nothing_backend")
+))]
 mod gl;
-#[cfg(all(not(target_arch = "wasm32"), feature = "gl"))]
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "gl",
+    not(feature = "do_nothing_backend")
+))]
 pub use gl::*;
 
-#[cfg(all(target_arch = "wasm32", feature = "gl"))]
+#[cfg(all(
+    target_arch = "wasm32",
+    feature = "gl",
+    not(feature = "do_nothing_backend")
+))]
 mod webgl_backend;
-#[cfg(all(target_arch = "wasm32", feature = "gl"))]
+#[cfg(all(
+    target_arch = "wasm32",
+    feature = "gl",
+    not(feature = "do_nothing_backend")
+))]
 pub use webgl_backend::*;
 
-#[cfg(feature = "gl")]
+#[cfg(all(feature = "gl", not(feature = "do_nothing_backend")))]
 #[allow(unused)]
 mod gl_shared;
-#[cfg(feature = "gl")]
+#[cfg(all(feature = "gl", not(feature = "do_nothing_backend")))]
 #[allow(unused)]
 use gl_shared::*;
 
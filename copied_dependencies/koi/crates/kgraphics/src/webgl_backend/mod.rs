@@ -128,6 +128,8 @@ enum Command {
     SetCubeMapUniform = 15,
     SetDepthMask = 16,
     BlitFramebuffer = 17,
+    SetInstanceAttribute = 18,
+    DrawTrianglesInstanced = 19,
 }
 
 pub struct CommandBuffer {
@@ -326,6 +328,24 @@ impl RenderPassTrait for RenderPass<'_> {
         }
     }
 
+    fn set_instance_attribute<T>(
+        &mut self,
+        vertex_attribute: &VertexAttribute<T>,
+        buffer: Option<&DataBuffer<T>>,
+    ) {
+        if let Some(info) = vertex_attribute.info {
+            self.command_buffer
+                .commands
+                .push(Command::SetInstanceAttribute);
+
+            self.command_buffer.u32_data.extend_from_slice(&[
+                info.index,
+                info.byte_size / 4, // Number of components
+                buffer.map_or(0, |b| b.js_object.index()),
+            ]);
+        }
+    }
+
     fn set_vertex_attribute_to_constant<T>(
         &mut self,
         vertex_attribute: &VertexAttribute<T>,
@@ -459,6 +479,22 @@ impl RenderPassTrait for RenderPass<'_> {
             .extend_from_slice(&[count * 3, buffer.0.index()]);
     }
 
+    fn draw_triangles_instanced(
+        &mut self,
+        count: u32,
+        buffer: &IndexBuffer,
+        instance_count: u32,
+    ) {
+        self.command_buffer
+            .commands
+            .push(Command::DrawTrianglesInstanced);
+        self.command_buffer.u32_data.extend_from_slice(&[
+            count * 3,
+            buffer.0.index(),
+            instance_count,
+        ]);
+    }
+
     fn draw_triangles_without_buffer(&mut self, count: u32) {
         self.command_buffer.commands.push(Command::DrawTriangles);
         self.command_buffer
@@ -613,6 +649,7 @@ struct WebGLJS {
     new_vertex_function: JSObject,
     new_fragment_function: JSObject,
     new_data_buffer: JSObject,
+    update_data_buffer: JSObject,
     new_index_buffer: JSObject,
     delete_buffer: JSObject,
     new_texture: JSObject,
@@ -645,6 +682,7 @@ impl WebGLJS {
             new_vertex_function: o.get_property("new_vertex_function"),
             new_fragment_function: o.get_property("new_fragment_function"),
             new_data_buffer: o.get_property("new_data_buffer"),
+            update_data_buffer: o.get_property("update_data_buffer"),
             new_index_buffer: o.get_property("new_index_buffer"),
             delete_buffer: o.get_property("delete_buffer"),
             new_texture: o.get_property("new_texture"),
@@ -833,6 +871,14 @@ impl GraphicsContextTrait for GraphicsContext {
         self.js.delete_buffer.call_1_arg(&data_buffer.js_object);
     }
 
+    fn update_data_buffer<T>(&mut self, data_buffer: &DataBuffer<T>, data: &[T]) {
+        self.js.update_data_buffer.call_raw(&[
+            data_buffer.js_object.index(),
+            data.as_ptr() as u32,
+            (data.len() * std::mem::size_of::<T>()) as u32,
+        ]);
+    }
+
     fn new_index_buffer(&mut self, data: &[u32]) -> Result<IndexBuffer, ()> {
         let js_object = self
             .js
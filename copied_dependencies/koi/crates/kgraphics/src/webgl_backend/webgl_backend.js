@@ -83,6 +83,11 @@ var gl_web_object = {
         gl.bufferData(gl.ARRAY_BUFFER, data, gl.STATIC_DRAW);
         return buffer;
     },
+    update_data_buffer(data_buffer_index, data_ptr, data_length) {
+        const data = new Uint8Array(self.kwasm_memory.buffer, data_ptr, data_length);
+        gl.bindBuffer(gl.ARRAY_BUFFER, self.kwasm_get_object(data_buffer_index));
+        gl.bufferSubData(gl.ARRAY_BUFFER, 0, data);
+    },
     new_index_buffer(data_ptr, data_length) {
         const data = new Uint32Array(self.kwasm_memory.buffer, data_ptr, data_length);
         let buffer = gl.createBuffer();
@@ -409,6 +414,8 @@ var gl_web_object = {
                         );
                         gl.enableVertexAttribArray(attribute_index);
                     }
+                    // The attribute may have been used as an instance attribute by a previous draw.
+                    gl.vertexAttribDivisor(attribute_index, 0);
                     break;
                 }
                 case 4: {
@@ -587,6 +594,37 @@ var gl_web_object = {
                     gl.invalidateFramebuffer(gl.READ_FRAMEBUFFER, [gl.COLOR_ATTACHMENT0, gl.DEPTH_ATTACHMENT]);
                     break;
                 }
+                case 18: {
+                    // SetInstanceAttribute
+                    let attribute_index = u32_data[u32_offset++];
+                    let number_of_components = u32_data[u32_offset++];
+                    let buffer_index = u32_data[u32_offset++];
+
+                    let buffer = kwasm_get_object(buffer_index);
+
+                    if (buffer === null) {
+                        gl.disableVertexAttribArray(attribute_index);
+                        gl.vertexAttribDivisor(attribute_index, 0);
+                    } else {
+                        gl.bindBuffer(gl.ARRAY_BUFFER, buffer);
+                        gl.vertexAttribPointer(attribute_index, number_of_components, gl.FLOAT, false, 0, 0);
+                        gl.enableVertexAttribArray(attribute_index);
+                        // Advance the attribute once per instance instead of once per vertex.
+                        gl.vertexAttribDivisor(attribute_index, 1);
+                    }
+                    break;
+                }
+                case 19: {
+                    // DrawTrianglesInstanced
+                    let count = u32_data[u32_offset++]; // Number of vertices to draw
+                    let buffer_index = u32_data[u32_offset++];
+                    let instance_count = u32_data[u32_offset++];
+
+                    let buffer = kwasm_get_object(buffer_index);
+                    gl.bindBuffer(gl.ELEMENT_ARRAY_BUFFER, buffer);
+                    gl.drawElementsInstanced(gl.TRIANGLES, count, gl.UNSIGNED_INT, 0, instance_count);
+                    break;
+                }
             }
         }
 
@@ -37,6 +37,32 @@ impl<
     }
 }
 
+/// Reflected as a list of the values column by column.
+impl<T: kreflect::ReflectFieldTrait, const ROWS: usize, const COLUMNS: usize>
+    kreflect::ReflectFieldTrait for Matrix<T, ROWS, COLUMNS>
+{
+    fn to_reflect_value(&self) -> kreflect::ReflectValue {
+        kreflect::ReflectValue::List(
+            self.0
+                .iter()
+                .flatten()
+                .map(kreflect::ReflectFieldTrait::to_reflect_value)
+                .collect(),
+        )
+    }
+
+    fn from_reflect_value(value: kreflect::ReflectValue) -> Option<Self> {
+        let values = <Vec<T> as kreflect::ReflectFieldTrait>::from_reflect_value(value)?;
+        if values.len() != ROWS * COLUMNS {
+            return None;
+        }
+        let mut values = values.into_iter();
+        Some(Self(std::array::from_fn(|_| {
+            std::array::from_fn(|_| values.next().unwrap())
+        })))
+    }
+}
+
 impl<T: Numeric, const R: usize, const C: usize> Default for Matrix<T, R, C> {
     fn default() -> Self {
         Self::ZERO
@@ -26,6 +26,17 @@ impl<
     }
 }
 
+/// Reflected as a list of `x`, `y`, `z`, and `w`.
+impl<T: kreflect::ReflectFieldTrait + NumericFloat> kreflect::ReflectFieldTrait for Quaternion<T> {
+    fn to_reflect_value(&self) -> kreflect::ReflectValue {
+        self.0.to_reflect_value()
+    }
+
+    fn from_reflect_value(value: kreflect::ReflectValue) -> Option<Self> {
+        Some(Self(Vector::<T, 4>::from_reflect_value(value)?))
+    }
+}
+
 impl<T: NumericFloat + std::fmt::Debug> Quaternion<T> {
     pub const IDENTITY: Self = Quaternion(Vector::<T, 4>::new(T::ZERO, T::ZERO, T::ZERO, T::ONE));
 
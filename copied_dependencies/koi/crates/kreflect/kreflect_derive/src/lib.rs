@@ -1,127 +1,114 @@
 use kreflect_common::*;
 use proc_macro::TokenStream;
 
-#[proc_macro_derive(Reflect)]
+#[proc_macro_derive(Reflect, attributes(reflect))]
 pub fn derive_reflect(item: TokenStream) -> TokenStream {
     let mut rust_tokens = Vec::new();
     token_stream_to_rust_tokens(item, &mut rust_tokens);
 
-    let mut parser = Parser::new(&rust_tokens);
-    let _parse_result = parser.parse().expect("Could not parse");
-    // println!("PARSE RESULT: {:#?}", _parse_result);
-    // panic!();
-    "".parse().unwrap()
-}
-/*
-#[proc_macro_derive(Reflect)]
-pub fn derive_reflect(item: TokenStream) -> TokenStream {
-    let mut rust_tokens = Vec::new();
-    token_stream_to_rust_tokens(item, &mut rust_tokens);
-
-    println!("TOKENS: {:#?}", rust_tokens);
     let mut parser = Parser::new(&rust_tokens);
     let parse_result = parser.parse().expect("Could not parse");
+    reflect_impl(&parse_result).parse().unwrap()
+}
 
-    //let stream = TokenStream::new();
-
-    // panic!("REFLECT: {:#?}", parse_result);
-    match &parse_result {
-        Value::Struct(s) => {
-            let mut reflection_output = String::new();
-            reflection_output += "kreflect::Value::Struct (kreflect::Struct {\n";
-            reflection_output += "  name: \"";
-            reflection_output += &s.name;
-            reflection_output += "\",\n";
-            reflection_output += "fields: Fields::";
-            match &s.fields {
-                Fields::Tuple(_) => {
-                    todo!("TUPLE DERIVE NOT YET IMPLEMENTED");
-                }
-                Fields::Struct(v) => {
-                    println!("STRUCT: {:#?}", v);
-                    reflection_output += "Struct(vec![";
+fn reflect_impl(value: &Value) -> String {
+    let (name, generic_parameters, fields) = match value {
+        Value::Struct(s) => (&s.name, &s.generic_parameters, &s.fields),
+        // Enum variants have different fields so enums are reflected without any.
+        Value::Enum(e) => (&e.name, &e.generic_parameters, &Fields::Unit),
+    };
+    let fields = match fields {
+        Fields::Struct(fields) | Fields::Tuple(fields) => &fields[..],
+        Fields::Unit => &[],
+    };
 
-                    for member in v {
-                        let visibility_string = match member.visibility {
-                            Visibility::Private => "Visibility::Private",
-                            Visibility::Pub => "Visibility::Pub",
-                            Visibility::Crate => "Visibility::Crate",
-                            Visibility::Super => "Visibility::Super",
-                        };
+    let mut field_infos = String::new();
+    let mut get_fields = String::new();
+    let mut set_fields = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if field_has_reflect_option(field, "skip") {
+            continue;
+        }
+        let field_name = field
+            .name
+            .as_ref()
+            .map_or_else(|| i.to_string(), |name| name.to_string());
 
-                        reflection_output += &format!(
-                            r#"Field {{
-                            name: Some("{}"),
-                            _type: todo!(),
-                            visibility: {}
-                        }},"#,
-                            member.name.as_ref().unwrap(),
-                            //   member._type,
-                            visibility_string
-                        );
-                    }
-                    reflection_output += "]),";
-                }
-                Fields::Unit => reflection_output += "Unit,",
-            }
-            reflection_output += "generic_lifetimes: vec![";
-            for lifetime in &s.generic_lifetimes {
-                reflection_output += "\"";
-                reflection_output += lifetime;
-                reflection_output += "\"";
-            }
-            reflection_output += "],";
+        field_infos += &format!(
+            "kreflect::FieldInfo {{ name: {:?}, type_name: {:?} }},\n",
+            field_name,
+            field._type.as_string()
+        );
+        get_fields += &format!(
+            "{:?} => Some(kreflect::ReflectFieldTrait::to_reflect_value(&self.{})),\n",
+            field_name, field_name
+        );
+        set_fields += &format!(
+            "{:?} => {{
+                self.{} = kreflect::ReflectFieldTrait::from_reflect_value(value)
+                    .ok_or(kreflect::ReflectError::WrongType)?;
+                Ok(())
+            }}\n",
+            field_name, field_name
+        );
+    }
 
-            reflection_output += "generic_types: vec![";
-            for _type in &s.generic_types {
-                reflection_output += "\"";
-                reflection_output += _type;
-                reflection_output += "\"";
-            }
-            reflection_output += "],";
-            reflection_output += "visibility: Visibility::";
-            reflection_output += match s.visibility {
-                Visibility::Crate => "Crate",
-                Visibility::Private => "Private",
-                Visibility::Pub => "Pub",
-                Visibility::Super => "Super",
-            };
-            reflection_output += ",";
-            reflection_output += "})";
+    format!(
+        r#"
+        impl{} kreflect::Reflect for {}{} {{
+            fn type_name(&self) -> &'static str {{
+                {:?}
+            }}
 
-            println!("REFLECTION OUTPUT: {}", reflection_output);
+            fn fields(&self) -> &'static [kreflect::FieldInfo] {{
+                &[{}]
+            }}
 
-            let mut lifetimes_and_generics = String::new();
-            for lifetime in &s.generic_lifetimes {
-                lifetimes_and_generics.push('\'');
-                lifetimes_and_generics += lifetime;
-                lifetimes_and_generics.push(',');
-            }
-            for generic in &s.generic_types {
-                lifetimes_and_generics += generic;
-                lifetimes_and_generics.push(',');
-            }
-            // panic!("reflection output: {}", reflection_output);
-            let implementation = format!(
-                r#"
-            impl<{}> Reflect for {}<{}> {{
-                fn type_name() -> &'static str {{
-                    &"{}"
+            #[allow(unused_variables)]
+            fn get_field(&self, name: &str) -> Option<kreflect::ReflectValue> {{
+                match name {{
+                    {}
+                    _ => None,
                 }}
+            }}
 
-                fn reflect() -> Value<'static> {{
+            #[allow(unused_variables)]
+            fn set_field(
+                &mut self,
+                name: &str,
+                value: kreflect::ReflectValue,
+            ) -> Result<(), kreflect::ReflectError> {{
+                match name {{
                     {}
+                    _ => Err(kreflect::ReflectError::NoSuchField),
                 }}
             }}
-            "#,
-                lifetimes_and_generics, s.name, lifetimes_and_generics, s.name, reflection_output
-            );
-
-            return implementation.parse().unwrap();
-        }
-        _ => panic!("Non-structs are not yet supported"),
-    }
+        }}
+    "#,
+        &generic_parameters.as_impl_args(),
+        name,
+        &generic_parameters.as_args(),
+        name,
+        field_infos,
+        get_fields,
+        set_fields,
+    )
+}
 
-    // stream
+/// Checks for a `#[reflect(option)]` attribute.
+/// A bare `#[skip]` isn't used because other derives share it.
+fn field_has_reflect_option(field: &Field, option: &str) -> bool {
+    field.attributes.iter().any(|a| {
+        let is_reflect = match a.path.segments.as_slice() {
+            [segment] => match &segment.path_segment_type {
+                PathSegmentType::Named(name) => name == "reflect",
+                _ => false,
+            },
+            _ => false,
+        };
+        is_reflect
+            && a.tokens
+                .iter()
+                .any(|t| matches!(t, Token::Identifier(name) if name == option))
+    })
 }
-*/
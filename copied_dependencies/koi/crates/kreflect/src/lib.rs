@@ -1,7 +1,30 @@
 pub use kreflect_common::*;
 pub use kreflect_derive::*;
 
+mod reflect_value;
+pub use reflect_value::*;
+
+/// Runtime access to a type's fields by name.
+/// `#[derive(Reflect)]` implements this for structs whose fields implement [ReflectFieldTrait].
+/// Fields marked `#[reflect(skip)]` are left out.
+/// Enums are reflected without any fields.
 pub trait Reflect {
-    fn type_name() -> &'static str;
-    fn reflect() -> Value<'static>;
+    fn type_name(&self) -> &'static str;
+    fn fields(&self) -> &'static [FieldInfo];
+    fn get_field(&self, name: &str) -> Option<ReflectValue>;
+    fn set_field(&mut self, name: &str, value: ReflectValue) -> Result<(), ReflectError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// The field's type as it's written in the struct declaration.
+    pub type_name: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReflectError {
+    NoSuchField,
+    /// The [ReflectValue] couldn't be converted to the field's type.
+    WrongType,
 }
@@ -0,0 +1,142 @@
+/// A field's value, converted to a form that doesn't depend on its type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// Tuples, arrays, `Vec`s, and math types like vectors.
+    List(Vec<ReflectValue>),
+    Option(Option<Box<ReflectValue>>),
+}
+
+/// Types that can be read and written as a [ReflectValue].
+pub trait ReflectFieldTrait: Sized {
+    fn to_reflect_value(&self) -> ReflectValue;
+    /// Returns `None` if `value` isn't the right kind of [ReflectValue] or is out of range.
+    fn from_reflect_value(value: ReflectValue) -> Option<Self>;
+}
+
+impl ReflectFieldTrait for bool {
+    fn to_reflect_value(&self) -> ReflectValue {
+        ReflectValue::Bool(*self)
+    }
+    fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! int_impls {
+    ($($int: ty),*) => {
+        $(impl ReflectFieldTrait for $int {
+            /// Unsigned values too large for an `i64` saturate at `i64::MAX`.
+            fn to_reflect_value(&self) -> ReflectValue {
+                ReflectValue::Int((*self).try_into().unwrap_or(i64::MAX))
+            }
+            fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+                match value {
+                    ReflectValue::Int(i) => i.try_into().ok(),
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
+int_impls! {i8, i16, i32, i64, isize, u8, u16, u32, u64, usize}
+
+macro_rules! float_impls {
+    ($($float: ty),*) => {
+        $(impl ReflectFieldTrait for $float {
+            fn to_reflect_value(&self) -> ReflectValue {
+                ReflectValue::Float(*self as f64)
+            }
+            /// Also accepts a [ReflectValue::Int] so whole numbers can be written either way.
+            fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+                match value {
+                    ReflectValue::Float(f) => Some(f as _),
+                    ReflectValue::Int(i) => Some(i as _),
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
+float_impls! {f32, f64}
+
+impl ReflectFieldTrait for String {
+    fn to_reflect_value(&self) -> ReflectValue {
+        ReflectValue::String(self.clone())
+    }
+    fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ReflectFieldTrait> ReflectFieldTrait for Option<T> {
+    fn to_reflect_value(&self) -> ReflectValue {
+        ReflectValue::Option(
+            self.as_ref()
+                .map(|value| Box::new(value.to_reflect_value())),
+        )
+    }
+    fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::Option(None) => Some(None),
+            ReflectValue::Option(Some(value)) => Some(Some(T::from_reflect_value(*value)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ReflectFieldTrait> ReflectFieldTrait for Vec<T> {
+    fn to_reflect_value(&self) -> ReflectValue {
+        ReflectValue::List(self.iter().map(T::to_reflect_value).collect())
+    }
+    fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::List(values) => values.into_iter().map(T::from_reflect_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ReflectFieldTrait, const SIZE: usize> ReflectFieldTrait for [T; SIZE] {
+    fn to_reflect_value(&self) -> ReflectValue {
+        ReflectValue::List(self.iter().map(T::to_reflect_value).collect())
+    }
+    fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+        Vec::<T>::from_reflect_value(value)?.try_into().ok()
+    }
+}
+
+macro_rules! tuple_impls {
+    ($count: tt, $( ($index: tt, $tuple: ident) ),*) => {
+        impl<$($tuple: ReflectFieldTrait,)*> ReflectFieldTrait for ($($tuple,)*) {
+            fn to_reflect_value(&self) -> ReflectValue {
+                ReflectValue::List(vec![$(self.$index.to_reflect_value(),)*])
+            }
+            fn from_reflect_value(value: ReflectValue) -> Option<Self> {
+                match value {
+                    ReflectValue::List(values) if values.len() == $count => {
+                        let mut values = values.into_iter();
+                        Some(($($tuple::from_reflect_value(values.next()?)?,)*))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+tuple_impls! { 1, (0, A) }
+tuple_impls! { 2, (0, A), (1, B) }
+tuple_impls! { 3, (0, A), (1, B), (2, C) }
+tuple_impls! { 4, (0, A), (1, B), (2, C), (3, D) }
@@ -200,6 +200,15 @@ impl<'a, CONTEXT> Deserializer<'a> for JSONDeserializer<'a, CONTEXT> {
 }
 
 impl<'a, CONTEXT> JSONDeserializer<'a, CONTEXT> {
+    pub fn new_with_context(source: &'a str, context: CONTEXT) -> Self {
+        Self {
+            recursive_depth: 0,
+            iter: source.char_indices().peekable(),
+            source,
+            context,
+        }
+    }
+
     pub fn skip_whitespace(&mut self) {
         while self.iter.peek().map_or(false, |(_, c)| c.is_whitespace()) {
             self.iter.next();
@@ -21,7 +21,7 @@ impl Default for JSONSerializer<()> {
 }
 
 impl<CONTEXT> JSONSerializer<CONTEXT> {
-    fn new_with_context(context: CONTEXT) -> Self {
+    pub fn new_with_context(context: CONTEXT) -> Self {
         JSONSerializer {
             s: String::new(),
             indentation: 0,
@@ -5,12 +5,15 @@ use std::sync::{mpsc, Arc, Weak};
 struct Item<T> {
     item: T,
     indirect_index: usize,
+    /// Set from `IndirectionStorage::next_generation` when the item is added or borrowed mutably.
+    generation: usize,
 }
 
 struct IndirectionStorage<T> {
     items: Vec<Item<T>>,
     indirect_indices: Vec<usize>,
     free_indirect_indices: Vec<usize>,
+    next_generation: usize,
 }
 impl<T> IndirectionStorage<T> {
     fn new() -> Self {
@@ -18,9 +21,15 @@ impl<T> IndirectionStorage<T> {
             items: Vec::new(),
             indirect_indices: Vec::new(),
             free_indirect_indices: Vec::new(),
+            next_generation: 0,
         }
     }
 
+    fn next_generation(&mut self) -> usize {
+        self.next_generation += 1;
+        self.next_generation
+    }
+
     /// Push an item and return its indirect index.
     fn push(&mut self, item: T) -> usize {
         let indirect_index = if let Some(indirect_index) = self.free_indirect_indices.pop() {
@@ -30,9 +39,11 @@ impl<T> IndirectionStorage<T> {
             self.indirect_indices.push(self.items.len());
             self.indirect_indices.len() - 1
         };
+        let generation = self.next_generation();
         self.items.push(Item {
             item,
             indirect_index,
+            generation,
         });
         indirect_index
     }
@@ -59,9 +70,11 @@ impl<T> IndirectionStorage<T> {
     }
 
     fn replace_placeholder(&mut self, indirect_index: usize, item: T) {
+        let generation = self.next_generation();
         self.items.push(Item {
             item,
             indirect_index,
+            generation,
         });
         debug_assert!(self.indirect_indices[indirect_index] == 0);
         self.indirect_indices[indirect_index] = self.items.len() - 1;
@@ -72,7 +85,14 @@ impl<T> IndirectionStorage<T> {
     }
 
     fn get_mut(&mut self, indirect_index: usize) -> &mut T {
-        &mut self.items[self.indirect_indices[indirect_index]].item
+        let generation = self.next_generation();
+        let item = &mut self.items[self.indirect_indices[indirect_index]];
+        item.generation = generation;
+        &mut item.item
+    }
+
+    fn generation(&self, indirect_index: usize) -> usize {
+        self.items[self.indirect_indices[indirect_index]].generation
     }
 
     fn is_placeholder(&self, indirect_index: usize) -> bool {
@@ -109,6 +129,41 @@ impl<T: 'static> ComponentTrait for Handle<T> {
     }
 }
 
+impl<'w, T: LoadableAssetTrait> kserde::Serialize<WorldSerializer<'w>> for Handle<T> {
+    /// A [Handle] is written as the path its asset was loaded from.
+    /// [Handle]s to assets that weren't loaded from a path are written as `null`.
+    fn serialize(&self, serializer: &mut WorldSerializer<'w>) {
+        use kserde::Serializer;
+        let path = (|assets: &Assets<T>| assets.handle_to_path(self).map(str::to_owned))
+            .try_run(serializer.get_context().resources)
+            .ok()
+            .flatten();
+        serializer.serialize(&path);
+    }
+}
+
+impl<'a, 'w, T: LoadableAssetTrait> kserde::Deserialize<'a, WorldDeserializer<'a, 'w>>
+    for Handle<T>
+{
+    /// Loads the asset from its path with the [Assets] in the resources [World].
+    /// `null` becomes a [Handle] to the default placeholder.
+    fn deserialize(deserializer: &mut WorldDeserializer<'a, 'w>) -> Option<Self> {
+        use kserde::Deserializer;
+        match deserializer.any()? {
+            kserde::AnyValue::String(path) => Some(
+                deserializer
+                    .get_context_mut()
+                    .resources
+                    .get_single_component_mut::<Assets<T>>()
+                    .ok()?
+                    .load(&path),
+            ),
+            kserde::AnyValue::Null => Some(Handle::default()),
+            _ => None,
+        }
+    }
+}
+
 impl<T> Clone for Handle<T> {
     fn clone(&self) -> Self {
         Self {
@@ -296,6 +351,13 @@ impl<T: LoadableAssetTrait> Assets<T> {
         }
     }
 
+    /// Changes whenever the asset `handle` points to is replaced or borrowed mutably,
+    /// so anything built from the asset can tell when it needs to be rebuilt.
+    pub fn generation(&self, handle: &Handle<T>) -> usize {
+        self.indirection_storage
+            .generation(handle.indirection_index)
+    }
+
     pub fn handle_to_path(&self, handle: &Handle<T>) -> Option<&str> {
         self.handle_to_path
             .get(&handle.indirection_index)
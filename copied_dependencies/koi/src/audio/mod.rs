@@ -42,19 +42,26 @@ pub fn setup_audio(world: &mut World) {
 
     let (scene_handle, scene) = oddio::split(mixer);
 
+    #[allow(unused_mut)]
     let mut audio_thread = AudioThread { scene };
 
+    // Headless apps may run where there's no audio device so nothing is played.
+    #[cfg(not(feature = "headless"))]
     kaudio::begin_audio_thread(move |samples, _info| {
         audio_thread.provide_samples(samples);
     });
+    #[cfg(feature = "headless")]
+    drop(audio_thread);
     world.spawn((Name("Assets<Sound>".into()), sound_assets));
     world.spawn((Name("AudioManager".into()), AudioManager { scene_handle }));
 }
 
+#[cfg_attr(feature = "headless", allow(dead_code))]
 struct AudioThread {
     scene: oddio::SplitSignal<oddio::Reinhard<oddio::Adapt<oddio::SpatialScene>>>,
 }
 
+#[cfg_attr(feature = "headless", allow(dead_code))]
 impl AudioThread {
     fn provide_samples(&mut self, samples: &mut [f32]) {
         let frames = oddio::frame_stereo(samples);
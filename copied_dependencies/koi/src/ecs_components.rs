@@ -2,5 +2,5 @@ use kecs::Component;
 
 use crate::*;
 
-#[derive(Component, Clone)]
+#[derive(SerdeComponent, Clone)]
 pub struct Name(pub String);
@@ -0,0 +1,61 @@
+#VERTEX
+
+#ifdef MULTIVIEW
+#extension GL_OVR_multiview2 : require
+layout (num_views = 2) in;
+#endif
+
+uniform mat4 p_views[NUM_VIEWS];
+uniform mat4 p_projections[NUM_VIEWS];
+
+in vec3 a_position;
+in vec2 a_texture_coordinate;
+
+// These advance once per particle instead of once per vertex.
+// xyz is the particle's position and w is its size.
+in vec4 a_instance_position_size;
+in vec4 a_instance_color;
+
+out vec2 TexCoords;
+out vec4 ParticleColor;
+
+void main()
+{
+    TexCoords = a_texture_coordinate;
+    ParticleColor = a_instance_color;
+
+    #ifdef MULTIVIEW
+        mat4 view = p_views[gl_ViewID_OVR];
+        mat4 projection = p_projections[gl_ViewID_OVR];
+    #else
+        mat4 view = p_views[0];
+        mat4 projection = p_projections[0];
+    #endif
+
+    // Expand the quad along the camera's right and up axes so it always faces the camera.
+    vec3 camera_right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 camera_up = vec3(view[0][1], view[1][1], view[2][1]);
+    float size = a_instance_position_size.w;
+    vec3 world_position = a_instance_position_size.xyz
+        + (camera_right * a_position.x + camera_up * a_position.y) * size;
+
+    gl_Position = projection * view * vec4(world_position, 1.0);
+}
+
+#FRAGMENT
+
+in vec2 TexCoords;
+in vec4 ParticleColor;
+
+out vec4 color_out;
+
+void main()
+{
+    // Fade out towards the edge of the quad so particles look round.
+    float distance_from_center = length(TexCoords - vec2(0.5)) * 2.0;
+    float alpha = 1.0 - smoothstep(0.5, 1.0, distance_from_center);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    color_out = vec4(ParticleColor.rgb, ParticleColor.a * alpha);
+}
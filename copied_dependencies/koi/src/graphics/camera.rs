@@ -229,8 +229,16 @@ impl Camera {
         Ray3::new(world_space_near, direction)
     }
 }
-pub fn resize_camera(mut cameras: Query<(&mut Camera,)>, window: &NotSendSync<kapp::Window>) {
+pub fn resize_camera(
+    mut cameras: Query<(&mut Camera,)>,
+    windows: Query<&NotSendSync<kapp::Window>>,
+) {
     // This is very incorrect, but it works for now with the single window assumption
+    // Headless apps have no window so their cameras keep their initial size.
+    let window = match windows.iter().next() {
+        Some(window) => window,
+        None => return,
+    };
     for camera in &mut cameras {
         let (width, height) = window.size();
         if width != 0 && height != 0 {
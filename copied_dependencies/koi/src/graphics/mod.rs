@@ -80,17 +80,19 @@ pub fn request_window_redraw(world: &mut World) {
 /// Ensure that the primary window redraws continuously.
 fn automatic_redraw_request(
     graphics: &mut Graphics,
-    window: &mut NotSendSync<kapp::Window>,
+    windows: Query<&NotSendSync<kapp::Window>>,
     time: &mut Time,
     #[cfg(feature = "xr")] xr: &crate::XR,
 ) {
     if graphics.request_redraw || graphics.automatic_request_redraw {
-        #[cfg(feature = "xr")]
-        if !xr.running() {
+        for window in windows.iter() {
+            #[cfg(feature = "xr")]
+            if !xr.running() {
+                window.request_redraw();
+            }
+            #[cfg(not(feature = "xr"))]
             window.request_redraw();
         }
-        #[cfg(not(feature = "xr"))]
-        window.request_redraw();
         graphics.request_redraw = graphics.automatic_request_redraw;
     } else {
         time.discontinuity = true;
@@ -156,11 +158,11 @@ pub enum PipelineError {
     PipelineCompilationError(String),
 }
 
-fn setup_graphics(world: &mut World) {
-    let main_window = world
-        .get_single_component_mut::<NotSendSync<kapp::Window>>()
-        .unwrap();
+/// The view size used when there's no window, like in `App::run_headless`.
+pub const HEADLESS_VIEW_SIZE: (u32, u32) = (1600, 1200);
 
+fn setup_graphics(world: &mut World) {
+    #[allow(unused_mut)]
     let mut context = GraphicsContext::new_with_settings(GraphicsContextSettings {
         high_resolution_framebuffer: true,
         /// How many MSAA samples the window framebuffer should have
@@ -168,23 +170,34 @@ fn setup_graphics(world: &mut World) {
     })
     .unwrap();
 
-    let main_window: &kapp::Window = main_window;
-
-    let (window_width, window_height) = main_window.size();
-    context.resize(main_window, window_width, window_height);
-
-    #[cfg(not(feature = "SDL"))]
-    let render_target = unsafe {
-        context
-            .get_render_target_for_window(main_window, window_width, window_height)
-            .unwrap()
-    };
+    // The do-nothing backend doesn't need a window so headless apps don't create one.
+    #[cfg(feature = "headless")]
+    let (render_target, main_window_id) = (RenderTarget, kapp::WindowId::new(std::ptr::null_mut()));
+
+    #[cfg(not(feature = "headless"))]
+    let (render_target, main_window_id) = {
+        let main_window = world
+            .get_single_component_mut::<NotSendSync<kapp::Window>>()
+            .unwrap();
+        let main_window: &kapp::Window = main_window;
+
+        let (window_width, window_height) = main_window.size();
+        context.resize(main_window, window_width, window_height);
+
+        #[cfg(not(feature = "SDL"))]
+        let render_target = unsafe {
+            context
+                .get_render_target_for_window(main_window, window_width, window_height)
+                .unwrap()
+        };
 
-    #[cfg(feature = "SDL")]
-    let render_target = unsafe {
-        context
-            .get_render_target_for_window_sdl(main_window.id, window_width, window_height)
-            .unwrap()
+        #[cfg(feature = "SDL")]
+        let render_target = unsafe {
+            context
+                .get_render_target_for_window_sdl(main_window.id, window_width, window_height)
+                .unwrap()
+        };
+        (render_target, main_window.id)
     };
 
     #[cfg(feature = "xr")]
@@ -194,7 +207,7 @@ fn setup_graphics(world: &mut World) {
         context,
         render_target,
         current_camera_target: None,
-        primary_camera_target: CameraTarget::Window(main_window.id),
+        primary_camera_target: CameraTarget::Window(main_window_id),
         override_views: Vec::new(),
         current_target_framebuffer: Framebuffer::default(),
         shader_snippets: HashMap::new(),
@@ -482,16 +495,17 @@ impl GraphicsInner {
     }
 }
 
-pub fn resize_window(graphics: &mut Graphics, window: &NotSendSync<kapp::Window>) {
+pub fn resize_window(graphics: &mut Graphics, windows: Query<&NotSendSync<kapp::Window>>) {
     // There are bad assumptions here about only a single window existing.
-    let main_window: &NotSendSync<kapp::Window> = window;
-    let main_window: &kapp::Window = main_window;
+    for main_window in windows.iter() {
+        let main_window: &kapp::Window = main_window;
 
-    let (window_width, window_height) = main_window.size();
+        let (window_width, window_height) = main_window.size();
 
-    graphics
-        .context
-        .resize(main_window, window_width, window_height);
+        graphics
+            .context
+            .resize(main_window, window_width, window_height);
+    }
 }
 
 fn check_for_dropped_graphics_assets(
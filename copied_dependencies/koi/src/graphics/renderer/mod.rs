@@ -22,6 +22,9 @@ pub use shadow_caster::*;
 mod offscreen_render_target;
 pub use offscreen_render_target::*;
 
+mod particles;
+pub use particles::*;
+
 use crate::graphics::texture::Texture;
 
 struct RenderTargetTexture {
@@ -69,6 +72,8 @@ pub struct RendererInfo {
     offscreen_render_target: OffscreenRenderTarget,
     blur_calculator: BloomCalculator,
     final_postprocess_shader: Shader,
+    particle_shader: Shader,
+    particle_draws: Vec<particles::ParticleDraw>,
     pub bloom_enabled: bool,
     /// This value should be from 0.0 to 1.0
     /// The default value is 0.1. More than 0.3 looks rather extreme. 0.0 is no bloom.
@@ -78,8 +83,10 @@ pub struct RendererInfo {
 pub fn renderer_plugin() -> Plugin {
     Plugin {
         setup_systems: vec![setup_renderer.system()],
+        fixed_update_systems: vec![update_particle_emitters.system()],
         end_of_frame_systems: vec![
             prepare_shadow_casters.system(),
+            prepare_particles.system(),
             render_scene.system(),
             drop_materials.system(),
         ],
@@ -102,7 +109,9 @@ pub fn setup_renderer(world: &mut World) {
         .unwrap()
         .add(brdf_lookup_table);
 
-    let initial_size = world.get_singleton::<NotSendSync<kapp::Window>>().size();
+    let initial_size = world
+        .get_single_component_mut::<NotSendSync<kapp::Window>>()
+        .map_or(HEADLESS_VIEW_SIZE, |window| window.size());
 
     let blur_calculator = BloomCalculator::new.run(world);
     let renderer_info = RendererInfo {
@@ -119,6 +128,18 @@ pub fn setup_renderer(world: &mut World) {
                 },
             )
             .unwrap(),
+        particle_shader: world
+            .get_singleton::<Graphics>()
+            .new_shader(
+                include_str!("../built_in_shaders/particles.glsl"),
+                PipelineSettings {
+                    faces_to_render: FacesToRender::FrontAndBack,
+                    blending: Some((BlendFactor::SourceAlpha, BlendFactor::OneMinusSourceAlpha)),
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        particle_draws: Vec::new(),
         blur_calculator,
         brdf_lookup_table,
         offscreen_render_target: (|graphics: &mut Graphics, textures: &mut Assets<Texture>| {
@@ -190,6 +211,8 @@ struct Renderer<'a, 'b: 'a> {
     just_changed_material: bool,
     brdf_lookup_texture: &'a Texture,
     color_is_set: bool,
+    particle_shader: &'a Shader,
+    particle_draws: &'a [particles::ParticleDraw],
 }
 
 impl<'a, 'b: 'a> Renderer<'a, 'b> {
@@ -228,6 +251,8 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             just_changed_material: false,
             brdf_lookup_texture,
             color_is_set: false,
+            particle_shader: &renderer_info.particle_shader,
+            particle_draws: &renderer_info.particle_draws,
         }
     }
 
@@ -752,6 +777,10 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             }
             self.render_mesh(transform, mesh_handle);
         }
+
+        if camera.render_flags.includes_layer(RenderFlags::DEFAULT) {
+            self.render_particles();
+        }
     }
 }
 
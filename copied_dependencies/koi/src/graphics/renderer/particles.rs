@@ -0,0 +1,387 @@
+use crate::*;
+use kgraphics::*;
+
+/// A value that changes over a particle's life.
+/// Keys are `(life, value)` pairs where `life` goes from 0.0 (just spawned) to 1.0 (expired).
+#[derive(Clone, Debug)]
+pub struct ParticleCurve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: InterpolateTrait + Copy> ParticleCurve<T> {
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// Keys do not need to be sorted.
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        assert!(!keys.is_empty(), "A ParticleCurve needs at least one key");
+        keys.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { keys }
+    }
+
+    /// Linearly interpolates between the keys on either side of `life`.
+    pub fn sample(&self, life: f32) -> T {
+        let after = self.keys.iter().position(|(key_life, _)| *key_life > life);
+        match after {
+            Some(0) => self.keys[0].1,
+            Some(i) => {
+                let (life0, value0) = self.keys[i - 1];
+                let (life1, value1) = self.keys[i];
+                value0.interpolate(&value1, (life - life0) / (life1 - life0))
+            }
+            None => self.keys[self.keys.len() - 1].1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+    scale: f32,
+}
+
+/// Spawns and simulates particles. All of an emitter's particles are drawn in a single instanced draw call.
+/// Particles are simulated in world space so moving the emitter doesn't move existing particles.
+#[derive(Component, Clone)]
+pub struct ParticleEmitter {
+    /// Particles spawned per second at the emitter's position.
+    pub rate: f32,
+    /// How many seconds particles live for.
+    pub lifetime: f32,
+    /// Each particle's lifetime is randomly shortened by up to this fraction.
+    pub lifetime_randomness: f32,
+    /// The initial velocity of each particle.
+    pub velocity: Vec3,
+    /// A random velocity of up to this length is added to each particle's initial velocity.
+    pub velocity_randomness: f32,
+    /// Particles spawn at a random point within this distance from where they're emitted.
+    pub spawn_radius: f32,
+    pub gravity: Vec3,
+    /// How much of its velocity a particle loses per second. 0.0 is no drag.
+    pub drag: f32,
+    pub color_over_life: ParticleCurve<Color>,
+    pub size_over_life: ParticleCurve<f32>,
+    /// New particles aren't spawned while this many are alive.
+    pub max_particles: usize,
+    particles: Vec<Particle>,
+    pending_burst: usize,
+    spawn_accumulator: f32,
+    random: Random,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            lifetime: 1.0,
+            lifetime_randomness: 0.0,
+            velocity: Vec3::ZERO,
+            velocity_randomness: 0.0,
+            spawn_radius: 0.0,
+            gravity: Vec3::ZERO,
+            drag: 0.0,
+            color_over_life: ParticleCurve::constant(Color::WHITE),
+            size_over_life: ParticleCurve::constant(1.0),
+            max_particles: 1000,
+            particles: Vec::new(),
+            pending_burst: 0,
+            spawn_accumulator: 0.0,
+            random: Random::new(),
+        }
+    }
+}
+
+impl ParticleEmitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns `count` particles at the emitter's position on its next update.
+    pub fn burst(&mut self, count: usize) {
+        self.pending_burst += count;
+    }
+
+    /// Immediately spawns `count` particles at `position`.
+    /// `scale` multiplies the particles' size, spawn radius, and initial velocity.
+    pub fn burst_at(&mut self, position: Vec3, count: usize, scale: f32) {
+        for _ in 0..count {
+            self.spawn_particle(position, scale);
+        }
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending_burst = 0;
+    }
+
+    fn spawn_particle(&mut self, position: Vec3, scale: f32) {
+        if self.particles.len() >= self.max_particles {
+            return;
+        }
+        let offset: Vec3 = self.random.point_in_unit_sphere();
+        let random_velocity: Vec3 = self.random.point_in_unit_sphere();
+        let lifetime = self.lifetime * (1.0 - self.random.f32() * self.lifetime_randomness);
+        self.particles.push(Particle {
+            position: position + offset * self.spawn_radius * scale,
+            velocity: (self.velocity + random_velocity * self.velocity_randomness) * scale,
+            age: 0.0,
+            lifetime,
+            scale,
+        });
+    }
+
+    /// Spawns new particles, moves existing particles, and removes expired ones.
+    pub fn update(&mut self, position: Vec3, delta_seconds: f32) {
+        for _ in 0..std::mem::take(&mut self.pending_burst) {
+            self.spawn_particle(position, 1.0);
+        }
+
+        self.spawn_accumulator += self.rate * delta_seconds;
+        while self.spawn_accumulator >= 1.0 {
+            self.spawn_accumulator -= 1.0;
+            self.spawn_particle(position, 1.0);
+        }
+
+        let drag = (1.0 - self.drag * delta_seconds).max(0.0);
+        for particle in &mut self.particles {
+            particle.age += delta_seconds;
+            particle.velocity += self.gravity * delta_seconds;
+            particle.velocity *= drag;
+            particle.position += particle.velocity * delta_seconds;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+}
+
+#[test]
+fn particle_curve_sample() {
+    let curve = ParticleCurve::new(vec![(1.0, 0.0), (0.0, 2.0), (0.5, 1.0)]);
+    assert_eq!(curve.sample(-1.0), 2.0);
+    assert_eq!(curve.sample(0.25), 1.5);
+    assert_eq!(curve.sample(0.75), 0.5);
+    assert_eq!(curve.sample(2.0), 0.0);
+}
+
+#[test]
+fn particles_expire() {
+    let mut emitter = ParticleEmitter {
+        rate: 10.0,
+        lifetime: 1.0,
+        ..Default::default()
+    };
+    emitter.burst(5);
+    emitter.update(Vec3::ZERO, 0.5);
+    assert_eq!(emitter.particle_count(), 10);
+    emitter.rate = 0.0;
+    emitter.update(Vec3::ZERO, 0.75);
+    assert_eq!(emitter.particle_count(), 0);
+}
+
+/// Runs each fixed update so particles move at the same speed at any frame rate.
+pub fn update_particle_emitters(
+    time: &Time,
+    mut emitters: Query<(&GlobalTransform, &mut ParticleEmitter)>,
+) {
+    let delta_seconds = time.fixed_time_step as f32;
+    for (transform, emitter) in &mut emitters {
+        emitter.update(transform.position, delta_seconds);
+    }
+}
+
+/// An emitter's particles uploaded to the GPU for this frame.
+/// The buffers are reused by the next frame and only replaced when they need to grow.
+pub(super) struct ParticleDraw {
+    /// xyz is each particle's position and w is its size.
+    positions_and_sizes: DataBuffer<Vec4>,
+    colors: DataBuffer<Vec4>,
+    /// How many particles the buffers have room for.
+    capacity: usize,
+    count: u32,
+}
+
+impl ParticleDraw {
+    fn new(graphics: &mut Graphics) -> Self {
+        Self {
+            positions_and_sizes: graphics.context.new_data_buffer(&[]).unwrap(),
+            colors: graphics.context.new_data_buffer(&[]).unwrap(),
+            capacity: 0,
+            count: 0,
+        }
+    }
+
+    fn upload(&mut self, graphics: &mut Graphics, positions_and_sizes: &[Vec4], colors: &[Vec4]) {
+        let count = positions_and_sizes.len();
+        if count > self.capacity {
+            // Leave room to grow so the buffers aren't replaced every time a particle spawns.
+            self.capacity = count.next_power_of_two();
+            let empty = vec![Vec4::ZERO; self.capacity];
+            let old_positions_and_sizes = std::mem::replace(
+                &mut self.positions_and_sizes,
+                graphics.context.new_data_buffer(&empty).unwrap(),
+            );
+            let old_colors = std::mem::replace(
+                &mut self.colors,
+                graphics.context.new_data_buffer(&empty).unwrap(),
+            );
+            graphics.context.delete_data_buffer(old_positions_and_sizes);
+            graphics.context.delete_data_buffer(old_colors);
+        }
+        graphics
+            .context
+            .update_data_buffer(&self.positions_and_sizes, positions_and_sizes);
+        graphics.context.update_data_buffer(&self.colors, colors);
+        self.count = count as u32;
+    }
+}
+
+/// Uploads the particles of each emitter to instance buffers.
+pub fn prepare_particles(
+    graphics: &mut Graphics,
+    renderer_info: &mut RendererInfo,
+    emitters: Query<&ParticleEmitter>,
+) {
+    let mut positions_and_sizes = Vec::new();
+    let mut colors = Vec::new();
+    let mut draw_count = 0;
+    for emitter in &emitters {
+        if emitter.particles.is_empty() {
+            continue;
+        }
+        positions_and_sizes.clear();
+        colors.clear();
+        for particle in &emitter.particles {
+            let life = particle.age / particle.lifetime;
+            let size = emitter.size_over_life.sample(life) * particle.scale;
+            positions_and_sizes.push(particle.position.extend(size));
+            colors.push(
+                emitter
+                    .color_over_life
+                    .sample(life)
+                    .to_rgb_color(color_spaces::LINEAR_SRGB),
+            );
+        }
+        if renderer_info.particle_draws.len() == draw_count {
+            renderer_info
+                .particle_draws
+                .push(ParticleDraw::new(graphics));
+        }
+        renderer_info.particle_draws[draw_count].upload(graphics, &positions_and_sizes, &colors);
+        draw_count += 1;
+    }
+
+    // Keep the unused buffers around for when more emitters have particles.
+    for particle_draw in &mut renderer_info.particle_draws[draw_count..] {
+        particle_draw.count = 0;
+    }
+}
+
+impl<'a, 'b: 'a> Renderer<'a, 'b> {
+    /// Draws each emitter's particles as camera-facing quads.
+    /// Particles are blended and don't write to depth so they should be drawn last.
+    pub(super) fn render_particles(&mut self) {
+        if self.particle_draws.is_empty() {
+            return;
+        }
+        let mesh_assets = self.mesh_assets;
+        let gpu_mesh = match &mesh_assets.get(&Mesh::VERTICAL_QUAD).gpu_mesh {
+            Some(gpu_mesh) => gpu_mesh,
+            None => return,
+        };
+
+        let particle_shader = self.particle_shader;
+        #[cfg(not(feature = "xr"))]
+        let pipeline = &particle_shader.pipeline;
+        #[cfg(feature = "xr")]
+        let pipeline = if self.multiview_enabled {
+            particle_shader.multiview_pipeline.as_ref().unwrap()
+        } else {
+            &particle_shader.pipeline
+        };
+        self.current_pipeline = Some(pipeline);
+        self.render_pass.set_pipeline(pipeline);
+        self.render_pass.set_depth_mask(false);
+
+        let position_attribute = pipeline.get_vertex_attribute::<Vec3>("a_position").unwrap();
+        let texture_coordinate_attribute = pipeline
+            .get_vertex_attribute::<Vec2>("a_texture_coordinate")
+            .unwrap();
+        let position_and_size_attribute = pipeline
+            .get_vertex_attribute::<Vec4>("a_instance_position_size")
+            .unwrap();
+        let color_attribute = pipeline
+            .get_vertex_attribute::<Vec4>("a_instance_color")
+            .unwrap();
+
+        self.render_pass
+            .set_vertex_attribute(&position_attribute, Some(&gpu_mesh.positions));
+        self.render_pass.set_vertex_attribute(
+            &texture_coordinate_attribute,
+            gpu_mesh.texture_coordinates.as_ref(),
+        );
+
+        for (i, camera_info) in self.camera_info.iter().enumerate() {
+            self.bind_view(camera_info, i);
+        }
+
+        for particle_draw in self.particle_draws {
+            if particle_draw.count == 0 {
+                continue;
+            }
+            self.render_pass.set_instance_attribute(
+                &position_and_size_attribute,
+                Some(&particle_draw.positions_and_sizes),
+            );
+            self.render_pass
+                .set_instance_attribute(&color_attribute, Some(&particle_draw.colors));
+
+            if self.camera_info.len() == 1 || self.multiview_enabled {
+                self.render_pass.draw_triangles_instanced(
+                    gpu_mesh.triangle_count,
+                    &gpu_mesh.index_buffer,
+                    particle_draw.count,
+                );
+            } else {
+                // Render the particles for each view if we're rendering in XR without multiview.
+                for camera_info in self.camera_info.iter() {
+                    let size = camera_info.viewport.size();
+                    self.render_pass.set_viewport(
+                        camera_info.viewport.min.x as u32,
+                        camera_info.viewport.min.y as u32,
+                        size.x as u32,
+                        size.y as u32,
+                    );
+                    self.bind_view(camera_info, 0);
+                    self.render_pass.draw_triangles_instanced(
+                        gpu_mesh.triangle_count,
+                        &gpu_mesh.index_buffer,
+                        particle_draw.count,
+                    );
+                }
+            }
+        }
+
+        // Other pipelines may use these attribute slots for per-vertex data.
+        self.render_pass
+            .set_instance_attribute(&position_and_size_attribute, None);
+        self.render_pass
+            .set_instance_attribute(&color_attribute, None);
+        self.render_pass.set_depth_mask(true);
+
+        // The next material needs to rebind everything.
+        self.bound_mesh = None;
+        self.bound_shader = None;
+        self.material_handle = None;
+        self.pipeline_info = None;
+    }
+}
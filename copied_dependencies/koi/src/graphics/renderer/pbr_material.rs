@@ -1,17 +1,24 @@
 use crate::*;
 
-#[derive(Debug, Clone)]
+/// Textures and blending aren't reflected.
+#[derive(Debug, Clone, Reflect)]
 pub struct PBRProperties {
     pub base_color: Color,
+    #[reflect(skip)]
     pub base_color_texture: Option<Handle<Texture>>,
     pub metallic: f32,
     pub roughness: f32,
+    #[reflect(skip)]
     pub metallic_roughness_texture: Option<Handle<Texture>>,
     pub ambient: f32,
+    #[reflect(skip)]
     pub ambient_texture: Option<Handle<Texture>>,
     pub emissive: Vec3,
+    #[reflect(skip)]
     pub emissive_texture: Option<Handle<Texture>>,
+    #[reflect(skip)]
     pub normal_texture: Option<Handle<Texture>>,
+    #[reflect(skip)]
     pub blending: Option<(BlendFactor, BlendFactor)>,
 }
 
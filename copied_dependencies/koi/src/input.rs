@@ -1,5 +1,8 @@
 use core::ops::Deref;
+use kapp::{Event, Key, PointerButton};
 use kecs::*;
+use kmath::*;
+use kserde::*;
 
 #[derive(NotCloneComponent)]
 pub struct Input(pub(crate) kapp::StateTracker);
@@ -15,4 +18,208 @@ impl Input {
     pub(crate) fn new() -> Self {
         Self(kapp::StateTracker::new())
     }
+
+    /// The first key or pointer button pressed since the last frame, if any.
+    /// Useful for letting players pick a new binding for an [InputActions] action.
+    pub fn binding_pressed(&self) -> Option<InputBinding> {
+        self.all_events_since_last_frame()
+            .iter()
+            .find_map(|event| match event {
+                Event::KeyDown { key, .. } => Some(InputBinding::Key(*key)),
+                Event::PointerDown { button, .. } => Some(InputBinding::PointerButton(*button)),
+                _ => None,
+            })
+    }
+}
+
+/// A key, button, or axis that triggers an action.
+#[derive(Clone, Copy, Debug, PartialEq, SerializeDeserialize)]
+pub enum InputBinding {
+    Key(Key),
+    PointerButton(PointerButton),
+    /// Four keys that act as a 2D axis, like WASD.
+    KeyAxis {
+        up: Key,
+        down: Key,
+        left: Key,
+        right: Key,
+    },
+}
+
+impl InputBinding {
+    fn held(&self, input: &Input) -> bool {
+        match *self {
+            Self::Key(key) => input.key(key),
+            Self::PointerButton(button) => input.pointer_button(button),
+            Self::KeyAxis { .. } => self.axis(input) != Vec2::ZERO,
+        }
+    }
+
+    fn pressed(&self, input: &Input) -> bool {
+        match *self {
+            Self::Key(key) => input.key_down(key),
+            Self::PointerButton(button) => input.pointer_button_down(button),
+            Self::KeyAxis {
+                up,
+                down,
+                left,
+                right,
+            } => [up, down, left, right]
+                .iter()
+                .any(|key| input.key_down(*key)),
+        }
+    }
+
+    fn axis(&self, input: &Input) -> Vec2 {
+        match *self {
+            Self::KeyAxis {
+                up,
+                down,
+                left,
+                right,
+            } => {
+                let value = |key| if input.key(key) { 1.0 } else { 0.0 };
+                Vec2::new(value(right) - value(left), value(up) - value(down))
+            }
+            _ => Vec2::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Debug, SerializeDeserialize)]
+struct InputAction {
+    name: String,
+    bindings: Vec<InputBinding>,
+}
+
+/// Maps named actions like "jump" or "move" to keys, pointer buttons, and axes.
+/// Gameplay queries actions instead of raw keys so players can rebind controls at runtime.
+/// Bindings can be saved and loaded with kserde's `to_json` and `from_json`.
+#[derive(Component, Clone, Debug, Default, SerializeDeserialize)]
+pub struct InputActions {
+    actions: Vec<InputAction>,
+}
+
+impl InputActions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to the action, creating the action if it doesn't exist.
+    pub fn bind(&mut self, action: &str, binding: InputBinding) {
+        match self.actions.iter_mut().find(|a| a.name == action) {
+            Some(action) => {
+                if !action.bindings.contains(&binding) {
+                    action.bindings.push(binding)
+                }
+            }
+            None => self.actions.push(InputAction {
+                name: action.into(),
+                bindings: vec![binding],
+            }),
+        }
+    }
+
+    /// Replaces all of the action's bindings.
+    pub fn set_bindings(&mut self, action: &str, bindings: Vec<InputBinding>) {
+        self.unbind(action);
+        self.actions.push(InputAction {
+            name: action.into(),
+            bindings,
+        });
+    }
+
+    /// Removes the action and all of its bindings.
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.retain(|a| a.name != action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[InputBinding] {
+        self.actions
+            .iter()
+            .find(|a| a.name == action)
+            .map(|a| a.bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Names of all the actions that have bindings.
+    pub fn action_names(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|a| a.name.as_str())
+    }
+
+    /// Replaces the bindings of each action in `other`. Actions `other` doesn't have are kept.
+    /// Useful for applying saved bindings on top of defaults.
+    pub fn apply(&mut self, other: &InputActions) {
+        for action in &other.actions {
+            self.set_bindings(&action.name, action.bindings.clone());
+        }
+    }
+
+    /// Returns true while any of the action's bindings are held.
+    pub fn held(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action).iter().any(|b| b.held(input))
+    }
+
+    /// Returns true if any of the action's bindings were pressed since the last frame.
+    pub fn pressed(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action).iter().any(|b| b.pressed(input))
+    }
+
+    /// The sum of the action's axis bindings, clamped to a length of 1.0.
+    pub fn axis(&self, input: &Input, action: &str) -> Vec2 {
+        let axis = self
+            .bindings(action)
+            .iter()
+            .fold(Vec2::ZERO, |axis, b| axis + b.axis(input));
+        if axis.length() > 1.0 {
+            axis.normalized()
+        } else {
+            axis
+        }
+    }
+}
+
+#[test]
+fn input_actions() {
+    let mut input = Input::new();
+    let mut actions = InputActions::new();
+    actions.bind("jump", InputBinding::Key(Key::Space));
+    actions.bind(
+        "move",
+        InputBinding::KeyAxis {
+            up: Key::W,
+            down: Key::S,
+            left: Key::A,
+            right: Key::D,
+        },
+    );
+
+    input.0.handle_event(&Event::KeyDown {
+        key: Key::Space,
+        timestamp: std::time::Duration::ZERO,
+    });
+    input.0.handle_event(&Event::KeyDown {
+        key: Key::D,
+        timestamp: std::time::Duration::ZERO,
+    });
+    assert!(actions.pressed(&input, "jump"));
+    assert!(actions.held(&input, "jump"));
+    assert_eq!(actions.axis(&input, "move"), Vec2::X);
+
+    actions.set_bindings("jump", vec![InputBinding::Key(Key::Return)]);
+    assert!(!actions.held(&input, "jump"));
+    assert_eq!(input.binding_pressed(), Some(InputBinding::Key(Key::Space)));
+}
+
+#[test]
+fn input_actions_json() {
+    let mut actions = InputActions::new();
+    actions.bind(
+        "grapple",
+        InputBinding::PointerButton(PointerButton::Secondary),
+    );
+    actions.bind("grapple", InputBinding::Key(Key::E));
+
+    let loaded = InputActions::from_json(&actions.to_json()).unwrap();
+    assert_eq!(loaded.bindings("grapple"), actions.bindings("grapple"));
 }
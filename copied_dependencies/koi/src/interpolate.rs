@@ -5,3 +5,15 @@ pub trait InterpolateTrait {
 pub fn smooth_step(amount: f32) -> f32 {
     amount * amount * (3.0 - 2.0 * amount)
 }
+
+impl InterpolateTrait for f32 {
+    fn interpolate(&self, other: &Self, amount: f32) -> Self {
+        self + (other - self) * amount
+    }
+}
+
+impl InterpolateTrait for kcolor::Color {
+    fn interpolate(&self, other: &Self, amount: f32) -> Self {
+        kcolor::Color::interpolate(*self, *other, amount)
+    }
+}
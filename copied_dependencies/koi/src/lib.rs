@@ -3,6 +3,7 @@ use std::ops::{Deref, DerefMut};
 pub use kcolor::*;
 pub use kecs::hierarchy::HierarchyNode;
 pub use kecs::*;
+pub use kreflect::{FieldInfo, Reflect, ReflectError, ReflectFieldTrait, ReflectValue};
 pub use kserde::*;
 
 pub use klog::*;
@@ -32,6 +33,9 @@ pub use temporary::*;
 mod input;
 pub use input::*;
 
+mod replay;
+pub use replay::*;
+
 mod world_assets;
 pub use world_assets::*;
 
@@ -97,11 +101,16 @@ pub struct App {
 
 pub type KappApplication = NotSendSync<kapp::Application>;
 
+/// Systems for each stage of the app's loop.
+/// Within a stage systems run in parallel on `ktasks` workers where their component access allows.
+/// See [kecs::Scheduler] for the ordering rules.
 #[derive(Default)]
 pub struct Plugin {
     pub setup_systems: Vec<System>,
     pub pre_fixed_update_systems: Vec<System>,
     pub fixed_update_systems: Vec<System>,
+    /// Runs after every fixed update, once all of the fixed update systems have run.
+    pub post_fixed_update_systems: Vec<System>,
     pub pre_draw_systems: Vec<System>,
     pub draw_systems: Vec<System>,
     pub end_of_frame_systems: Vec<System>,
@@ -115,6 +124,7 @@ impl Plugin {
             mut setup_systems,
             mut pre_fixed_update_systems,
             mut fixed_update_systems,
+            mut post_fixed_update_systems,
             mut pre_draw_systems,
             mut draw_systems,
             mut end_of_frame_systems,
@@ -126,6 +136,8 @@ impl Plugin {
         self.pre_fixed_update_systems
             .append(&mut pre_fixed_update_systems);
         self.fixed_update_systems.append(&mut fixed_update_systems);
+        self.post_fixed_update_systems
+            .append(&mut post_fixed_update_systems);
         self.pre_draw_systems.append(&mut pre_draw_systems);
         self.draw_systems.append(&mut draw_systems);
         self.end_of_frame_systems.append(&mut end_of_frame_systems);
@@ -135,6 +147,15 @@ impl Plugin {
     }
 }
 
+/// Adds an [Events] channel for `T` that systems can use with [EventWriter] and [EventReader].
+/// Events are kept for two frames so every system gets to read them.
+pub fn events_plugin<T: Send + Sync + 'static>() -> Plugin {
+    Plugin {
+        setup_systems: vec![(|world: &mut World| world.add_events::<T>()).system()],
+        ..Default::default()
+    }
+}
+
 pub enum Event {
     FixedUpdate,
     Draw,
@@ -232,11 +253,7 @@ impl App {
         ktasks::create_workers();
 
         let mut world = World::new();
-        world.spawn((Name("Commands".into()), Commands::new()));
-        // Setup input
-        let input_entity = world.spawn((Name("Input".into()), Input::new()));
-
-        let kapp_events_entity = world.spawn((Name("KappEvents".into()), KappEvents(Vec::new())));
+        let (input_entity, replay_entity, kapp_events_entity) = spawn_engine_components(&mut world);
 
         // For now `kapp` is integrated directly into `koi`
         let (kapp_app, kapp_event_loop) = kapp::initialize();
@@ -261,26 +278,12 @@ impl App {
 
         let window_entity = world.spawn((Name("Window".into()), NotSendSync::new(window)));
 
-        for setup_system in &mut self.systems.setup_systems {
-            setup_system.run(&mut world)
-        }
+        run_systems(&mut world, &mut self.systems.setup_systems);
 
         // Setup time tracking
         let start = Instant::now();
         let time_acumulator = 0.0;
-
-        // Hard-coded to 60 fixed updates per second for now.
-        let fixed_time_step = 1.0 / 60.0;
-
-        world.spawn((
-            Name("Time".into()),
-            Time {
-                // Set the delta_time to fixed_time_delta so that a fixed update runs for the first frame.
-                delta_seconds_f64: fixed_time_step,
-                fixed_time_step,
-                discontinuity: false,
-            },
-        ));
+        let fixed_time_step = spawn_time(&mut world);
 
         let run_system = Box::new(setup_and_run_function(&mut world));
 
@@ -292,7 +295,8 @@ impl App {
             fixed_time_step,
             run_system,
             input_entity,
-            window_entity,
+            replay_entity,
+            window_entity: Some(window_entity),
             kapp_events_entity,
         };
         kapp_event_loop.run(move |event| {
@@ -308,6 +312,104 @@ impl App {
             }
         })
     }
+
+    /// Runs the app without a window for `ticks` fixed updates and returns its [World].
+    /// This lets tests run gameplay systems and then assert on the resulting state.
+    ///
+    /// `input` is played back as if it were live input so tests can script what the player does.
+    /// Only fixed updates run: `Event::Draw` is never sent and draw systems don't run.
+    /// Enable the `headless` feature so graphics don't need a window or GPU.
+    ///
+    /// Playing `input` seeds `Random::new` for the whole process
+    /// so headless runs that depend on randomness shouldn't run in parallel.
+    pub fn run_headless<S: FnMut(Event, &mut World) -> bool + 'static>(
+        mut self,
+        ticks: u32,
+        input: Option<Replay>,
+        setup_and_run_function: impl FnOnce(&mut World) -> S,
+    ) -> World {
+        let mut world = World::new();
+        let (input_entity, replay_entity, kapp_events_entity) = spawn_engine_components(&mut world);
+
+        // Start playback before setup so any `Random`s created during setup are seeded.
+        if let Some(input) = input {
+            world
+                .get_component_mut::<InputReplay>(replay_entity)
+                .unwrap()
+                .play(input);
+        }
+
+        run_systems(&mut world, &mut self.systems.setup_systems);
+
+        let fixed_time_step = spawn_time(&mut world);
+        let run_system = Box::new(setup_and_run_function(&mut world));
+
+        let mut koi_state = KoiState {
+            world,
+            systems: self.systems,
+            start: Instant::now(),
+            time_acumulator: 0.0,
+            fixed_time_step,
+            run_system,
+            input_entity,
+            replay_entity,
+            window_entity: None,
+            kapp_events_entity,
+        };
+
+        // Worker threads aren't started so tasks (like asset loading) run here, in order.
+        for _ in 0..ticks {
+            ktasks::run_tasks_unless_there_are_workers();
+            run_systems(
+                &mut koi_state.world,
+                &mut koi_state.systems.pre_fixed_update_systems,
+            );
+            koi_state.fixed_update();
+            apply_commands(&mut koi_state.world);
+            koi_state
+                .world
+                .get_component_mut::<KappEvents>(koi_state.kapp_events_entity)
+                .unwrap()
+                .clear();
+            koi_state.world.clear_trackers();
+        }
+        koi_state.world
+    }
+}
+
+/// Spawns the components every app has and returns the `Input`, `InputReplay`,
+/// and `KappEvents` entities.
+fn spawn_engine_components(world: &mut World) -> (Entity, Entity, Entity) {
+    world.spawn((Name("Commands".into()), Commands::new()));
+    // Setup input
+    let input_entity = world.spawn((Name("Input".into()), Input::new()));
+    world.spawn((Name("InputActions".into()), InputActions::new()));
+
+    let mut reflect_registry = ReflectRegistry::new();
+    reflect_registry.register::<Transform>();
+    world.spawn((Name("ReflectRegistry".into()), reflect_registry));
+    let replay_entity = world.spawn((Name("InputReplay".into()), InputReplay::new()));
+
+    let kapp_events_entity = world.spawn((Name("KappEvents".into()), KappEvents(Vec::new())));
+    (input_entity, replay_entity, kapp_events_entity)
+}
+
+/// Spawns `Time` and returns the fixed time step.
+fn spawn_time(world: &mut World) -> f64 {
+    // Hard-coded to 60 fixed updates per second for now.
+    let fixed_time_step = 1.0 / 60.0;
+
+    world.spawn((
+        Name("Time".into()),
+        Time {
+            // Set the delta_time to fixed_time_delta so that a fixed update runs for the first frame.
+            delta_seconds_f64: fixed_time_step,
+            fixed_time_step,
+            discontinuity: false,
+            interpolation_alpha: 1.0,
+        },
+    ));
+    fixed_time_step
 }
 
 pub struct KoiState {
@@ -318,7 +420,9 @@ pub struct KoiState {
     pub fixed_time_step: f64,
     pub run_system: Box<dyn FnMut(Event, &mut World) -> bool>,
     pub input_entity: Entity,
-    pub window_entity: Entity,
+    pub replay_entity: Entity,
+    /// `None` for headless apps.
+    pub window_entity: Option<Entity>,
     pub kapp_events_entity: Entity,
 }
 
@@ -326,6 +430,20 @@ impl KoiState {
     fn handle_event(&mut self, event: KappEvent) {
         ktasks::run_tasks_unless_there_are_workers();
 
+        let input_replay = self
+            .world
+            .get_component_mut::<InputReplay>(self.replay_entity)
+            .unwrap();
+        // While a replay plays its events are delivered by `draw` instead.
+        if input_replay.ignores_live_event(&event) {
+            return;
+        }
+        input_replay.record(&event);
+
+        self.deliver_event(event);
+    }
+
+    fn deliver_event(&mut self, event: KappEvent) {
         // Run user callback and give it a chance to consume the event.
         if (self.run_system)(crate::Event::KappEvent(event.clone()), &mut self.world) {
             return;
@@ -356,9 +474,7 @@ impl KoiState {
                 .unwrap()
                 .push(event.clone());
 
-            for system in &mut self.systems.on_kapp_events {
-                system.run(&mut self.world)
-            }
+            run_systems(&mut self.world, &mut self.systems.on_kapp_events);
 
             std::mem::swap(&mut self.systems.additional_control_flow, &mut swap);
 
@@ -368,11 +484,44 @@ impl KoiState {
         }
     }
 
-    pub fn draw(&mut self) {
-        for system in &mut self.systems.pre_fixed_update_systems {
-            system.run(&mut self.world)
+    fn fixed_update(&mut self) {
+        let replayed_events = self
+            .world
+            .get_component_mut::<InputReplay>(self.replay_entity)
+            .unwrap()
+            .take_events_for_tick(self.fixed_time_step);
+        for event in replayed_events {
+            self.deliver_event(event);
         }
 
+        (self.run_system)(crate::Event::FixedUpdate, &mut self.world);
+        apply_commands(&mut self.world);
+        run_systems(&mut self.world, &mut self.systems.fixed_update_systems);
+
+        // Clear Input after each FixedUpdate. This means if there are multiple FixedUpdates per frame
+        // only the first will receive input events.
+        // Todo: It would be better if Input was updated based on an event's timestamp. Each FixedUpdate would progress time
+        // and only events that occurred before that time would progress the input.
+        let input = &mut self
+            .world
+            .get_component_mut::<Input>(self.input_entity)
+            .unwrap()
+            .0;
+        input.clear();
+        input.reset_touch();
+
+        apply_commands(&mut self.world);
+        run_systems(&mut self.world, &mut self.systems.post_fixed_update_systems);
+        apply_commands(&mut self.world);
+        self.world
+            .get_component_mut::<InputReplay>(self.replay_entity)
+            .unwrap()
+            .advance_tick();
+    }
+
+    pub fn draw(&mut self) {
+        run_systems(&mut self.world, &mut self.systems.pre_fixed_update_systems);
+
         let elapsed = self.start.elapsed();
         let time_elapsed_seconds = elapsed.as_secs_f64();
         //klog::log!("TIME ELAPSED: {:?}", elapsed.as_millis());
@@ -390,49 +539,35 @@ impl KoiState {
             self.time_acumulator = self.fixed_time_step;
         }
 
+        let ran_fixed_update = self.time_acumulator >= self.fixed_time_step;
         while self.time_acumulator >= self.fixed_time_step {
-            (self.run_system)(crate::Event::FixedUpdate, &mut self.world);
-            apply_commands(&mut self.world);
-            for system in &mut self.systems.fixed_update_systems {
-                system.run(&mut self.world);
-
-                // Clear Input after each FixedUpdate. This means if there are multiple FixedUpdates per frame
-                // only the first will receive input events.
-                // Todo: It would be better if Input was updated based on an event's timestamp. Each FixedUpdate would progress time
-                // and only events that occurred before that time would progress the input.
-                let input = &mut self
-                    .world
-                    .get_component_mut::<Input>(self.input_entity)
-                    .unwrap()
-                    .0;
-                input.clear();
-                input.reset_touch();
-            }
-            apply_commands(&mut self.world);
+            self.fixed_update();
             self.time_acumulator -= self.fixed_time_step;
         }
+        self.world.get_singleton::<Time>().interpolation_alpha =
+            (self.time_acumulator / self.fixed_time_step) as f32;
 
         apply_commands(&mut self.world);
-        for system in &mut self.systems.pre_draw_systems {
-            system.run(&mut self.world)
-        }
+        run_systems(&mut self.world, &mut self.systems.pre_draw_systems);
 
         (self.run_system)(crate::Event::Draw, &mut self.world);
         apply_commands(&mut self.world);
-        for system in &mut self.systems.draw_systems {
-            system.run(&mut self.world)
-        }
+        run_systems(&mut self.world, &mut self.systems.draw_systems);
         apply_commands(&mut self.world);
 
         // Run systems after the last draw.
-        for system in &mut self.systems.end_of_frame_systems {
-            system.run(&mut self.world)
-        }
+        run_systems(&mut self.world, &mut self.systems.end_of_frame_systems);
         apply_commands(&mut self.world);
 
         self.world
             .get_component_mut::<KappEvents>(self.kapp_events_entity)
             .unwrap()
             .clear();
+
+        // Removed components are kept until the second clear,
+        // so only clear on frames where `FixedUpdate` systems got to see them.
+        if ran_fixed_update {
+            self.world.clear_trackers();
+        }
     }
 }
@@ -2,10 +2,9 @@ use kecs::*;
 use std::ops::{Deref, DerefMut};
 
 /// Ensure a component can only be accessed on the thread that created it.
-/// Presently everything runs on the main thread, so this isn't yet complete.
+/// Systems that access a [NotSendSync] run on the main thread instead of on a worker.
 // I'm not particularly fond of this approach, but it seems reasonable for now.
 // For now label all components that use this as not Clone.
-#[derive(NotCloneComponent)]
 pub struct NotSendSync<T: 'static> {
     value: T,
     thread_id: std::thread::ThreadId,
@@ -14,6 +13,10 @@ pub struct NotSendSync<T: 'static> {
 unsafe impl<T> Send for NotSendSync<T> {}
 unsafe impl<T> Sync for NotSendSync<T> {}
 
+impl<T: 'static> ComponentTrait for NotSendSync<T> {
+    const MAIN_THREAD_ONLY: bool = true;
+}
+
 impl<T> NotSendSync<T> {
     pub fn new(t: T) -> Self {
         Self {
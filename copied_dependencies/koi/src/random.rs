@@ -1,9 +1,20 @@
 use core::ops::Range;
 use kmath::{Quat, Vector};
+use std::sync::Mutex;
 use std::usize;
 
 use crate::*;
 
+/// When set `Random::new` draws its seeds from here instead of the system time.
+static SEED_SOURCE: Mutex<Option<oorandom::Rand32>> = Mutex::new(None);
+
+/// Makes every following `Random::new` produce the same sequence of seeds for the same `seed`.
+/// This is used by [InputReplay] so recorded runs can be played back.
+/// Pass `None` to go back to seeding from the system time.
+pub fn set_random_seed(seed: Option<u64>) {
+    *SEED_SOURCE.lock().unwrap() = seed.map(oorandom::Rand32::new);
+}
+
 #[derive(ManualSerdeComponent, Clone)]
 pub struct Random {
     random_number_generator: oorandom::Rand32,
@@ -39,7 +50,12 @@ impl Default for Random {
 
 impl Random {
     /// Create a new `Random` with a seed based on the current system time.
+    /// If [set_random_seed] was called the seed comes from there instead.
     pub fn new() -> Self {
+        if let Some(seed_source) = SEED_SOURCE.lock().unwrap().as_mut() {
+            return Self::new_with_seed(seed_source.rand_u32() as u64);
+        }
+
         #[cfg(not(target_arch = "wasm32"))]
         {
             let start = std::time::SystemTime::now();
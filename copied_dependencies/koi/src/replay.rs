@@ -0,0 +1,416 @@
+//! Records the input delivered to the game so a run can be played back exactly.
+//!
+//! Every input event is tagged with the fixed update it was delivered before. During playback
+//! live input is ignored and the recorded events are delivered on the same fixed updates instead.
+//! `Random::new` is seeded from the replay's seed so random numbers match as well.
+use std::time::Duration;
+
+use crate::*;
+
+/// An input event that can be saved in a [Replay].
+/// Timestamps aren't stored because fixed updates don't depend on them.
+#[derive(SerializeDeserialize, Clone, Debug, PartialEq)]
+pub enum RecordedEvent {
+    KeyDown {
+        key: Key,
+    },
+    KeyUp {
+        key: Key,
+    },
+    KeyRepeat {
+        key: Key,
+    },
+    CharacterReceived {
+        character: u32,
+    },
+    PointerMoved {
+        x: f64,
+        y: f64,
+        source: PointerSource,
+        id: usize,
+    },
+    MouseMotion {
+        delta_x: f64,
+        delta_y: f64,
+    },
+    PointerDown {
+        x: f64,
+        y: f64,
+        source: PointerSource,
+        button: PointerButton,
+        id: usize,
+    },
+    PointerUp {
+        x: f64,
+        y: f64,
+        source: PointerSource,
+        button: PointerButton,
+        id: usize,
+    },
+    Scroll {
+        delta_x: f64,
+        delta_y: f64,
+        window_id: kapp::WindowId,
+    },
+    PinchGesture {
+        delta: f64,
+    },
+}
+
+impl RecordedEvent {
+    /// Returns `None` for events that aren't user input.
+    pub fn from_kapp_event(event: &KappEvent) -> Option<Self> {
+        Some(match *event {
+            KappEvent::KeyDown { key, .. } => Self::KeyDown { key },
+            KappEvent::KeyUp { key, .. } => Self::KeyUp { key },
+            KappEvent::KeyRepeat { key, .. } => Self::KeyRepeat { key },
+            KappEvent::CharacterReceived { character } => Self::CharacterReceived {
+                character: character as u32,
+            },
+            KappEvent::PointerMoved {
+                x, y, source, id, ..
+            } => Self::PointerMoved { x, y, source, id },
+            KappEvent::MouseMotion {
+                delta_x, delta_y, ..
+            } => Self::MouseMotion { delta_x, delta_y },
+            KappEvent::PointerDown {
+                x,
+                y,
+                source,
+                button,
+                id,
+                ..
+            } => Self::PointerDown {
+                x,
+                y,
+                source,
+                button,
+                id,
+            },
+            KappEvent::PointerUp {
+                x,
+                y,
+                source,
+                button,
+                id,
+                ..
+            } => Self::PointerUp {
+                x,
+                y,
+                source,
+                button,
+                id,
+            },
+            KappEvent::Scroll {
+                delta_x,
+                delta_y,
+                window_id,
+                ..
+            } => Self::Scroll {
+                delta_x,
+                delta_y,
+                window_id,
+            },
+            KappEvent::PinchGesture { delta, .. } => Self::PinchGesture { delta },
+            _ => return None,
+        })
+    }
+
+    pub fn to_kapp_event(&self, timestamp: Duration) -> KappEvent {
+        match *self {
+            Self::KeyDown { key } => KappEvent::KeyDown { key, timestamp },
+            Self::KeyUp { key } => KappEvent::KeyUp { key, timestamp },
+            Self::KeyRepeat { key } => KappEvent::KeyRepeat { key, timestamp },
+            Self::CharacterReceived { character } => KappEvent::CharacterReceived {
+                character: char::from_u32(character).unwrap_or(char::REPLACEMENT_CHARACTER),
+            },
+            Self::PointerMoved { x, y, source, id } => KappEvent::PointerMoved {
+                x,
+                y,
+                source,
+                id,
+                timestamp,
+            },
+            Self::MouseMotion { delta_x, delta_y } => KappEvent::MouseMotion {
+                delta_x,
+                delta_y,
+                timestamp,
+            },
+            Self::PointerDown {
+                x,
+                y,
+                source,
+                button,
+                id,
+            } => KappEvent::PointerDown {
+                x,
+                y,
+                source,
+                button,
+                id,
+                timestamp,
+            },
+            Self::PointerUp {
+                x,
+                y,
+                source,
+                button,
+                id,
+            } => KappEvent::PointerUp {
+                x,
+                y,
+                source,
+                button,
+                id,
+                timestamp,
+            },
+            Self::Scroll {
+                delta_x,
+                delta_y,
+                window_id,
+            } => KappEvent::Scroll {
+                delta_x,
+                delta_y,
+                window_id,
+                timestamp,
+            },
+            Self::PinchGesture { delta } => KappEvent::PinchGesture { delta, timestamp },
+        }
+    }
+}
+
+#[derive(SerializeDeserialize, Clone, Debug, PartialEq)]
+pub struct ReplayEvent {
+    /// The fixed update this event was delivered before.
+    /// Ticks are counted from when the recording started.
+    pub tick: u32,
+    pub event: RecordedEvent,
+}
+
+/// A recorded run that can be saved, attached to a bug report, and played back.
+#[derive(SerializeDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    /// Seeds `Random::new` for the duration of the run.
+    /// This is a `u32` because `kserde` stores numbers as `f64`s.
+    pub seed: u32,
+    /// Events ordered by tick.
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "could not parse replay")
+        })
+    }
+
+    /// The web doesn't have files to save replays to.
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self, _path: &str) -> std::io::Result<()> {
+        Err(Self::unsupported())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(_path: &str) -> std::io::Result<Self> {
+        Err(Self::unsupported())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn unsupported() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "replay files aren't supported on the web",
+        )
+    }
+}
+
+enum ReplayMode {
+    Off,
+    Recording(Replay),
+    Playing { replay: Replay, next_event: usize },
+}
+
+/// Controls recording and playback of input.
+/// There's a single `InputReplay` in the `World`.
+#[derive(NotCloneComponent)]
+pub struct InputReplay {
+    mode: ReplayMode,
+    tick: u32,
+}
+
+impl InputReplay {
+    pub(crate) fn new() -> Self {
+        Self {
+            mode: ReplayMode::Off,
+            tick: 0,
+        }
+    }
+
+    /// The number of fixed updates since recording or playback started.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Starts recording input and seeds `Random::new` with a fresh seed.
+    /// This should be called before the game creates any `Random`s.
+    pub fn start_recording(&mut self) {
+        let seed = Random::new().u32();
+        set_random_seed(Some(seed as u64));
+        self.tick = 0;
+        self.mode = ReplayMode::Recording(Replay {
+            seed,
+            events: Vec::new(),
+        });
+    }
+
+    /// The replay recorded so far, if recording.
+    pub fn recording(&self) -> Option<&Replay> {
+        match &self.mode {
+            ReplayMode::Recording(replay) => Some(replay),
+            _ => None,
+        }
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Replay> {
+        match std::mem::replace(&mut self.mode, ReplayMode::Off) {
+            ReplayMode::Recording(replay) => {
+                set_random_seed(None);
+                Some(replay)
+            }
+            mode => {
+                self.mode = mode;
+                None
+            }
+        }
+    }
+
+    /// Plays back `replay`. Live input is ignored until playback is stopped.
+    /// Like `start_recording` this should be called before the game creates any `Random`s.
+    pub fn play(&mut self, replay: Replay) {
+        set_random_seed(Some(replay.seed as u64));
+        self.tick = 0;
+        self.mode = ReplayMode::Playing {
+            replay,
+            next_event: 0,
+        };
+    }
+
+    pub fn stop_playing(&mut self) {
+        if self.is_playing() {
+            set_random_seed(None);
+            self.mode = ReplayMode::Off;
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.mode, ReplayMode::Playing { .. })
+    }
+
+    /// Returns true if playing and every recorded event has been delivered.
+    pub fn playback_finished(&self) -> bool {
+        match &self.mode {
+            ReplayMode::Playing { replay, next_event } => *next_event >= replay.events.len(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if a live event should be ignored because a replay is playing.
+    pub(crate) fn ignores_live_event(&self, event: &KappEvent) -> bool {
+        self.is_playing() && RecordedEvent::from_kapp_event(event).is_some()
+    }
+
+    pub(crate) fn record(&mut self, event: &KappEvent) {
+        if let ReplayMode::Recording(replay) = &mut self.mode {
+            if let Some(event) = RecordedEvent::from_kapp_event(event) {
+                replay.events.push(ReplayEvent {
+                    tick: self.tick,
+                    event,
+                });
+            }
+        }
+    }
+
+    /// Takes the recorded events that should be delivered before the current fixed update.
+    pub(crate) fn take_events_for_tick(&mut self, fixed_time_step: f64) -> Vec<KappEvent> {
+        let mut events = Vec::new();
+        if let ReplayMode::Playing { replay, next_event } = &mut self.mode {
+            let timestamp = Duration::from_secs_f64(self.tick as f64 * fixed_time_step);
+            while let Some(replay_event) = replay.events.get(*next_event) {
+                if replay_event.tick > self.tick {
+                    break;
+                }
+                events.push(replay_event.event.to_kapp_event(timestamp));
+                *next_event += 1;
+            }
+        }
+        events
+    }
+
+    pub(crate) fn advance_tick(&mut self) {
+        self.tick += 1;
+    }
+}
+
+#[test]
+fn replay_json_round_trip() {
+    let replay = Replay {
+        seed: 1234,
+        events: vec![
+            ReplayEvent {
+                tick: 0,
+                event: RecordedEvent::KeyDown { key: Key::W },
+            },
+            ReplayEvent {
+                tick: 3,
+                event: RecordedEvent::MouseMotion {
+                    delta_x: 1.5,
+                    delta_y: -2.0,
+                },
+            },
+            ReplayEvent {
+                tick: 3,
+                event: RecordedEvent::PointerDown {
+                    x: 10.0,
+                    y: 20.0,
+                    source: PointerSource::Mouse,
+                    button: PointerButton::Primary,
+                    id: 0,
+                },
+            },
+        ],
+    };
+    assert_eq!(Replay::from_json(&replay.to_json()), Some(replay));
+}
+
+#[test]
+fn replay_events_are_delivered_on_their_tick() {
+    let mut input_replay = InputReplay::new();
+    input_replay.start_recording();
+    input_replay.record(&KappEvent::KeyDown {
+        key: Key::Space,
+        timestamp: Duration::ZERO,
+    });
+    input_replay.advance_tick();
+    input_replay.advance_tick();
+    input_replay.record(&KappEvent::KeyUp {
+        key: Key::Space,
+        timestamp: Duration::ZERO,
+    });
+    let replay = input_replay.stop_recording().unwrap();
+
+    input_replay.play(replay);
+    assert_eq!(input_replay.take_events_for_tick(1.0 / 60.0).len(), 1);
+    input_replay.advance_tick();
+    assert!(input_replay.take_events_for_tick(1.0 / 60.0).is_empty());
+    input_replay.advance_tick();
+    assert_eq!(input_replay.take_events_for_tick(1.0 / 60.0).len(), 1);
+    assert!(input_replay.playback_finished());
+    input_replay.stop_playing();
+}
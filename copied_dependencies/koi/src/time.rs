@@ -6,4 +6,7 @@ pub struct Time {
     pub delta_seconds_f64: f64,
     pub fixed_time_step: f64,
     pub discontinuity: bool,
+    /// How far the current draw is between the previous fixed update (0.0) and the latest (1.0).
+    /// [Interpolated] entities are drawn blended by this amount.
+    pub interpolation_alpha: f32,
 }
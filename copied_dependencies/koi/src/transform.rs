@@ -12,10 +12,11 @@ pub fn transform_plugin() -> Plugin {
             update_global_transforms.system(),
             apply_commands.system(),
         ],
+        post_fixed_update_systems: vec![record_interpolated_transforms.system()],
         draw_systems: vec![
-            update_root_global_transforms.system(),
+            update_interpolated_root_global_transforms.system(),
             apply_commands.system(),
-            update_global_transforms.system(),
+            update_interpolated_global_transforms.system(),
             apply_commands.system(),
         ],
         ..Default::default()
@@ -32,7 +33,7 @@ impl Deref for GlobalTransform {
     }
 }
 
-#[derive(Clone, Copy, Debug, Component)]
+#[derive(Clone, Copy, Debug, SerdeComponent, Reflect)]
 pub struct Transform {
     /// Position relative to parent
     pub position: Vec3,
@@ -166,6 +167,56 @@ fn lerp_test() {
     println!("VALUE: {:?}", v0.lerp(v1, 0.5));
 }
 
+/// Marks an entity whose [Transform] is updated during fixed updates.
+/// When drawing, its [GlobalTransform] is blended between its [Transform]s from the last two fixed updates
+/// using [Time::interpolation_alpha]. This avoids judder on displays faster than the fixed update rate.
+#[derive(Clone, Copy, Debug, Default, Component)]
+pub struct Interpolated {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+}
+
+impl Interpolated {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw at `transform` without blending from the previous fixed update.
+    /// Use this after teleporting an entity.
+    pub fn snap(&mut self, transform: Transform) {
+        self.previous = Some(transform);
+        self.current = Some(transform);
+    }
+
+    /// The [Transform] to draw. `transform` is used until a fixed update has been recorded.
+    pub fn blended(&self, transform: &Transform, alpha: f32) -> Transform {
+        match (self.previous, self.current) {
+            (Some(previous), Some(current)) => previous.interpolate(&current, alpha),
+            _ => *transform,
+        }
+    }
+}
+
+/// Remembers the [Transform]s of [Interpolated] entities at the end of each fixed update.
+pub fn record_interpolated_transforms(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, interpolated) in query.iter_mut() {
+        interpolated.previous = Some(interpolated.current.unwrap_or(*transform));
+        interpolated.current = Some(*transform);
+    }
+}
+
+/// The local [Transform] to use, blended if an `interpolation_alpha` is passed in.
+fn drawn_transform(
+    transform: &Transform,
+    interpolated: Option<&Interpolated>,
+    interpolation_alpha: Option<f32>,
+) -> Transform {
+    match (interpolated, interpolation_alpha) {
+        (Some(interpolated), Some(alpha)) => interpolated.blended(transform, alpha),
+        _ => *transform,
+    }
+}
+
 impl Mul<Transform> for Transform {
     type Output = Transform;
     fn mul(self, rhs: Transform) -> Self::Output {
@@ -175,18 +226,50 @@ impl Mul<Transform> for Transform {
 
 /// Add [GlobalTransform]s to all root nodes without them.
 pub fn update_root_global_transforms(
+    commands: &mut Commands,
+    query: Query<(
+        &Transform,
+        Option<&Interpolated>,
+        Option<&mut GlobalTransform>,
+        Option<&mut HierarchyNode>,
+    )>,
+) {
+    update_root_global_transforms_inner(commands, query, None)
+}
+
+/// Like [update_root_global_transforms] but [Interpolated] entities use their blended [Transform].
+pub fn update_interpolated_root_global_transforms(
+    commands: &mut Commands,
+    query: Query<(
+        &Transform,
+        Option<&Interpolated>,
+        Option<&mut GlobalTransform>,
+        Option<&mut HierarchyNode>,
+    )>,
+    time: &Time,
+) {
+    update_root_global_transforms_inner(commands, query, Some(time.interpolation_alpha))
+}
+
+fn update_root_global_transforms_inner(
     commands: &mut Commands,
     mut query: Query<(
         &Transform,
+        Option<&Interpolated>,
         Option<&mut GlobalTransform>,
         Option<&mut HierarchyNode>,
     )>,
+    interpolation_alpha: Option<f32>,
 ) {
-    for (entity, (local_transform, global_transform, hierarchy_node)) in
+    for (entity, (local_transform, interpolated, global_transform, hierarchy_node)) in
         query.entities_and_components_mut()
     {
         if hierarchy_node.map_or(true, |h| h.parent().is_none()) {
-            let new_global_transform = GlobalTransform(*local_transform);
+            let new_global_transform = GlobalTransform(drawn_transform(
+                local_transform,
+                interpolated,
+                interpolation_alpha,
+            ));
             if let Some(global_transform) = global_transform {
                 *global_transform = new_global_transform;
             } else {
@@ -197,25 +280,61 @@ pub fn update_root_global_transforms(
 }
 
 pub fn update_global_transforms(
+    commands: &mut Commands,
+    query: Query<(
+        &HierarchyNode,
+        Option<&Transform>,
+        Option<&Interpolated>,
+        Option<&mut GlobalTransform>,
+    )>,
+) {
+    update_global_transforms_inner(commands, query, None)
+}
+
+/// Like [update_global_transforms] but [Interpolated] entities use their blended [Transform].
+pub fn update_interpolated_global_transforms(
+    commands: &mut Commands,
+    query: Query<(
+        &HierarchyNode,
+        Option<&Transform>,
+        Option<&Interpolated>,
+        Option<&mut GlobalTransform>,
+    )>,
+    time: &Time,
+) {
+    update_global_transforms_inner(commands, query, Some(time.interpolation_alpha))
+}
+
+fn update_global_transforms_inner(
     commands: &mut Commands,
     mut query: Query<(
         &HierarchyNode,
         Option<&Transform>,
+        Option<&Interpolated>,
         Option<&mut GlobalTransform>,
     )>,
+    interpolation_alpha: Option<f32>,
 ) {
     // It'd be nice to find a way to avoid this allocation
     let mut parents = Vec::new();
 
     // This is a bit inefficient in that all hierarchies are updated, regardless of if they changed.
-    for (e, (node, _local_transform, _global_transform)) in query.entities_and_components() {
+    for (e, (node, _local_transform, _interpolated, _global_transform)) in
+        query.entities_and_components()
+    {
         if node.parent().is_none() {
             parents.push(*e)
         }
     }
 
     for parent_entity in &parents {
-        update_descendent_transforms(commands, &mut query, *parent_entity, &Mat4::IDENTITY);
+        update_descendent_transforms(
+            commands,
+            &mut query,
+            *parent_entity,
+            &Mat4::IDENTITY,
+            interpolation_alpha,
+        );
     }
 }
 
@@ -224,16 +343,19 @@ fn update_descendent_transforms(
     query: &mut Query<(
         &HierarchyNode,
         Option<&Transform>,
+        Option<&Interpolated>,
         Option<&mut GlobalTransform>,
     )>,
     child_entity: Entity,
     parent_matrix: &Mat4,
+    interpolation_alpha: Option<f32>,
 ) {
-    if let Some((hierarchy_node, local_transform, global_transform)) =
+    if let Some((hierarchy_node, local_transform, interpolated, global_transform)) =
         query.get_entity_components_mut(child_entity)
     {
         let my_global_matrix = if let Some(local_transform) = local_transform {
-            *parent_matrix * local_transform.model()
+            *parent_matrix
+                * drawn_transform(local_transform, interpolated, interpolation_alpha).model()
         } else {
             *parent_matrix
         };
@@ -252,7 +374,13 @@ fn update_descendent_transforms(
         while let Some(child_entity) = child {
             let hierachy_node = query.get_entity_components_mut(child_entity).unwrap();
             child = *hierachy_node.0.previous_sibling();
-            update_descendent_transforms(commands, query, child_entity, &my_global_matrix);
+            update_descendent_transforms(
+                commands,
+                query,
+                child_entity,
+                &my_global_matrix,
+                interpolation_alpha,
+            );
         }
     }
 }
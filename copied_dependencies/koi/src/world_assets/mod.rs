@@ -12,7 +12,11 @@ pub use kgltf;
 pub fn world_assets_plugin() -> Plugin {
     Plugin {
         setup_systems: vec![setup_prefabs.system()],
-        pre_fixed_update_systems: vec![load_prefabs_system.system(), delayed_spawn_system.system()],
+        pre_fixed_update_systems: vec![
+            load_prefabs_system.system(),
+            load_scenes_system.system(),
+            delayed_spawn_system.system(),
+        ],
         ..Default::default()
     }
 }
@@ -20,6 +24,17 @@ pub fn world_assets_plugin() -> Plugin {
 fn setup_prefabs(world: &mut World) {
     let assets = Assets::<World>::new(World::new(), WorldLoader::new());
     world.spawn((Name("Assets<World>".into()), assets));
+
+    let mut component_registry = ComponentRegistry::new();
+    component_registry.register::<Transform>("Transform");
+    component_registry.register::<Name>("Name");
+    component_registry.register::<Handle<World>>("Handle<World>");
+    #[cfg(feature = "graphics")]
+    {
+        component_registry.register::<Handle<Mesh>>("Handle<Mesh>");
+        component_registry.register::<Handle<Material>>("Handle<Material>");
+    }
+    world.spawn((Name("ComponentRegistry".into()), component_registry));
 }
 
 #[allow(dead_code, unused_variables, unreachable_code)]
@@ -41,6 +56,17 @@ fn load_prefabs_system(
     } in messages.into_iter()
     {
         let world: Option<World> = match world_load_message_data {
+            // Scenes can contain [Handle]s to any type of asset,
+            // so they're deserialized by [load_scenes_system] which can access the whole [World].
+            PrefabLoadMessageData::Scene { path, source } => {
+                worlds.asset_loader.pending_scenes.push(PendingScene {
+                    path,
+                    source,
+                    handle,
+                    options,
+                });
+                continue;
+            }
             #[cfg(feature = "gltf")]
             PrefabLoadMessageData::GlTf {
                 path,
@@ -67,6 +93,41 @@ fn load_prefabs_system(
     }
 }
 
+/// Deserializes the `.scene.json` files received by [load_prefabs_system].
+/// Components are deserialized with the [ComponentRegistry] in the [World].
+fn load_scenes_system(world: &mut World) {
+    let pending_scenes = std::mem::take(
+        &mut world
+            .get_singleton::<Assets<World>>()
+            .asset_loader
+            .pending_scenes,
+    );
+    if pending_scenes.is_empty() {
+        return;
+    }
+
+    // Cloned because the [World] is also borrowed to load the scene's assets.
+    let component_registry = world.get_singleton::<ComponentRegistry>().clone();
+    for PendingScene {
+        path,
+        source,
+        handle,
+        options,
+    } in pending_scenes
+    {
+        if let Some(mut scene) = World::deserialize(&source, &component_registry, world) {
+            if let Some(run_on_world) = options.run_on_world {
+                run_on_world(&mut scene);
+            }
+            world
+                .get_singleton::<Assets<World>>()
+                .replace_placeholder(&handle, scene);
+        } else {
+            klog::log!("Could not deserialize scene: {:?}", path);
+        }
+    }
+}
+
 /// Spawns worlds as they load.
 /// Top-level nodes will have their parents set to the [Entity] with the [Handle<World>]
 /// This does not yet handle situations where the spawning worlds don't have [HierarchyNode]'s on their top-level components.
@@ -132,6 +193,10 @@ struct PrefabLoadMessage {
 }
 
 enum PrefabLoadMessageData {
+    Scene {
+        path: String,
+        source: String,
+    },
     #[cfg(feature = "gltf")]
     GlTf {
         path: String,
@@ -144,6 +209,15 @@ enum PrefabLoadMessageData {
 pub struct WorldLoader {
     sender: SyncGuard<mpsc::Sender<PrefabLoadMessage>>,
     receiver: SyncGuard<mpsc::Receiver<PrefabLoadMessage>>,
+    /// Scenes waiting for [load_scenes_system].
+    pending_scenes: Vec<PendingScene>,
+}
+
+struct PendingScene {
+    path: String,
+    source: String,
+    handle: Handle<World>,
+    options: LoadWorldOptions,
 }
 
 impl WorldLoader {
@@ -152,6 +226,7 @@ impl WorldLoader {
         Self {
             sender: SyncGuard::new(sender),
             receiver: SyncGuard::new(receiver),
+            pending_scenes: Vec::new(),
         }
     }
 }
@@ -167,10 +242,7 @@ impl AssetLoader<World> for WorldLoader {
         let path = path.to_owned();
         let sender = self.sender.inner().clone();
 
-        let extension = std::path::Path::new(&path)
-            .extension()
-            .and_then(std::ffi::OsStr::to_str)
-            .unwrap();
+        let extension = world_extension(&path).unwrap();
 
         ktasks::spawn(async move {
             let world_load_message_data = load_world(&path).await.unwrap();
@@ -212,6 +284,10 @@ async fn load_world_from_bytes_and_extension(
 ) -> Option<PrefabLoadMessageData> {
     #[allow(unreachable_code)]
     Some(match extension {
+        "scene.json" => PrefabLoadMessageData::Scene {
+            path: path.to_string(),
+            source: String::from_utf8(bytes.to_vec()).ok()?,
+        },
         #[cfg(feature = "gltf")]
         "glb" => {
             let glb = kgltf::GLB::from_bytes(&bytes).unwrap();
@@ -253,14 +329,23 @@ async fn load_world_from_bytes_and_extension(
 }
 #[allow(dead_code, unused_variables)]
 async fn load_world(path: &str) -> Option<PrefabLoadMessageData> {
-    let extension = std::path::Path::new(&path)
-        .extension()
-        .and_then(std::ffi::OsStr::to_str)?;
+    let extension = world_extension(path)?;
 
     let bytes = crate::fetch_bytes(path).await.ok()?;
     load_world_from_bytes_and_extension(&bytes, &path, &extension).await
 }
 
+/// Like [std::path::Path::extension] but `.scene.json` files have the extension `scene.json`.
+fn world_extension(path: &str) -> Option<&str> {
+    if path.ends_with(".scene.json") {
+        Some("scene.json")
+    } else {
+        std::path::Path::new(path)
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+    }
+}
+
 pub fn flatten_world(world: &mut World) {
     let mut commands = Commands::new();
 
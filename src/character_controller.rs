@@ -1,18 +1,35 @@
 use rapier3d::parry::query::TOIStatus;
 
-use crate::rocket::spawn_rocket;
+use crate::rocket::{spawn_rocket, ROCKET_COOLDOWN_SECONDS};
 use crate::*;
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect)]
 pub struct CharacterController {
+    #[reflect(skip)]
     grapple_target: Entity,
+    #[reflect(skip)]
     grapple_line: Entity,
-    grapple_position: Option<(Vec3, f32)>,
+    #[reflect(skip)]
+    grapple: Option<Rope>,
+    /// How long the grapple's cable is allowed to be while reeling out.
+    grapple_length: f32,
     extra_jumps: usize,
+    /// Jumps granted by powerups on top of `MAX_EXTRA_JUMPS`.
+    pub bonus_jumps: usize,
     pub max_cable_length: f32,
     pub can_shoot: bool,
+    pub rocket_ammo: u32,
+    /// Fired rockets steer towards the worm's mouth.
+    pub homing_rockets: bool,
+    /// Rockets can't be fired until this reaches zero.
+    rocket_cooldown_seconds: f32,
+    /// While this is above zero gravity is reduced.
+    pub slow_fall_seconds: f32,
+    #[reflect(skip)]
     jump_sound: Handle<Sound>,
+    #[reflect(skip)]
     grapple_sound: Handle<Sound>,
+    #[reflect(skip)]
     wind_sound: Handle<Sound>,
 }
 
@@ -27,6 +44,18 @@ pub struct CharacterControllerCamera;
 
 pub const MAX_EXTRA_JUMPS: usize = 2;
 
+/// The player's gravity scale while slow falling.
+const SLOW_FALL_GRAVITY_SCALE: f32 = 0.3;
+/// The player's gravity scale while grappling.
+const GRAPPLE_GRAVITY_SCALE: f32 = 0.1;
+
+/// How fast the grapple reels in on its own, in meters per second.
+const GRAPPLE_REEL_IN_SPEED: f32 = 20.0;
+/// How fast the grapple reels out while `REEL_OUT` is held, in meters per second.
+const GRAPPLE_REEL_OUT_SPEED: f32 = 10.0;
+/// The grapple lets go once the cable is this short.
+const GRAPPLE_MIN_LENGTH: f32 = 1.3;
+
 impl CharacterController {
     pub fn new(world: &mut World) -> Self {
         let sounds = world.get_singleton::<Assets<Sound>>();
@@ -50,23 +79,45 @@ impl CharacterController {
                 Cable::new(),
             )),
             max_cable_length: 50.0,
-            grapple_position: None,
+            grapple: None,
+            grapple_length: 0.0,
             extra_jumps: MAX_EXTRA_JUMPS,
+            bonus_jumps: 0,
             can_shoot: false,
+            rocket_ammo: 0,
+            homing_rockets: false,
+            rocket_cooldown_seconds: 0.0,
+            slow_fall_seconds: 0.0,
             jump_sound,
             grapple_sound,
             wind_sound,
         }
     }
 
-    pub fn reset(&mut self) {
-        self.grapple_position = None;
+    pub fn reset(&mut self, rapier_physics: &mut RapierPhysicsManager) {
+        self.release_grapple(rapier_physics);
         self.max_cable_length = 50.0;
-        self.extra_jumps = MAX_EXTRA_JUMPS;
+        self.bonus_jumps = 0;
+        self.rocket_ammo = 0;
+        self.homing_rockets = false;
+        self.rocket_cooldown_seconds = 0.0;
+        self.extra_jumps = self.max_extra_jumps();
+        self.slow_fall_seconds = 0.0;
+    }
+
+    pub fn max_extra_jumps(&self) -> usize {
+        MAX_EXTRA_JUMPS + self.bonus_jumps
+    }
+
+    pub fn release_grapple(&mut self, rapier_physics: &mut RapierPhysicsManager) {
+        if let Some(grapple) = self.grapple.take() {
+            rapier_physics.remove_rope(&grapple);
+        }
     }
 
     pub fn fixed_update(
         input: &Input,
+        input_actions: &InputActions,
         rapier_physics: &mut RapierPhysicsManager,
         (camera_transform, _camera, _, head_audio_source): (
             &GlobalTransform,
@@ -79,6 +130,7 @@ impl CharacterController {
             &mut CharacterController,
             &mut RigidBody,
             &RapierCollider,
+            Option<&RapierRigidBody>,
             &mut AudioSource,
         )>,
         time: &Time,
@@ -88,8 +140,14 @@ impl CharacterController {
         explosion_manager: &mut ExplosionManager,
         commands: &mut Commands,
     ) {
-        for (transform, character_controller, rigid_body, rapier_collider, audio_source) in
-            controlled.iter_mut()
+        for (
+            transform,
+            character_controller,
+            rigid_body,
+            rapier_collider,
+            rapier_rigid_body,
+            audio_source,
+        ) in controlled.iter_mut()
         {
 
             game_state.player_max_height = transform.position.y.max(game_state.player_max_height);
@@ -98,8 +156,14 @@ impl CharacterController {
                 return;
             }
 
+            character_controller.slow_fall_seconds =
+                (character_controller.slow_fall_seconds - time.fixed_time_step as f32).max(0.0);
             if transform.position.y < 0.0 {
                 rigid_body.gravity_scale = -4.0;
+            } else if character_controller.grapple.is_some() {
+                rigid_body.gravity_scale = GRAPPLE_GRAVITY_SCALE;
+            } else if character_controller.slow_fall_seconds > 0.0 {
+                rigid_body.gravity_scale = SLOW_FALL_GRAVITY_SCALE;
             } else {
                 rigid_body.gravity_scale = 1.0;
             }
@@ -118,8 +182,8 @@ impl CharacterController {
                 &[0.0, -1.0, 0.0].into(),
                 &rapier3d::prelude::Ball::new(1.0),
                 0.3,
-                rapier3d::prelude::InteractionGroups::all(),
-                Some(&|c| c != rapier_collider.0),
+                rapier_physics.collision_groups(rapier_collider.0),
+                Some(&|c| c != rapier_collider.0 && !rapier_physics.is_sensor(c)),
             );
 
             if let Some(v) = grounded {
@@ -130,7 +194,7 @@ impl CharacterController {
             let grounded = grounded.is_some();
 
             if grounded {
-                character_controller.extra_jumps = MAX_EXTRA_JUMPS;
+                character_controller.extra_jumps = character_controller.max_extra_jumps();
             }
 
             let mut max = 6.0;
@@ -143,36 +207,37 @@ impl CharacterController {
                 air_acceleration
             };
 
-            if character_controller.grapple_position.is_some() {
+            if character_controller.grapple.is_some() {
                 acceleration = 0.2;
                 max = 12.0;
             }
             let mut forward = camera_transform.forward();
 
-            if character_controller.grapple_position.is_none() {
+            if character_controller.grapple.is_none() {
                 forward.y = 0.0;
                 forward = forward.normalized();
             }
 
             let mut right = camera_transform.right();
-            if character_controller.grapple_position.is_none() {
+            if character_controller.grapple.is_none() {
                 right.y = 0.0;
                 right = right.normalized();
             }
 
             let horizontal_velocity = Vec3::new(rigid_body.velocity.x, 0.0, rigid_body.velocity.z);
 
-            if input.key(Key::W) && horizontal_velocity.dot(forward) < max {
-                rigid_body.velocity += forward * acceleration;
+            let move_axis = input_actions.axis(input, MOVE);
+            if move_axis.y > 0.0 && horizontal_velocity.dot(forward) < max {
+                rigid_body.velocity += forward * acceleration * move_axis.y;
             }
-            if input.key(Key::S) && horizontal_velocity.dot(-forward) < max {
-                rigid_body.velocity -= forward * acceleration;
+            if move_axis.y < 0.0 && horizontal_velocity.dot(-forward) < max {
+                rigid_body.velocity += forward * acceleration * move_axis.y;
             }
-            if input.key(Key::A) && horizontal_velocity.dot(-right) < max {
-                rigid_body.velocity -= right * acceleration;
+            if move_axis.x < 0.0 && horizontal_velocity.dot(-right) < max {
+                rigid_body.velocity += right * acceleration * move_axis.x;
             }
-            if input.key(Key::D) && horizontal_velocity.dot(right) < max {
-                rigid_body.velocity += right * acceleration;
+            if move_axis.x > 0.0 && horizontal_velocity.dot(right) < max {
+                rigid_body.velocity += right * acceleration * move_axis.x;
             }
 
             rigid_body.mutated_velocity = true;
@@ -186,7 +251,7 @@ impl CharacterController {
 
             let mut jumped = false;
             if grounded || character_controller.extra_jumps > 0 {
-                if input.key_down(Key::Space) {
+                if input_actions.pressed(input, JUMP) {
                     rigid_body.velocity += Vec3::Y * 5.0;
                     jumped = true;
                     character_controller.extra_jumps =
@@ -212,13 +277,19 @@ impl CharacterController {
                 &ray,
                 grapple_distance,
                 false,
-                rapier3d::prelude::InteractionGroups::all(),
-                Some(&|c| c != rapier_collider.0),
+                rapier_physics.collision_groups(rapier_collider.0),
+                Some(&|c| c != rapier_collider.0 && !rapier_physics.is_sensor(c)),
             );
             game_state.can_grapple = ray_cast.is_some();
 
-            if character_controller.can_shoot {
-                if input.pointer_button_down(PointerButton::Secondary) {
+            character_controller.rocket_cooldown_seconds =
+                (character_controller.rocket_cooldown_seconds - time.fixed_time_step as f32)
+                    .max(0.0);
+            if character_controller.can_shoot
+                && character_controller.rocket_ammo > 0
+                && character_controller.rocket_cooldown_seconds == 0.0
+            {
+                if input_actions.pressed(input, FIRE_ROCKET) {
                     /*
                     if let Some(result) = ray_cast {
                         let position = camera_ray.get_point(result.1);
@@ -226,10 +297,14 @@ impl CharacterController {
                     }
                     */
                     println!("SPAWNING ROCKET");
+                    game_state.rockets_fired += 1;
+                    character_controller.rocket_ammo -= 1;
+                    character_controller.rocket_cooldown_seconds = ROCKET_COOLDOWN_SECONDS;
                     spawn_rocket(
                         commands,
                         camera_transform.position,
                         camera_transform.forward(),
+                        character_controller.homing_rockets,
                     )
                 }
             }
@@ -242,9 +317,11 @@ impl CharacterController {
             }
             */
 
-            if input.pointer_button_down(PointerButton::Primary) {
-                if let Some(result) = ray_cast {
-                    let position = camera_ray.get_point(result.1);
+            if input_actions.pressed(input, GRAPPLE) {
+                if let (Some((hit_collider, toi)), Some(rapier_rigid_body)) =
+                    (ray_cast, rapier_rigid_body)
+                {
+                    let position = camera_ray.get_point(toi);
 
                     println!("GRAPPLING");
                     audio_source.play(&character_controller.grapple_sound, false);
@@ -265,7 +342,17 @@ impl CharacterController {
                         120 * 4,
                     );
                     */
-                    character_controller.grapple_position = Some((position, 2.0));
+                    character_controller.release_grapple(rapier_physics);
+                    character_controller.grapple =
+                        rapier_physics.attach_rope(rapier_rigid_body.0, hit_collider, position);
+                    // The player's body can be a little farther from the hit than the camera is,
+                    // so never let the cable be shorter than where it started.
+                    let start_length = match &character_controller.grapple {
+                        Some(grapple) => rapier_physics.rope_length(grapple),
+                        None => 0.0,
+                    };
+                    character_controller.grapple_length =
+                        character_controller.max_cable_length.max(start_length);
 
                     let velocity_along_direction =
                         camera_ray.direction.dot(rigid_body.velocity) * camera_ray.direction;
@@ -273,40 +360,46 @@ impl CharacterController {
                         rigid_body.velocity - velocity_along_direction;
                     rigid_body.velocity =
                         velocity_along_direction * 0.8 + velocity_not_along_direction * 0.7;
-                    rigid_body.gravity_scale = 0.1;
                     rigid_body.mutated_velocity = true;
                 }
             }
 
-            if let Some((grapple_position, time_remaining)) =
-                &mut character_controller.grapple_position
-            {
-                rigid_body.mutated_velocity = true;
-
-                character_controller.extra_jumps = MAX_EXTRA_JUMPS;
-                let diff = *grapple_position - transform.position;
-                let dir_normalized = diff.normalized();
-                rigid_body.velocity += dir_normalized * 0.8;
-                rigid_body.velocity += camera_transform.forward() * 0.1;
-
-                let max_grapple_velocity = 80.0;
-                if rigid_body.velocity.length() > max_grapple_velocity {
-                    rigid_body.velocity = rigid_body.velocity.normalized() * max_grapple_velocity;
-                }
-                *time_remaining -= time.delta_seconds_f64 as f32;
-
-                cable.start = camera_transform.position
-                    + camera_transform.right() * 0.3
-                    + camera_transform.down() * 0.2;
-                cable.end = *grapple_position;
-
-                if diff.length() < 1.3 || jumped {
-                    character_controller.grapple_position = None;
-                    rigid_body.gravity_scale = 1.0;
-                    cable.start = Vec3::ZERO;
-                    cable.end = Vec3::ZERO;
+            if let Some(grapple) = character_controller.grapple.clone() {
+                character_controller.extra_jumps = character_controller.max_extra_jumps();
+
+                // Reel in on its own, or out while REEL_OUT is held.
+                let delta_seconds = time.fixed_time_step as f32;
+                let length = rapier_physics.rope_length(&grapple);
+                let length = if input_actions.held(input, REEL_OUT) {
+                    (length + GRAPPLE_REEL_OUT_SPEED * delta_seconds)
+                        .min(character_controller.grapple_length)
+                } else {
+                    length - GRAPPLE_REEL_IN_SPEED * delta_seconds
+                };
+                rapier_physics.set_rope_length(&grapple, length.max(GRAPPLE_MIN_LENGTH));
+
+                match rapier_physics.rope_anchors(&grapple) {
+                    Some((anchor, _)) if length > GRAPPLE_MIN_LENGTH && !jumped => {
+                        // The player's end of the rope is at the camera,
+                        // so the visible cable starts at the player's hand instead.
+                        grapple_target_transform.position = anchor;
+                        cable.start = camera_transform.position
+                            + camera_transform.right() * 0.3
+                            + camera_transform.down() * 0.2;
+                        cable.end = anchor;
+                    }
+                    // Let go when reeled all the way in, when jumping,
+                    // or when whatever the grapple was attached to is gone.
+                    _ => {
+                        character_controller.release_grapple(rapier_physics);
+                    }
                 }
             }
+
+            if character_controller.grapple.is_none() {
+                cable.start = Vec3::ZERO;
+                cable.end = Vec3::ZERO;
+            }
         }
     }
 }
@@ -0,0 +1,137 @@
+use crate::*;
+
+/// How far below a respawned player the worm is moved.
+const RESPAWN_WORM_DISTANCE: f32 = 500.0;
+
+/// What the player had when they reached a [Checkpoint].
+#[derive(Clone)]
+pub struct CheckpointSave {
+    pub transform: Transform,
+    pub max_cable_length: f32,
+    pub bonus_jumps: usize,
+    pub can_shoot: bool,
+    pub rocket_ammo: u32,
+    pub homing_rockets: bool,
+    pub collected_powerups: Vec<Entity>,
+}
+
+/// Saves the player when they first climb above `height`.
+/// On game over the player respawns at the highest saved checkpoint,
+/// unless they press the [RESTART] key to restart the climb from the bottom.
+#[derive(Component, Clone)]
+pub struct Checkpoint {
+    pub height: f32,
+    pub save: Option<CheckpointSave>,
+}
+
+/// Places a checkpoint at each height the worm catches up to.
+pub fn setup_checkpoints(world: &mut World) {
+    let heights: Vec<f32> = (|worm: &WormBehavior| {
+        worm.description
+            .catch_up_phases
+            .iter()
+            .map(|phase| phase.worm_height)
+            .collect()
+    })
+    .run(world);
+    for height in heights {
+        world.spawn(Checkpoint { height, save: None });
+    }
+}
+
+pub fn reach_checkpoints(
+    game_state: &GameState,
+    (transform, character_controller): (&Transform, &CharacterController),
+    mut checkpoints: Query<&mut Checkpoint>,
+    powerups: Query<&Powerup>,
+) {
+    if game_state.game_mode != GameMode::Game || game_state.victory {
+        return;
+    }
+    for checkpoint in checkpoints.iter_mut() {
+        if checkpoint.save.is_none() && transform.position.y >= checkpoint.height {
+            checkpoint.save = Some(CheckpointSave {
+                transform: *transform,
+                max_cable_length: character_controller.max_cable_length,
+                bonus_jumps: character_controller.bonus_jumps,
+                can_shoot: character_controller.can_shoot,
+                rocket_ammo: character_controller.rocket_ammo,
+                homing_rockets: character_controller.homing_rockets,
+                collected_powerups: powerups
+                    .entities_and_components()
+                    .filter(|(_, powerup)| powerup.collected())
+                    .map(|(entity, _)| *entity)
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// Forgets every saved checkpoint so a new climb starts from the bottom.
+pub fn clear_checkpoints(mut checkpoints: Query<&mut Checkpoint>) {
+    for checkpoint in checkpoints.iter_mut() {
+        checkpoint.save = None;
+    }
+}
+
+/// Respawns the player at the highest saved checkpoint.
+/// The run keeps its clock and stats. Returns false if no checkpoint has been reached.
+pub fn respawn_at_checkpoint(world: &mut World) -> bool {
+    let save = (|checkpoints: Query<&Checkpoint>| {
+        checkpoints
+            .iter()
+            .filter(|checkpoint| checkpoint.save.is_some())
+            .max_by(|a, b| a.height.total_cmp(&b.height))
+            .and_then(|checkpoint| checkpoint.save.clone())
+    })
+    .run(world);
+    let save = match save {
+        Some(save) => save,
+        None => return false,
+    };
+
+    (|player: (
+        &mut CharacterController,
+        &mut Transform,
+        &mut RigidBody,
+        &mut Interpolated,
+    ),
+      rapier_physics: &mut RapierPhysicsManager| {
+        let (character_controller, transform, rigid_body, interpolated) = player;
+        *transform = save.transform;
+        interpolated.snap(save.transform);
+        rigid_body.velocity = Vec3::ZERO;
+        rigid_body.mutated_position = true;
+        rigid_body.mutated_velocity = true;
+        character_controller.reset(rapier_physics);
+        character_controller.max_cable_length = save.max_cable_length;
+        character_controller.bonus_jumps = save.bonus_jumps;
+        character_controller.can_shoot = save.can_shoot;
+        character_controller.rocket_ammo = save.rocket_ammo;
+        character_controller.homing_rockets = save.homing_rockets;
+    })
+    .run(world);
+
+    (|rapier_physics: &mut RapierPhysicsManager,
+      commands: &mut Commands,
+      mut powerups: Query<(&mut Powerup, &mut RenderFlags)>| {
+        for (entity, (powerup, render_flags)) in powerups.entities_and_components_mut() {
+            let collected = save.collected_powerups.contains(entity);
+            powerup.set_collected(*entity, collected, render_flags, rapier_physics, commands);
+        }
+    })
+    .run(world);
+
+    (|(worm_transform, worm): (&mut Transform, &mut WormBehavior),
+      worm_events: &mut WormEvents| {
+        worm_transform.position.y = save.transform.position.y - RESPAWN_WORM_DISTANCE;
+        worm.respawn();
+        *worm_events = WormEvents::default();
+    })
+    .run(world);
+
+    let game_state = world.get_singleton::<GameState>();
+    game_state.continue_run();
+    game_state.needs_reset = false;
+    true
+}
@@ -0,0 +1,64 @@
+use crate::*;
+
+/// Where control bindings are saved. On the web this is the key in the browser's IndexedDB.
+pub const CONTROLS_PATH: &str = "controls.json";
+
+/// Names of the [InputActions] gameplay reads.
+pub const MOVE: &str = "move";
+pub const JUMP: &str = "jump";
+pub const GRAPPLE: &str = "grapple";
+pub const FIRE_ROCKET: &str = "fire_rocket";
+/// Lets the grapple's cable out while held.
+pub const REEL_OUT: &str = "reel_out";
+/// Unlocks the mouse cursor.
+pub const RELEASE_MOUSE: &str = "release_mouse";
+/// On the game over screen, restarts the climb from the bottom instead of the last checkpoint.
+pub const RESTART: &str = "restart";
+
+/// The bindings used until the player changes them.
+/// The arrow keys and right-hand keys are bound too so left-handed players can play.
+pub fn default_input_actions() -> InputActions {
+    let mut input_actions = InputActions::new();
+    input_actions.bind(
+        MOVE,
+        InputBinding::KeyAxis {
+            up: Key::W,
+            down: Key::S,
+            left: Key::A,
+            right: Key::D,
+        },
+    );
+    input_actions.bind(
+        MOVE,
+        InputBinding::KeyAxis {
+            up: Key::Up,
+            down: Key::Down,
+            left: Key::Left,
+            right: Key::Right,
+        },
+    );
+    input_actions.bind(JUMP, InputBinding::Key(Key::Space));
+    input_actions.bind(JUMP, InputBinding::Key(Key::RightShift));
+    input_actions.bind(GRAPPLE, InputBinding::PointerButton(PointerButton::Primary));
+    input_actions.bind(
+        FIRE_ROCKET,
+        InputBinding::PointerButton(PointerButton::Secondary),
+    );
+    input_actions.bind(REEL_OUT, InputBinding::Key(Key::Q));
+    input_actions.bind(REEL_OUT, InputBinding::Key(Key::RightControl));
+    input_actions.bind(RELEASE_MOUSE, InputBinding::Key(Key::Escape));
+    input_actions.bind(RESTART, InputBinding::Key(Key::R));
+    input_actions
+}
+
+/// Applies saved bindings on top of the defaults so actions added since they were saved still work.
+pub fn apply_saved_controls(input_actions: &mut InputActions, json: &str) {
+    match InputActions::from_json(json) {
+        Some(saved) => input_actions.apply(&saved),
+        None => println!("Could not read saved controls"),
+    }
+}
+
+pub fn save_controls(input_actions: &InputActions) {
+    save_to_storage(CONTROLS_PATH, &input_actions.to_json());
+}
@@ -1,190 +1,238 @@
 use crate::*;
+use rapier3d::math::Isometry;
+use rapier3d::parry::query::PointQuery;
+use rapier3d::prelude::nalgebra::{Point3, Vector3};
+use rapier3d::prelude::{Ball, InteractionGroups, Ray};
+
+/// Colliders hidden from an explosion by other colliders are pushed this much as hard.
+const OCCLUDED_STRENGTH_SCALE: f32 = 0.25;
+/// Anything this close to an explosion's center doesn't block it.
+const OCCLUSION_IGNORE_DISTANCE: f32 = 0.5;
 
 #[derive(Component, Clone)]
 pub struct ExplosionManager {
-    explosion_mesh: Handle<Mesh>,
+    /// The [ParticleEmitter] that draws every explosion.
+    particle_emitter: Entity,
     explosions_queue: Vec<ExplosionData>,
-    colors: Vec<Color>,
     colliders_intersected: Vec<rapier3d::prelude::ColliderHandle>,
     explosion_sound: Handle<Sound>,
+    craters: Vec<Crater>,
 }
 
 #[derive(Clone)]
 pub struct ExplosionData {
     pub center: Vec3,
     pub scale: f32,
+    pub crater_radius: f32,
+}
+
+/// An entity whose collider was caught in an explosion.
+#[derive(Clone, Copy, Debug)]
+pub struct ExplosionHit {
+    pub entity: Entity,
+    /// From 0.0 at the edge of the explosion to 1.0 at its center, after occlusion.
+    pub strength: f32,
+    /// Another collider, like terrain, was between the explosion and this entity.
+    pub occluded: bool,
+}
+
+/// Sent for each explosion. Gameplay can read these with an [EventReader] to apply damage.
+#[derive(Clone, Debug)]
+pub struct ExplosionEvent {
+    pub center: Vec3,
+    pub scale: f32,
+    pub hits: Vec<ExplosionHit>,
 }
 
-#[derive(Clone, Component)]
-pub struct ExplosionPiece {
-    spawned_pieces: u32,
+/// A spherical hole an explosion should carve into the terrain.
+#[derive(Clone, Copy)]
+pub struct Crater {
+    pub center: Vec3,
+    pub radius: f32,
 }
 
 impl ExplosionManager {
     pub fn setup_system(world: &mut World) {
-        let low_poly_uv_sphere = (|meshes: &mut Assets<Mesh>, graphics: &mut Graphics| {
-            meshes.add(Mesh::new(graphics, uv_sphere(4, 4, Vec2::ONE)))
-        })
-        .run(world);
+        let particle_emitter = world.spawn((
+            Transform::new(),
+            ParticleEmitter {
+                lifetime: 0.6,
+                lifetime_randomness: 0.5,
+                velocity_randomness: 0.6,
+                spawn_radius: 0.1,
+                gravity: Vec3::Y * -5.0,
+                drag: 2.0,
+                color_over_life: ParticleCurve::new(vec![
+                    (0.0, Color::new(239. / 255., 249. / 255., 126. / 255., 1.0)),
+                    (0.3, Color::new(204. / 255., 137. / 255., 75. / 255., 1.0)),
+                    (1.0, Color::new(212. / 255., 39. / 255., 15. / 255., 0.0)),
+                ]),
+                size_over_life: ParticleCurve::new(vec![(0.0, 0.3), (0.2, 0.5), (1.0, 0.1)]),
+                max_particles: 2000,
+                ..Default::default()
+            },
+        ));
 
         let explosion_sound = world
             .get_singleton::<Assets<Sound>>()
             .load("assets/explosion.wav");
             
         world.spawn(Self {
-            explosion_mesh: low_poly_uv_sphere,
+            particle_emitter,
             explosions_queue: Vec::new(),
-            colors: vec![
-                Color::new(212. / 255., 39. / 255., 15. / 255., 1.0),
-                Color::new(239. / 255., 249. / 255., 126. / 255., 1.0),
-                Color::new(204. / 255., 137. / 255., 75. / 255., 1.0),
-            ],
             colliders_intersected: Vec::new(),
             explosion_sound,
+            craters: Vec::new(),
         });
     }
 
     pub fn fixed_update_system(
         &mut self,
-        commands: &mut Commands,
-        mut explosion_pieces: Query<(&Transform, &mut ExplosionPiece)>,
-        mut rigid_bodies: Query<(&mut Transform, &RapierRigidBody, &mut RigidBody)>,
+        mut particle_emitters: Query<&mut ParticleEmitter>,
         rapier_physics_manager: &mut RapierPhysicsManager,
+        mut explosion_events: EventWriter<ExplosionEvent>,
     ) {
-        fn spawn_piece(
-            colors: &[Color],
-            spawned_pieces: u32,
-            center: Vec3,
-            scale: f32,
-            random: &mut Random,
-            mesh: Handle<Mesh>,
-            commands: &mut Commands,
-        ) {
-            commands.spawn((
-                *random.select_from_slice(colors),
-                Temporary(5 * 1),
-                mesh,
-                Material::UNLIT,
-                Transform::new()
-                    .with_position(center)
-                    .with_scale(Vec3::fill(scale)),
-                ExplosionPiece { spawned_pieces },
-            ))
+        for ExplosionData {
+            center,
+            scale,
+            crater_radius,
+        } in std::mem::take(&mut self.explosions_queue)
+        {
+            if crater_radius > 0.0 {
+                self.craters.push(Crater {
+                    center,
+                    radius: crater_radius,
+                });
+            }
+
+            if let Some(particle_emitter) =
+                particle_emitters.get_entity_components_mut(self.particle_emitter)
+            {
+                particle_emitter.burst_at(center, 150, scale);
+            }
+
+            let hits = self.apply_impulses(center, scale, rapier_physics_manager);
+            explosion_events.send(ExplosionEvent {
+                center,
+                scale,
+                hits,
+            });
         }
-        let mut random = Random::new();
+    }
 
-        for ExplosionData { center, scale } in self.explosions_queue.drain(..) {
-            for _ in 0..random.range_u32(2..8) {
-                spawn_piece(
-                    &self.colors,
-                    random.range_u32(3..30),
-                    center,
-                    scale,
-                    &mut random,
-                    self.explosion_mesh.clone(),
-                    commands,
-                );
-
-                let falloff = scale * 5.0;
-                self.colliders_intersected.clear();
-                rapier_physics_manager
+    /// Pushes away bodies near the explosion and returns what it hit.
+    /// Each collider is pushed at its point closest to the explosion. Colliders hidden behind
+    /// other colliders, like terrain, are pushed less.
+    fn apply_impulses(
+        &mut self,
+        center: Vec3,
+        scale: f32,
+        rapier_physics_manager: &mut RapierPhysicsManager,
+    ) -> Vec<ExplosionHit> {
+        let radius = scale * 5.0;
+        let max_force = 4.0 * scale;
+        let center: [f32; 3] = center.into();
+        let center: Point3<f32> = center.into();
+
+        self.colliders_intersected.clear();
+        rapier_physics_manager
+            .query_pipeline
+            .intersections_with_shape(
+                &rapier_physics_manager.collider_set,
+                &Isometry::translation(center.x, center.y, center.z),
+                &Ball::new(radius),
+                InteractionGroups::all(),
+                None,
+                |handle| {
+                    self.colliders_intersected.push(handle);
+                    true
+                },
+            );
+
+        let mut hits = Vec::new();
+        for collider_handle in self.colliders_intersected.iter().copied() {
+            let collider = rapier_physics_manager
+                .collider_set
+                .get(collider_handle)
+                .unwrap();
+            let closest_point = collider
+                .shape()
+                .project_point(collider.position(), &center, true)
+                .point;
+            let offset = closest_point - center;
+            let distance = offset.magnitude();
+
+            // If the explosion is inside the collider push away from the collider's center instead.
+            let direction = if distance > f32::EPSILON {
+                offset / distance
+            } else {
+                (collider.position().translation.vector - center.coords)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::y)
+            };
+
+            let occluded = distance > OCCLUSION_IGNORE_DISTANCE
+                && rapier_physics_manager
                     .query_pipeline
-                    .intersections_with_shape(
+                    .cast_ray(
                         &rapier_physics_manager.collider_set,
-                        &rapier3d::math::Isometry::translation(center.x, center.y, center.z),
-                        &rapier3d::prelude::Ball::new(falloff),
-                        rapier3d::prelude::InteractionGroups::all(),
-                        None,
-                        |handle| {
-                            self.colliders_intersected.push(handle);
-                            true
-                        },
-                    );
-
-                for collider_handle in self.colliders_intersected.iter() {
-                    let collider = rapier_physics_manager
-                        .collider_set
-                        .get(*collider_handle)
-                        .unwrap();
-                    let collider_center = collider.position();
-
-                    let center: [f32; 3] = center.into();
-                    let center: rapier3d::prelude::nalgebra::Point3<f32> = center.into();
-                    /*/
-                    let ray = rapier3d::prelude::Ray::new(
-                        center,
-                        &rapier3d::prelude::nalgebra::Point3::new(
-                            collider_center.translation.x,
-                            collider_center.translation.y,
-                            collider_center.translation.z,
-                        ) - center,
-                    );
-
-                    rapier_physics_manager.query_pipeline.cast_ray(
-                        &[collider],
-                        &ray,
-                        100.,
+                        &Ray::new(center, direction),
+                        distance,
                         true,
-                        rapier3d::prelude::InteractionGroups::all(),
-                        None,
-                    );
-                    */
-
-                    let dir = &rapier3d::prelude::nalgebra::Point3::new(
-                        collider_center.translation.x,
-                        collider_center.translation.y,
-                        collider_center.translation.z,
-                    ) - center;
-                    let max_force = 4.0 * scale;
-
-                    if let Some(parent) = rapier_physics_manager
-                        .collider_set
-                        .get(*collider_handle)
-                        .unwrap()
-                        .parent()
-                    {
-                        let mag = dir.magnitude();
-                        let falloff = 1.0 - (mag / falloff);
-                        let falloff = (falloff * falloff).min(1.0);
-                        let mut impulse = dir.normalize() * falloff * max_force;
-
-                        if impulse.y > 0.0 {
-                            impulse.y *= 2.0;
-                        }
-                        rapier_physics_manager
-                            .rigid_body_set
-                            .get_mut(parent)
-                            .unwrap()
-                            .apply_impulse(impulse, true);
-
-                        rapier_physics_manager
-                            .rigid_body_set
-                            .get_mut(parent)
-                            .unwrap()
-                            .apply_torque_impulse(impulse / 3., true);
-                    }
-                }
+                        InteractionGroups::all(),
+                        Some(&|c| c != collider_handle && !rapier_physics_manager.is_sensor(c)),
+                    )
+                    // The explosion may start on the surface it hit.
+                    .map_or(false, |(_, toi)| toi > OCCLUSION_IGNORE_DISTANCE);
+
+            let falloff = (1.0 - distance / radius).max(0.0);
+            let mut strength = (falloff * falloff).min(1.0);
+            if occluded {
+                strength *= OCCLUDED_STRENGTH_SCALE;
+            }
+            if strength <= 0.0 {
+                continue;
             }
-        }
 
-        for (entity, piece) in explosion_pieces.entities_and_components_mut() {
-            if piece.1.spawned_pieces > 0 && random.f32() < 0.8 {
-                piece.1.spawned_pieces -= 1;
-                spawn_piece(
-                    &self.colors,
-                    random.range_u32(0..piece.1.spawned_pieces),
-                    piece.0.position
-                        + random.point_in_unit_sphere() * piece.0.scale.x * random.f32() * 2.0,
-                    piece.0.scale.x * random.f32(),
-                    &mut random,
-                    self.explosion_mesh.clone(),
-                    commands,
-                );
+            if let Some(entity) = rapier_physics_manager.entity_for_collider(collider_handle) {
+                hits.push(ExplosionHit {
+                    entity,
+                    strength,
+                    occluded,
+                });
+            }
+
+            if let Some(parent) = collider.parent() {
+                let mut impulse = direction * strength * max_force;
+                if impulse.y > 0.0 {
+                    impulse.y *= 2.0;
+                }
+                rapier_physics_manager
+                    .rigid_body_set
+                    .get_mut(parent)
+                    .unwrap()
+                    .apply_impulse_at_point(impulse, closest_point, true);
             }
         }
+        hits
     }
 
     pub fn new_explosion(&mut self, center: Vec3, scale: f32) {
-        self.explosions_queue.push(ExplosionData { center, scale })
+        self.new_explosion_with_crater(center, scale, 0.0)
+    }
+
+    /// Like `new_explosion` but also carves a hole of `crater_radius` into the terrain.
+    pub fn new_explosion_with_crater(&mut self, center: Vec3, scale: f32, crater_radius: f32) {
+        self.explosions_queue.push(ExplosionData {
+            center,
+            scale,
+            crater_radius,
+        })
+    }
+
+    /// Takes the craters produced by explosions since the last call.
+    /// The terrain isn't part of the `World` so this is applied by the main loop.
+    pub fn take_craters(&mut self) -> Vec<Crater> {
+        std::mem::take(&mut self.craters)
     }
 }
@@ -27,9 +27,33 @@ mod find_flat_parts;
 
 mod terrain_generator;
 
+mod terrain_description;
+use terrain_description::*;
+
+mod terrain_file;
+use terrain_file::*;
+
 mod worm;
 use worm::*;
 
+mod profile;
+use profile::*;
+
+mod checkpoint;
+use checkpoint::*;
+
+mod powerup;
+use powerup::*;
+
+mod controls;
+use controls::*;
+
+#[cfg(all(test, feature = "headless"))]
+mod tests;
+
+/// Generate terrain around the player on worker threads instead of all at once during startup.
+const STREAM_TERRAIN: bool = true;
+
 #[derive(Component, Clone)]
 pub struct GameState {
     game_mode: GameMode,
@@ -37,6 +61,35 @@ pub struct GameState {
     needs_reset: bool,
     player_max_height: f32,
     victory: bool,
+    /// Seconds spent in the current run, including time before respawning at a checkpoint.
+    run_seconds: f32,
+    rockets_fired: u32,
+    /// Rockets already counted in the [Profile] by a game over before respawning at a checkpoint.
+    rockets_recorded: u32,
+    /// Set once the finished run has been added to the [Profile].
+    run_recorded: bool,
+    /// Set when the player chose to restart from the bottom instead of the last checkpoint.
+    restart_from_bottom: bool,
+}
+
+impl GameState {
+    /// Starts playing and clears the stats of the previous run,
+    /// so each run is only recorded in the [Profile] once.
+    fn start_run(&mut self) {
+        self.game_mode = GameMode::Game;
+        self.player_max_height = 0.0;
+        self.run_seconds = 0.0;
+        self.rockets_fired = 0;
+        self.rockets_recorded = 0;
+        self.run_recorded = false;
+    }
+
+    /// Keeps playing after a game over without clearing the run's clock and stats,
+    /// so a summit reached from a checkpoint counts the whole climb.
+    fn continue_run(&mut self) {
+        self.game_mode = GameMode::Game;
+        self.run_recorded = false;
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -80,716 +133,796 @@ fn prepare_model_world(world: &mut World, scale: Vec3) {
     koi::flatten_world(world);
 }
 
-fn main() {
-    App::new().setup_and_run(|world: &mut World| {
-        // Setup things here.
-
-        let mut camera = Camera::new();
-        camera.clear_color = Some(Color::WHITE);
-        let mut controls = CameraControls::new();
-        controls.max_speed *= 100.;
-
-        //Spawn a camera and make it look towards the origin
-        let title_camera = world.spawn((
-            Transform::new()
-                .with_position(Vec3::new(-847.27747, 111.74761, -594.119))
-                .with_rotation(Quat::from_xyzw(
-                    -0.11213023,
-                    0.8636663,
-                    -0.2205305,
-                    -0.43913826,
-                )),
-            camera,
-            // controls,
-        ));
-
-        world.spawn(GameState {
-            game_mode: GameMode::Title,
-            can_grapple: false,
-            needs_reset: false,
-            player_max_height: 0.0,
-            victory: false,
-        });
-
-        let size_xz = 64;
-
-        let mut terrain = Terrain::new(size_xz, 512);
-        terrain.create_chunks(world);
-
-        world.spawn((
-            Color::MAJORELLE_BLUE,
-            Mesh::SPHERE,
-            Transform::new().with_scale(Vec3::fill(30.0)),
-            Material::DEFAULT,
-            Collider::Sphere(0.5),
-        ));
-
-        let mut player_camera_entity = None;
-        //let mut terrain_chunks = Vec::new();
-
-        /*
-        for i in 0..2 {k
-            let (world_chunk_mesh, has_at_least_one_triangle) =
-                (|graphics: &mut Graphics, meshes: &mut Assets<Mesh>| {
-                    let generated_chunk_mesh =
-                        terrain.create_chunk_mesh(Vec3u::new(0, offset_y, 0), size_xz);
-                    let has_at_least_one_triangle = !generated_chunk_mesh.indices.is_empty();
-                    (
-                        meshes.add(Mesh::new(graphics, generated_chunk_mesh)),
-                        has_at_least_one_triangle,
-                    )
-                })
-                .run(world);
-
-            terrain_meshes.push(world_chunk_mesh.clone());
-            if has_at_least_one_triangle {
-                terrain_chunks.push(world.spawn((
-                    world_chunk_mesh,
-                    Material::DEFAULT,
-                    Transform::new().with_position(
-                        Vec3::Y * (offset_y as f32 / size_xz as f32) * terrain.scale + world_offset,
-                    ),
-                    Collider::AttachedMesh,
-                )));
-            } else {
-                println!("EMPTY CHUNK!");
+/// Handles the `--record <path>` and `--replay <path>` command line options.
+/// Returns the path a recording should be saved to when the game quits.
+fn setup_replay(world: &mut World) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let input_replay = world.get_singleton::<InputReplay>();
+    match args.get(1..3) {
+        Some([option, path]) if option == "--record" => {
+            input_replay.start_recording();
+            Some(path.clone())
+        }
+        Some([option, path]) if option == "--replay" => {
+            match Replay::load(path) {
+                Ok(replay) => input_replay.play(replay),
+                Err(error) => println!("Could not load replay {:?}: {:?}", path, error),
             }
-            offset_y += size_xz;
+            None
         }
-        */
+        _ => None,
+    }
+}
 
-        spawn_reflection_probe(world, "assets/venice_sunset.hdr");
+/// The koi [App] with the game's [Events] added.
+fn game_app() -> App {
+    App::new()
+        .add_plugin(events_plugin::<ExplosionEvent>())
+        .add_plugin(events_plugin::<RocketHit>())
+}
 
-        let sounds = world.get_singleton::<Assets<Sound>>();
-        let upbeat_vibes_song = sounds.load("assets/upbeat_vibes.wav");
-        //let shoot_grapple_sound = sounds.load("assets/shoot_grapple.wav");
+fn main() {
+    game_app().setup_and_run(|world: &mut World| {
+        // This must happen before anything creates a `Random` so replays are deterministic.
+        let replay_path = setup_replay(world);
+        setup_game(world, replay_path)
+    });
+}
 
-        world.spawn(RapierPhysicsManager::new());
+/// Sets up the game and returns the function that runs it.
+/// `replay_path` is where a recording is saved when the game quits.
+fn setup_game(
+    world: &mut World,
+    replay_path: Option<String>,
+) -> impl FnMut(Event, &mut World) -> bool {
+    // Setup things here.
+
+    let mut camera = Camera::new();
+    camera.clear_color = Some(Color::WHITE);
+    let mut controls = CameraControls::new();
+    controls.max_speed *= 100.;
+
+    //Spawn a camera and make it look towards the origin
+    let title_camera = world.spawn((
+        Transform::new()
+            .with_position(Vec3::new(-847.27747, 111.74761, -594.119))
+            .with_rotation(Quat::from_xyzw(
+                -0.11213023,
+                0.8636663,
+                -0.2205305,
+                -0.43913826,
+            )),
+        camera,
+        // controls,
+    ));
+
+    world.spawn(GameState {
+        game_mode: GameMode::Title,
+        can_grapple: false,
+        needs_reset: false,
+        player_max_height: 0.0,
+        victory: false,
+        run_seconds: 0.0,
+        rockets_fired: 0,
+        rockets_recorded: 0,
+        run_recorded: false,
+        restart_from_bottom: false,
+    });
+    ProfileLoader::setup(world);
 
-        // Setup UI
-        let mut ui_manager = UIManager::new(world);
+    let reflect_registry = world.get_singleton::<ReflectRegistry>();
+    reflect_registry.register::<CharacterController>();
+    reflect_registry.register::<RigidBody>();
 
-        let mut fonts = Fonts::empty();
-        fonts
-            .new_font_from_bytes(include_bytes!("../assets/Jomhuria-Regular.ttf"))
-            .unwrap();
-        //fonts.load_default_fonts();
+    let size_xz = 64;
 
-        let mut standard_context = StandardContext::new(
-            StandardStyle {
-                primary_text_color: Color::WHITE,
-                primary_color: Color::BLACK.with_alpha(0.5),
-                padding: 12.,
-                ..Default::default()
+    let terrain_description = TerrainDescription::load("assets/tower.json");
+    // A saved terrain takes priority so hand-tuned towers load exactly as they were saved.
+    // Saved streamed terrain keeps streaming, with its edits applied.
+    let mut terrain = Terrain::load("assets/tower.terrain").unwrap_or_else(|_| {
+        if STREAM_TERRAIN {
+            Terrain::new_streaming(size_xz, terrain_description)
+        } else {
+            Terrain::new(size_xz, 512, terrain_description)
+        }
+    });
+    terrain.mesh_style = TerrainMeshStyle::Smooth;
+    terrain.level_of_detail_distances = vec![400.0, 800.0];
+    terrain.create_chunks(world);
+
+    world.spawn((
+        Color::MAJORELLE_BLUE,
+        Mesh::SPHERE,
+        Transform::new().with_scale(Vec3::fill(30.0)),
+        Material::DEFAULT,
+        Collider::Sphere(0.5),
+    ));
+
+    let mut player_camera_entity = None;
+    //let mut terrain_chunks = Vec::new();
+
+    /*
+    for i in 0..2 {k
+        let (world_chunk_mesh, has_at_least_one_triangle) =
+            (|graphics: &mut Graphics, meshes: &mut Assets<Mesh>| {
+                let generated_chunk_mesh =
+                    terrain.create_chunk_mesh(Vec3u::new(0, offset_y, 0), size_xz);
+                let has_at_least_one_triangle = !generated_chunk_mesh.indices.is_empty();
+                (
+                    meshes.add(Mesh::new(graphics, generated_chunk_mesh)),
+                    has_at_least_one_triangle,
+                )
+            })
+            .run(world);
+
+        terrain_meshes.push(world_chunk_mesh.clone());
+        if has_at_least_one_triangle {
+            terrain_chunks.push(world.spawn((
+                world_chunk_mesh,
+                Material::DEFAULT,
+                Transform::new().with_position(
+                    Vec3::Y * (offset_y as f32 / size_xz as f32) * terrain.scale + world_offset,
+                ),
+                Collider::AttachedMesh,
+            )));
+        } else {
+            println!("EMPTY CHUNK!");
+        }
+        offset_y += size_xz;
+    }
+    */
+
+    spawn_reflection_probe(world, "assets/venice_sunset.hdr");
+
+    let sounds = world.get_singleton::<Assets<Sound>>();
+    let upbeat_vibes_song = sounds.load("assets/upbeat_vibes.wav");
+    //let shoot_grapple_sound = sounds.load("assets/shoot_grapple.wav");
+
+    world.spawn(RapierPhysicsManager::new());
+
+    // Setup UI
+    let mut ui_manager = UIManager::new(world);
+
+    let mut fonts = Fonts::empty();
+    fonts
+        .new_font_from_bytes(include_bytes!("../assets/Jomhuria-Regular.ttf"))
+        .unwrap();
+    //fonts.load_default_fonts();
+
+    let mut standard_context = StandardContext::new(
+        StandardStyle {
+            primary_text_color: Color::WHITE,
+            primary_color: Color::BLACK.with_alpha(0.5),
+            padding: 12.,
+            ..Default::default()
+        },
+        StandardInput::default(),
+        fonts,
+    );
+
+    let mut ui = get_ui();
+
+    world.spawn((Transform::new(), Camera::new_for_user_interface()));
+
+    let worlds = world.get_singleton::<Assets<World>>();
+    let models = [
+        worlds.load_with_options(
+            "assets/boat3.glb",
+            LoadWorldOptions {
+                run_on_world: Some(Box::new(|world: &mut World| {
+                    prepare_model_world(world, Vec3::fill(3.0));
+                    let mut commands = Commands::new();
+                    (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
+                        for m in entities_with_mesh.entities_and_components() {
+                            commands.add_component(*m.0, Color::RED);
+                            commands.add_component(*m.0, Collider::AttachedMeshConvex);
+                            commands.add_component(*m.0, Interpolated::new());
+                            commands.add_component(
+                                *m.0,
+                                RigidBody::new(RigidBodyInner {
+                                    kinematic: false,
+                                    can_rotate: (true, true, true),
+                                    gravity_scale: 0.0,
+                                    linear_damping: 0.0,
+                                    angular_damping: 0.0,
+                                    velocity: Vec3::ZERO,
+                                    ..Default::default()
+                                }),
+                            );
+                        }
+                    })
+                    .run(world);
+                    commands.apply(world);
+                })),
             },
-            StandardInput::default(),
-            fonts,
-        );
-
-        let mut ui = get_ui();
-
-        world.spawn((Transform::new(), Camera::new_for_user_interface()));
-
-        let worlds = world.get_singleton::<Assets<World>>();
-        let models = [
-            worlds.load_with_options(
-                "assets/boat3.glb",
-                LoadWorldOptions {
-                    run_on_world: Some(Box::new(|world: &mut World| {
-                        prepare_model_world(world, Vec3::fill(3.0));
-                        let mut commands = Commands::new();
-                        (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
-                            for m in entities_with_mesh.entities_and_components() {
-                                commands.add_component(*m.0, Color::RED);
-                                commands.add_component(*m.0, Collider::AttachedMeshConvex);
-                                commands.add_component(
-                                    *m.0,
-                                    RigidBody::new(RigidBodyInner {
-                                        kinematic: false,
-                                        can_rotate: (true, true, true),
-                                        gravity_scale: 0.0,
-                                        linear_damping: 0.0,
-                                        angular_damping: 0.0,
-                                        velocity: Vec3::ZERO,
-                                        ..Default::default()
-                                    }),
-                                );
-                            }
-                        })
-                        .run(world);
-                        commands.apply(world);
-                    })),
-                },
-            ),
-            worlds.load_with_options(
-                "assets/floating_island.glb",
-                LoadWorldOptions {
-                    run_on_world: Some(Box::new(|world: &mut World| {
-                        prepare_model_world(world, Vec3::fill(1.0));
-                        let mut commands = Commands::new();
-                        (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
-                            for m in entities_with_mesh.entities_and_components() {
-                                commands.add_component(*m.0, Collider::AttachedMeshConvex);
-                                commands.add_component(
-                                    *m.0,
-                                    RigidBody::new(RigidBodyInner {
-                                        kinematic: false,
-                                        can_rotate: (true, true, true),
-                                        gravity_scale: 0.0,
-                                        linear_damping: 1.0,
-                                        angular_damping: 1.0,
-                                        velocity: Vec3::ZERO,
-                                        ..Default::default()
-                                    }),
-                                );
-                            }
-                        })
-                        .run(world);
-                        commands.apply(world);
-                    })),
-                },
-            ),
-            worlds.load_with_options(
-                "assets/barrel.glb",
-                LoadWorldOptions {
-                    run_on_world: Some(Box::new(|world: &mut World| {
-                        prepare_model_world(world, Vec3::fill(0.3));
-                        let mut commands = Commands::new();
-                        (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
-                            for m in entities_with_mesh.entities_and_components() {
-                                commands.add_component(*m.0, Collider::AttachedMeshConvex);
-                                commands.add_component(
-                                    *m.0,
-                                    RigidBody::new(RigidBodyInner {
-                                        gravity_scale: 1.0,
-
-                                        ..Default::default()
-                                    }),
-                                );
-                            }
-                        })
-                        .run(world);
-                        commands.apply(world);
-                    })),
-                },
-            ),
-            worlds.load_with_options(
-                "assets/boat3.glb",
-                LoadWorldOptions {
-                    run_on_world: Some(Box::new(|world: &mut World| {
-                        prepare_model_world(world, Vec3::fill(2.0));
-                        let mut commands = Commands::new();
-                        (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
-                            for m in entities_with_mesh.entities_and_components() {
-                                commands.add_component(*m.0, Color::RED);
-                                commands.add_component(*m.0, Collider::AttachedMeshConvex);
-                                commands.add_component(
-                                    *m.0,
-                                    RigidBody::new(RigidBodyInner {
-                                        kinematic: false,
-                                        can_rotate: (true, true, true),
-                                        gravity_scale: 0.1,
-                                        linear_damping: 0.0,
-                                        angular_damping: 0.0,
-                                        velocity: Vec3::ZERO,
-                                        ..Default::default()
-                                    }),
-                                );
-                            }
-                        })
-                        .run(world);
-                        commands.apply(world);
-                    })),
-                },
-            ),
-            worlds.load_with_options(
-                "assets/rocket.glb",
-                LoadWorldOptions {
-                    run_on_world: Some(Box::new(|world: &mut World| {
-                        let mut commands = Commands::new();
-
-                        (|transform: Query<&mut Transform>| {
-                            for (e, _) in transform.entities_and_components().next() {
-                                commands.add_component(
-                                    *e,
-                                    Powerup {
-                                        grants_rockets: true,
-                                        collected: false,
-                                        grants_cable_length: false,
-                                    },
-                                )
-                            }
-                        })
-                        .run(world);
-                        commands.apply(world);
-                        prepare_model_world(world, Vec3::fill(2.0));
-                    })),
-                },
-            ),
-        ];
-
-        // Setup the water plane
-        let water_material = (|materials: &mut Assets<Material>| {
-            materials.add(new_pbr_material(
-                Shader::PHYSICALLY_BASED_TRANSPARENT_DOUBLE_SIDED,
-                PBRProperties {
-                    roughness: 0.02,
-                    base_color: Color::new_from_bytes(7, 80, 97, 200),
-                    ..Default::default()
-                },
-            ))
-        })
-        .run(world);
-
-        world.spawn((
-            Transform::new().with_scale(Vec3::fill(10000.)),
-            Mesh::PLANE,
-            water_material.clone(),
-            RenderFlags::DEFAULT.with_layer(RenderFlags::DO_NOT_CAST_SHADOWS),
-        ));
-
-        world.spawn((
-            Transform::new()
-                .with_position(Vec3::Y * -1000.0)
-                .with_scale(Vec3::fill(1000000.)),
-            Mesh::PLANE,
-            Color::BLACK,
-            RenderFlags::DEFAULT.with_layer(RenderFlags::DO_NOT_CAST_SHADOWS),
-        ));
-
-        ExplosionManager::setup_system(world);
-
-        let mut random = Random::new_with_seed(13);
-
-        let mut camera_rotation_angle: f32 = 0.0;
-
-        let mut loaded = false;
-
-        let low_poly_uv_sphere = (|meshes: &mut Assets<Mesh>, graphics: &mut Graphics| {
-            meshes.add(Mesh::new(graphics, uv_sphere(4, 4, Vec2::ONE)))
-        })
-        .run(world);
-
-        move |event: Event, world: &mut World| {
-            match event {
-                Event::KappEvent(event) => {
-                    if ui_manager.handle_event(&event, world, &mut standard_context) {
-                        return true;
-                    }
-                    match event {
-                        KappEvent::PointerDown { .. } | KappEvent::KeyDown { .. } => {
-                            //klog::log!("EVENT: {:?}", event);
-                            let world_state = world.get_singleton::<GameState>();
-                            match world_state.game_mode {
-                                GameMode::GameOver | GameMode::Title => {
-                                    world_state.needs_reset = true;
-                                }
-                                _ => {}
-                            }
+        ),
+        worlds.load_with_options(
+            "assets/floating_island.glb",
+            LoadWorldOptions {
+                run_on_world: Some(Box::new(|world: &mut World| {
+                    prepare_model_world(world, Vec3::fill(1.0));
+                    let mut commands = Commands::new();
+                    (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
+                        for m in entities_with_mesh.entities_and_components() {
+                            commands.add_component(*m.0, Collider::AttachedMeshConvex);
+                            commands.add_component(*m.0, Interpolated::new());
+                            commands.add_component(
+                                *m.0,
+                                RigidBody::new(RigidBodyInner {
+                                    kinematic: false,
+                                    can_rotate: (true, true, true),
+                                    gravity_scale: 0.0,
+                                    linear_damping: 1.0,
+                                    angular_damping: 1.0,
+                                    velocity: Vec3::ZERO,
+                                    ..Default::default()
+                                }),
+                            );
                         }
+                    })
+                    .run(world);
+                    commands.apply(world);
+                })),
+            },
+        ),
+        worlds.load_with_options(
+            "assets/barrel.glb",
+            LoadWorldOptions {
+                run_on_world: Some(Box::new(|world: &mut World| {
+                    prepare_model_world(world, Vec3::fill(0.3));
+                    let mut commands = Commands::new();
+                    (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
+                        for m in entities_with_mesh.entities_and_components() {
+                            commands.add_component(*m.0, Collider::AttachedMeshConvex);
+                            commands.add_component(*m.0, Interpolated::new());
+                            commands.add_component(
+                                *m.0,
+                                RigidBody::new(RigidBodyInner {
+                                    gravity_scale: 1.0,
 
-                        _ => {}
-                    }
-                }
-                Event::FixedUpdate => {
-                    // Check that all models are loaded.
-                    (|worlds: &mut Assets<World>, game_state: &mut GameState| {
-                        loaded = true;
-                        for asset in &models {
-                            if worlds.is_placeholder(asset) {
-                                loaded = false;
-                                break;
-                            }
+                                    ..Default::default()
+                                }),
+                            );
                         }
                     })
                     .run(world);
-
-                    // Start the game.
-
-                    (|game_state: &mut GameState, input: &Input| {
-                        /*
-                        if input.key_down(Key::Space) {
-                            game_state.game_mode = GameMode::Game
+                    commands.apply(world);
+                })),
+            },
+        ),
+        worlds.load_with_options(
+            "assets/boat3.glb",
+            LoadWorldOptions {
+                run_on_world: Some(Box::new(|world: &mut World| {
+                    prepare_model_world(world, Vec3::fill(2.0));
+                    let mut commands = Commands::new();
+                    (|entities_with_mesh: Query<&mut Handle<Mesh>>| {
+                        for m in entities_with_mesh.entities_and_components() {
+                            commands.add_component(*m.0, Color::RED);
+                            commands.add_component(*m.0, Collider::AttachedMeshConvex);
+                            commands.add_component(*m.0, Interpolated::new());
+                            commands.add_component(
+                                *m.0,
+                                RigidBody::new(RigidBodyInner {
+                                    kinematic: false,
+                                    can_rotate: (true, true, true),
+                                    gravity_scale: 0.1,
+                                    linear_damping: 0.0,
+                                    angular_damping: 0.0,
+                                    velocity: Vec3::ZERO,
+                                    ..Default::default()
+                                }),
+                            );
                         }
-                        */
-                        // if input.key_down(Key::T) {
-                        //     game_state.game_mode = GameMode::Title
-                        // }
                     })
                     .run(world);
+                    commands.apply(world);
+                })),
+            },
+        ),
+        worlds.load_with_options(
+            "assets/rocket.glb",
+            LoadWorldOptions {
+                run_on_world: Some(Box::new(|world: &mut World| {
+                    let mut commands = Commands::new();
+
+                    (|transform: Query<&mut Transform>| {
+                        for (e, _) in transform.entities_and_components().next() {
+                            commands.add_component(
+                                *e,
+                                Powerup::new(PowerupKind::RocketAmmo {
+                                    rockets: 30,
+                                    homing: true,
+                                }),
+                            );
+                            commands.add_component(*e, Collider::Sphere(1.35));
+                            commands.add_component(*e, Powerup::sensor_properties());
+                            commands.add_component(*e, RenderFlags::DEFAULT);
+                        }
+                    })
+                    .run(world);
+                    commands.apply(world);
+                    prepare_model_world(world, Vec3::fill(2.0));
+                })),
+            },
+        ),
+    ];
+
+    // Setup the water plane
+    let water_material = (|materials: &mut Assets<Material>| {
+        materials.add(new_pbr_material(
+            Shader::PHYSICALLY_BASED_TRANSPARENT_DOUBLE_SIDED,
+            PBRProperties {
+                roughness: 0.02,
+                base_color: Color::new_from_bytes(7, 80, 97, 200),
+                ..Default::default()
+            },
+        ))
+    })
+    .run(world);
 
-                    let needs_setup = &mut world.get_singleton::<GameState>().needs_reset;
-                    if loaded && *needs_setup {
-                        println!("RESETTING");
-                        *needs_setup = false;
-
-                        let mut commands = Commands::new();
+    world.spawn((
+        Transform::new().with_scale(Vec3::fill(10000.)),
+        Mesh::PLANE,
+        water_material.clone(),
+        RenderFlags::DEFAULT.with_layer(RenderFlags::DO_NOT_CAST_SHADOWS),
+    ));
 
-                        // (|rigid_bodies: Query<
-                        //     &mut RapierRigidBody,
-                        //     Without<CharacterController>,
-                        // >| {
-                        //     for (entity, _) in rigid_bodies.entities_and_components() {
-                        //         commands.add_component(*entity, ToDespawn);
-                        //     }
-                        // })
-                        // .run(world);
-                        commands.apply(world);
+    world.spawn((
+        Transform::new()
+            .with_position(Vec3::Y * -1000.0)
+            .with_scale(Vec3::fill(1000000.)),
+        Mesh::PLANE,
+        Color::BLACK,
+        RenderFlags::DEFAULT.with_layer(RenderFlags::DO_NOT_CAST_SHADOWS),
+    ));
 
-                        let game_state = world.get_singleton::<GameState>();
-                        game_state.game_mode = GameMode::Game;
-                        game_state.player_max_height = 0.0;
+    ExplosionManager::setup_system(world);
+    PowerupManager::setup_system(world);
 
-                        println!("SETTING UP");
+    let mut random = Random::new_with_seed(13);
 
-                        // Setup the player
+    let mut camera_rotation_angle: f32 = 0.0;
 
-                        let mut setup_already = false;
-                        // Reset or spawn the player
-                        let player_start_transform = Transform::new()
-                            .with_position(Vec3::new(38.728767, 47.28899, 22.055452))
-                            .with_rotation(Quat::from_angle_axis(
-                                std::f32::consts::TAU * 0.3,
-                                Vec3::Y,
-                            ));
-                        if (|player: (&mut CharacterController, &mut Transform, &mut RigidBody)| {
-                            *player.1 = player_start_transform;
-                            player.2.velocity = Vec3::ZERO;
-                            player.2.mutated_position = true;
-                            player.2.mutated_velocity = true;
-                            player.0.reset();
-                            setup_already = true;
-                        })
-                        .try_run(world)
-                        .is_err()
-                        {
-                            let player_audio_source = AudioSource::new().with_volume(0.2);
-
-                            let camera = world.spawn((
-                                Transform::new().with_position(Vec3::Y * 1.0),
-                                {
-                                    let mut camera = Camera::new();
-                                    camera.clear_color = Some(Color::WHITE);
-                                    camera.enabled = false;
-                                    camera.set_near_plane(0.2);
-                                    camera
-                                },
-                                CharacterControllerCamera,
-                                Listener::new(),
-                                MouseLook::new(),
-                                player_audio_source,
-                            ));
+    let mut loaded = false;
 
-                            player_camera_entity = Some(camera);
+    let low_poly_uv_sphere = (|meshes: &mut Assets<Mesh>, graphics: &mut Graphics| {
+        meshes.add(Mesh::new(graphics, uv_sphere(4, 4, Vec2::ONE)))
+    })
+    .run(world);
 
-                            // Setup the player
-                            let character_controller = CharacterController::new(world);
+    move |event: Event, world: &mut World| {
+        match event {
+            Event::KappEvent(event) => {
+                if let (KappEvent::Quit, Some(replay_path)) = (&event, &replay_path) {
+                    let input_replay = world.get_singleton::<InputReplay>();
+                    if let Some(replay) = input_replay.stop_recording() {
+                        if let Err(error) = replay.save(replay_path) {
+                            println!("Could not save replay {:?}: {:?}", replay_path, error);
+                        }
+                    }
+                }
+                if ui_manager.handle_event(&event, world, &mut standard_context) {
+                    return true;
+                }
+                match event {
+                    KappEvent::PointerDown { .. } | KappEvent::KeyDown { .. } => {
+                        //klog::log!("EVENT: {:?}", event);
+                        let restart_from_bottom = match &event {
+                            KappEvent::KeyDown { key, .. } => world
+                                .get_singleton::<InputActions>()
+                                .bindings(RESTART)
+                                .contains(&InputBinding::Key(*key)),
+                            _ => false,
+                        };
+                        let world_state = world.get_singleton::<GameState>();
+                        match world_state.game_mode {
+                            GameMode::GameOver | GameMode::Title => {
+                                world_state.needs_reset = true;
+                                world_state.restart_from_bottom = restart_from_bottom;
+                            }
+                            _ => {}
+                        }
+                    }
 
-                            let character_parent = world.spawn((
-                                player_start_transform,
-                                Collider::Sphere(1.3),
-                                RigidBody::new(RigidBodyInner {
-                                    kinematic: false,
-                                    can_rotate: (false, false, false),
-                                    ..Default::default()
-                                }),
-                                character_controller,
-                                AudioSource::new(),
-                            ));
-                            set_parent(world, Some(character_parent), camera);
+                    _ => {}
+                }
+            }
+            Event::FixedUpdate => {
+                // Check that all models are loaded.
+                (|worlds: &mut Assets<World>, game_state: &mut GameState| {
+                    loaded = true;
+                    for asset in &models {
+                        if worlds.is_placeholder(asset) {
+                            loaded = false;
+                            break;
                         }
+                    }
+                })
+                .run(world);
+
+                // Start the game.
+
+                (|game_state: &mut GameState, input: &Input| {
+                    /*
+                    if input.key_down(Key::Space) {
+                        game_state.game_mode = GameMode::Game
+                    }
+                    */
+                    // if input.key_down(Key::T) {
+                    //     game_state.game_mode = GameMode::Title
+                    // }
+                })
+                .run(world);
+
+                // After dying, continue from the last checkpoint unless the player chose to restart the climb.
+                let game_state = world.get_singleton::<GameState>();
+                if loaded
+                    && game_state.needs_reset
+                    && game_state.game_mode == GameMode::GameOver
+                    && !game_state.restart_from_bottom
+                {
+                    respawn_at_checkpoint(world);
+                }
 
-                        /*
-                        world.spawn((
-                            Transform::new()
-                                .with_scale(Vec3::fill(200.))
-                                .with_position(-Vec3::Y * 99. + Vec3::XZ * 100.),
-                            Mesh::CUBE,
-                            Material::DEFAULT,
-                            Color::WHITE,
-                            Collider::Cuboid(Vec3::fill(0.5)),
-                            RenderFlags::DEFAULT.with_layer(RenderFlags::DO_NOT_CAST_SHADOWS),
+                let needs_setup = &mut world.get_singleton::<GameState>().needs_reset;
+                if loaded && *needs_setup {
+                    println!("RESETTING");
+                    *needs_setup = false;
+
+                    let mut commands = Commands::new();
+
+                    // (|rigid_bodies: Query<
+                    //     &mut RapierRigidBody,
+                    //     Without<CharacterController>,
+                    // >| {
+                    //     for (entity, _) in rigid_bodies.entities_and_components() {
+                    //         commands.add_component(*entity, ToDespawn);
+                    //     }
+                    // })
+                    // .run(world);
+                    commands.apply(world);
+
+                    world.get_singleton::<GameState>().start_run();
+
+                    println!("SETTING UP");
+
+                    // Setup the player
+
+                    let mut setup_already = false;
+                    // Reset or spawn the player
+                    let player_start_transform = Transform::new()
+                        .with_position(Vec3::new(38.728767, 47.28899, 22.055452))
+                        .with_rotation(Quat::from_angle_axis(std::f32::consts::TAU * 0.3, Vec3::Y));
+                    if (|player: (
+                        &mut CharacterController,
+                        &mut Transform,
+                        &mut RigidBody,
+                        &mut Interpolated,
+                    ),
+                         rapier_physics: &mut RapierPhysicsManager| {
+                        *player.1 = player_start_transform;
+                        player.3.snap(player_start_transform);
+                        player.2.velocity = Vec3::ZERO;
+                        player.2.mutated_position = true;
+                        player.2.mutated_velocity = true;
+                        player.0.reset(rapier_physics);
+                        setup_already = true;
+                    })
+                    .try_run(world)
+                    .is_err()
+                    {
+                        let player_audio_source = AudioSource::new().with_volume(0.2);
+
+                        let camera = world.spawn((
+                            Transform::new().with_position(Vec3::Y * 1.0),
+                            {
+                                let mut camera = Camera::new();
+                                camera.clear_color = Some(Color::WHITE);
+                                camera.enabled = false;
+                                camera.set_near_plane(0.2);
+                                camera
+                            },
+                            CharacterControllerCamera,
+                            Listener::new(),
+                            MouseLook::new(),
+                            player_audio_source,
+                            Interpolated::new(),
                         ));
-                        */
 
-                        reset_powerups.run(world);
+                        player_camera_entity = Some(camera);
 
-                        if !setup_already {
-                            for _ in 0..150 {
-                                let random_position =
-                                    Vec3::new(
-                                        random.f32() * terrain.scale,
-                                        random.f32() * 2000.0 + 50.,
-                                        random.f32() * terrain.scale,
-                                    ) - Vec3::new(terrain.scale / 2.0, 0.0, terrain.scale / 2.0);
-                                world.spawn((
-                                    Transform::new()
-                                        .with_position(random_position)
-                                        .with_scale(Vec3::fill(8.0)),
-                                    low_poly_uv_sphere.clone(),
-                                    Collider::Sphere(0.5),
-                                    Color::from_srgb_hex(0xFFD700, 1.0),
-                                    Material::UNLIT,
-                                    Powerup {
-                                        collected: false,
-                                        grants_rockets: false,
-                                        grants_cable_length: true,
-                                    },
-                                ));
-                            }
-                            setup_worm(world);
+                        // Setup the player
+                        let character_controller = CharacterController::new(world);
+
+                        let character_parent = world.spawn((
+                            player_start_transform,
+                            Collider::Sphere(1.3),
+                            RigidBody::new(RigidBodyInner {
+                                kinematic: false,
+                                can_rotate: (false, false, false),
+                                ..Default::default()
+                            }),
+                            character_controller,
+                            AudioSource::new(),
+                            Interpolated::new(),
+                        ));
+                        set_parent(world, Some(character_parent), camera);
+                    }
+
+                    /*
+                    world.spawn((
+                        Transform::new()
+                            .with_scale(Vec3::fill(200.))
+                            .with_position(-Vec3::Y * 99. + Vec3::XZ * 100.),
+                        Mesh::CUBE,
+                        Material::DEFAULT,
+                        Color::WHITE,
+                        Collider::Cuboid(Vec3::fill(0.5)),
+                        RenderFlags::DEFAULT.with_layer(RenderFlags::DO_NOT_CAST_SHADOWS),
+                    ));
+                    */
 
-                            (|worlds: &mut Assets<World>| {
-                                let mut rocket = worlds.get_mut(&models[4]).clone_world();
+                    reset_powerups.run(world);
+                    clear_checkpoints.run(world);
+
+                    if !setup_already {
+                        for _ in 0..150 {
+                            let random_position =
+                                Vec3::new(
+                                    random.f32() * terrain.scale,
+                                    random.f32() * 2000.0 + 50.,
+                                    random.f32() * terrain.scale,
+                                ) - Vec3::new(terrain.scale / 2.0, 0.0, terrain.scale / 2.0);
+                            let kind = match random.range_u32(0..20) {
+                                0 => PowerupKind::ExtraJump,
+                                1 => PowerupKind::SlowFall { seconds: 8.0 },
+                                2 => PowerupKind::SlowWorm { seconds: 15.0 },
+                                _ => PowerupKind::CableLength { meters: 20.0 },
+                            };
+                            world.spawn((
+                                Transform::new()
+                                    .with_position(random_position)
+                                    .with_scale(Vec3::fill(8.0)),
+                                low_poly_uv_sphere.clone(),
+                                Powerup::COLLIDER,
+                                Powerup::sensor_properties(),
+                                kind.color(),
+                                Material::UNLIT,
+                                Powerup::new(kind),
+                                RenderFlags::DEFAULT,
+                            ));
+                        }
+                        setup_worm(world);
+                        setup_checkpoints(world);
+
+                        (|worlds: &mut Assets<World>| {
+                            let mut rocket = worlds.get_mut(&models[4]).clone_world();
 
-                                (|transform: &mut Transform| {
-                                    transform.position = Vec3::Y * 3191.0;
-                                })
-                                .run(&mut rocket);
+                            (|transform: &mut Transform| {
+                                transform.position = Vec3::Y * 3191.0;
+                            })
+                            .run(&mut rocket);
 
-                                commands.add_world(rocket);
+                            commands.add_world(rocket);
 
-                                for _ in 0..300 {
-                                    let v = random.f32();
-                                    let random_position = Vec3::new(
+                            for _ in 0..300 {
+                                let v = random.f32();
+                                let random_position =
+                                    Vec3::new(
                                         random.f32() * terrain.scale,
                                         random.f32() * 3000.0 + 50.,
                                         random.f32() * terrain.scale,
-                                    ) - Vec3::new(
-                                        terrain.scale / 2.0,
-                                        0.0,
-                                        terrain.scale / 2.0,
-                                    );
-                                    if v > 0.3 {
-                                        let mut boat = worlds.get_mut(&models[0]).clone_world();
-
+                                    ) - Vec3::new(terrain.scale / 2.0, 0.0, terrain.scale / 2.0);
+                                if v > 0.3 {
+                                    let mut boat = worlds.get_mut(&models[0]).clone_world();
+
+                                    (|transform: &mut Transform| {
+                                        transform.position = random_position;
+                                        transform.rotation = Quat::from_angle_axis(
+                                            random.f32() * std::f32::consts::TAU,
+                                            Vec3::Y,
+                                        );
+                                    })
+                                    .run(&mut boat);
+
+                                    commands.add_world(boat);
+
+                                    // Spawn some barrels on top
+                                    for _ in 0..3 {
+                                        let mut barrel = worlds.get_mut(&models[2]).clone_world();
+
+                                        let random_offset = Vec3::new(
+                                            random.f32() * 2.0 - 1.0 - 4.0,
+                                            7.0,
+                                            random.f32() * 2.0 - 1.0 - 4.0,
+                                        );
                                         (|transform: &mut Transform| {
-                                            transform.position = random_position;
+                                            transform.position = random_position + random_offset;
+
                                             transform.rotation = Quat::from_angle_axis(
                                                 random.f32() * std::f32::consts::TAU,
                                                 Vec3::Y,
                                             );
                                         })
-                                        .run(&mut boat);
-
-                                        commands.add_world(boat);
-
-                                        // Spawn some barrels on top
-                                        for _ in 0..3 {
-                                            let mut barrel =
-                                                worlds.get_mut(&models[2]).clone_world();
-
-                                            let random_offset = Vec3::new(
-                                                random.f32() * 2.0 - 1.0 - 4.0,
-                                                7.0,
-                                                random.f32() * 2.0 - 1.0 - 4.0,
-                                            );
-                                            (|transform: &mut Transform| {
-                                                transform.position =
-                                                    random_position + random_offset;
-
-                                                transform.rotation = Quat::from_angle_axis(
-                                                    random.f32() * std::f32::consts::TAU,
-                                                    Vec3::Y,
-                                                );
-                                            })
-                                            .run(&mut barrel);
-
-                                            commands.add_world(barrel);
-                                        }
-                                    } else {
-                                        let mut boat = worlds.get_mut(&models[1]).clone_world();
+                                        .run(&mut barrel);
 
+                                        commands.add_world(barrel);
+                                    }
+                                } else {
+                                    let mut boat = worlds.get_mut(&models[1]).clone_world();
+
+                                    (|transform: &mut Transform| {
+                                        transform.position = random_position;
+
+                                        transform.rotation = Quat::from_angle_axis(
+                                            random.f32() * std::f32::consts::TAU,
+                                            Vec3::Y,
+                                        );
+                                    })
+                                    .run(&mut boat);
+
+                                    commands.add_world(boat);
+
+                                    // Spawn some barrels on top
+                                    for _ in 0..3 {
+                                        let mut barrel = worlds.get_mut(&models[2]).clone_world();
+
+                                        let random_range = 4.0;
+                                        let random_offset = Vec3::new(
+                                            random.f32() * random_range - random_range / 2.0 - 4.0,
+                                            7.0,
+                                            random.f32() * random_range - random_range / 2.0 - 4.0,
+                                        );
                                         (|transform: &mut Transform| {
-                                            transform.position = random_position;
+                                            transform.position = random_position + random_offset;
 
                                             transform.rotation = Quat::from_angle_axis(
                                                 random.f32() * std::f32::consts::TAU,
                                                 Vec3::Y,
                                             );
                                         })
-                                        .run(&mut boat);
-
-                                        commands.add_world(boat);
-
-                                        // Spawn some barrels on top
-                                        for _ in 0..3 {
-                                            let mut barrel =
-                                                worlds.get_mut(&models[2]).clone_world();
-
-                                            let random_range = 4.0;
-                                            let random_offset = Vec3::new(
-                                                random.f32() * random_range
-                                                    - random_range / 2.0
-                                                    - 4.0,
-                                                7.0,
-                                                random.f32() * random_range
-                                                    - random_range / 2.0
-                                                    - 4.0,
-                                            );
-                                            (|transform: &mut Transform| {
-                                                transform.position =
-                                                    random_position + random_offset;
-
-                                                transform.rotation = Quat::from_angle_axis(
-                                                    random.f32() * std::f32::consts::TAU,
-                                                    Vec3::Y,
-                                                );
-                                            })
-                                            .run(&mut barrel);
-
-                                            commands.add_world(barrel);
-                                        }
+                                        .run(&mut barrel);
+
+                                        commands.add_world(barrel);
                                     }
                                 }
-                            })
-                            .run(world);
-                        }
-                        (|(worm_transform, worm): (&mut Transform, &mut Worm)| {
-                            worm_transform.position = Vec3::Y * -200.0;
-                            worm.lerp_target = None; // Some(3000.0);
-                            worm.rockets_hit = 0;
+                            }
                         })
                         .run(world);
-
-                        commands.apply(world);
-
-                        update_root_global_transforms.run(world);
-                        update_global_transforms.run(world);
-                        apply_commands(world);
                     }
+                    (|(worm_transform, worm): (&mut Transform, &mut WormBehavior),
+                      worm_events: &mut WormEvents| {
+                        worm_transform.position = Vec3::Y * -200.0;
+                        worm.reset();
+                        *worm_events = WormEvents::default();
+                    })
+                    .run(world);
+                    reset_worm_voice(world);
 
-                    let game_state = world.get_singleton::<GameState>();
+                    commands.apply(world);
 
-                    match game_state.game_mode {
-                        GameMode::Game => {
-                            world
-                                .get_component_mut::<Camera>(title_camera)
-                                .unwrap()
-                                .enabled = false;
-                            if let Some(player_camera_entity) = player_camera_entity {
-                                let transform = *world
-                                    .get_component_mut::<GlobalTransform>(player_camera_entity)
-                                    .unwrap();
-                                let camera = world
-                                    .get_component_mut::<Camera>(player_camera_entity)
-                                    .unwrap();
-                                // Blend the color towards blue
-                                camera.clear_color = Some(Color::interpolate(
-                                    Color::WHITE,
-                                    Color::AZURE.with_chroma(0.4),
-                                    (transform.position.y / 3191.0).clamp(0.0, 1.0),
-                                ));
-                                camera.enabled = true;
+                    update_root_global_transforms.run(world);
+                    update_global_transforms.run(world);
+                    apply_commands(world);
+                }
+
+                let terrain_focus = match world.get_singleton::<GameState>().game_mode {
+                    GameMode::Game => {
+                        (|player: (&GlobalTransform, &CharacterController)| player.0.position)
+                            .try_run(world)
+                            .ok()
+                    }
+                    _ => None,
+                }
+                .unwrap_or_else(|| {
+                    world
+                        .get_component_mut::<Transform>(title_camera)
+                        .unwrap()
+                        .position
+                });
+                terrain.update_streaming(world, terrain_focus);
+                terrain.update_level_of_detail(world, terrain_focus);
+
+                ProfileLoader::update.run(world);
+
+                let game_state = world.get_singleton::<GameState>();
+
+                match game_state.game_mode {
+                    GameMode::Game => {
+                        (|game_state: &mut GameState, time: &Time| {
+                            if !game_state.victory {
+                                game_state.run_seconds += time.fixed_time_step as f32;
                             }
-                            ExplosionManager::fixed_update_system.run(world);
-                            MouseLook::fixed_update.run(world);
-                            CharacterController::fixed_update.run(world);
-                            RapierPhysicsManager::despawn.run(world);
-                            RapierPhysicsManager::fixed_update(world);
-                            collect_powerups.run(world);
-                            check_rocket_collisions_system.run(world);
-                            worm::run_worm(world);
+                        })
+                        .run(world);
+                        world
+                            .get_component_mut::<Camera>(title_camera)
+                            .unwrap()
+                            .enabled = false;
+                        if let Some(player_camera_entity) = player_camera_entity {
+                            let transform = *world
+                                .get_component_mut::<GlobalTransform>(player_camera_entity)
+                                .unwrap();
+                            let camera = world
+                                .get_component_mut::<Camera>(player_camera_entity)
+                                .unwrap();
+                            // Blend the color towards blue
+                            camera.clear_color = Some(Color::interpolate(
+                                Color::WHITE,
+                                Color::AZURE.with_chroma(0.4),
+                                (transform.position.y / 3191.0).clamp(0.0, 1.0),
+                            ));
+                            camera.enabled = true;
                         }
-                        GameMode::GameOver => {
-                            println!("UNLOCKING MOUSE");
-                            MouseLook::unlock.run(world);
+                        ExplosionManager::fixed_update_system.run(world);
+                        let craters = world.get_singleton::<ExplosionManager>().take_craters();
+                        for crater in craters {
+                            terrain.carve_sphere(world, crater.center, crater.radius, 2.0);
                         }
-                        GameMode::Title => {
-                            RapierPhysicsManager::despawn.run(world);
-                            RapierPhysicsManager::fixed_update(world);
+                        MouseLook::fixed_update.run(world);
+                        CharacterController::fixed_update.run(world);
+                        RapierPhysicsManager::despawn.run(world);
+                        RapierPhysicsManager::fixed_update(world);
+                        PowerupManager::collect_powerups.run(world);
+                        reach_checkpoints.run(world);
+                        check_rocket_collisions_system.run(world);
+                        worm::run_worm(world);
+                        record_finished_run.run(world);
+                    }
+                    GameMode::GameOver => {
+                        record_finished_run.run(world);
+                        println!("UNLOCKING MOUSE");
+                        MouseLook::unlock.run(world);
+                    }
+                    GameMode::Title => {
+                        RapierPhysicsManager::despawn.run(world);
+                        RapierPhysicsManager::fixed_update(world);
+                        world
+                            .get_component_mut::<Camera>(title_camera)
+                            .unwrap()
+                            .enabled = true;
+                        if let Some(player_camera_entity) = player_camera_entity {
                             world
-                                .get_component_mut::<Camera>(title_camera)
+                                .get_component_mut::<Camera>(player_camera_entity)
                                 .unwrap()
-                                .enabled = true;
-                            if let Some(player_camera_entity) = player_camera_entity {
-                                world
-                                    .get_component_mut::<Camera>(player_camera_entity)
-                                    .unwrap()
-                                    .enabled = false;
-                            }
-
-                            let camera_transform =
-                                world.get_component_mut::<Transform>(title_camera).unwrap();
-                            let distance = 500.0;
-                            let (sin, cos) = camera_rotation_angle.sin_cos();
-                            camera_rotation_angle += 0.0004;
-                            let y_offset = camera_rotation_angle * 5.0 * Vec3::Y;
-                            *camera_transform = camera_transform
-                                .with_position(Vec3::new(cos, 0.0, sin) * distance + y_offset)
-                                .looking_at(Vec3::Y * 750.0 + y_offset, Vec3::Y);
-                        }
-                        _ => {}
-                    }
-                    // Perform physics and game related updates here.
-                }
-                Event::Draw => {
-                    /*
-                    (|cameras: Query<(&Transform, &Camera)>| {
-                        for camera in cameras.iter() {
-                            if camera.1.enabled {
-                                println!("TRANSFORM: {:?}", camera.0);
-                            }
+                                .enabled = false;
                         }
-                    }).run(world);
-                    */
-                    Cable::update_meshes_system.run(world);
 
-                    ui_manager.prepare(world, &mut standard_context);
-                    ui_manager.layout(world, &mut standard_context, &mut ui);
-                    ui_manager.render_ui(world);
-                    // Things that occur before rendering can go here.
+                        let camera_transform =
+                            world.get_component_mut::<Transform>(title_camera).unwrap();
+                        let distance = 500.0;
+                        let (sin, cos) = camera_rotation_angle.sin_cos();
+                        camera_rotation_angle += 0.0004;
+                        let y_offset = camera_rotation_angle * 5.0 * Vec3::Y;
+                        *camera_transform = camera_transform
+                            .with_position(Vec3::new(cos, 0.0, sin) * distance + y_offset)
+                            .looking_at(Vec3::Y * 750.0 + y_offset, Vec3::Y);
+                    }
+                    _ => {}
                 }
+                // Perform physics and game related updates here.
             }
-
-            // Do not consume the event and allow other systems to respond to it.
-            false
-        }
-    });
-}
-
-#[derive(Component, Clone)]
-pub struct Powerup {
-    collected: bool,
-    grants_rockets: bool,
-    grants_cable_length: bool,
-}
-fn collect_powerups(
-    (player_transform, character_controller): (&GlobalTransform, &mut CharacterController),
-    mut powerups: Query<(&mut Transform, &mut Powerup)>,
-) {
-    for powerup in powerups.iter_mut() {
-        if !powerup.1.collected {
-            let l = (powerup.0.position - player_transform.position).length_squared();
-            let collected = if powerup.1.grants_cable_length {
-                l < 8.0 * 8.0
-            } else {
-                l < 4.0 * 4.0
-            };
-            if collected {
-                println!("COLLECT POWERUP");
-                powerup.1.collected = true;
-
-                // Cheap way to hide it
-                powerup.0.scale = Vec3::ZERO;
-                if powerup.1.grants_rockets {
-                    character_controller.can_shoot = true;
-                }
-                if powerup.1.grants_cable_length {
-                    character_controller.max_cable_length += 20.0;
-                }
+            Event::Draw => {
+                /*
+                (|cameras: Query<(&Transform, &Camera)>| {
+                    for camera in cameras.iter() {
+                        if camera.1.enabled {
+                            println!("TRANSFORM: {:?}", camera.0);
+                        }
+                    }
+                }).run(world);
+                */
+                Cable::update_meshes_system.run(world);
+
+                ui_manager.prepare(world, &mut standard_context);
+                ui_manager.layout(world, &mut standard_context, &mut ui);
+                ui_manager.render_ui(world);
+                // Things that occur before rendering can go here.
             }
         }
-    }
-}
 
-fn reset_powerups(mut powerups: Query<(&mut Transform, &mut Powerup)>) {
-    for powerup in powerups.iter_mut() {
-        powerup.1.collected = false;
-        if powerup.1.grants_cable_length {
-            powerup.0.scale = Vec3::fill(8.0);
-        } else {
-            powerup.0.scale = Vec3::fill(2.0);
-        }
+        // Do not consume the event and allow other systems to respond to it.
+        false
     }
 }
@@ -27,6 +27,7 @@ impl MouseLook {
         mut mouse_look: Query<(&mut Transform, &mut Self)>,
         app: &mut KappApplication,
         input: &Input,
+        input_actions: &InputActions,
     ) {
         for (transform, mouse_look) in mouse_look.iter_mut() {
             if mouse_look.mouse_lock {
@@ -67,7 +68,7 @@ impl MouseLook {
                 }
             }
 
-            if input.key(Key::Escape) {
+            if input_actions.held(input, RELEASE_MOUSE) {
                 app.set_cursor_visible(true);
                 app.unlock_mouse_position();
                 mouse_look.mouse_lock = false;
@@ -0,0 +1,185 @@
+use crate::*;
+
+/// What a powerup does when the player picks it up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PowerupKind {
+    /// Lengthens the grapple's cable.
+    CableLength { meters: f32 },
+    /// Allows firing rockets and adds to the player's ammo.
+    /// With `homing` rockets steer towards the worm's mouth.
+    RocketAmmo { rockets: u32, homing: bool },
+    /// Permanently adds a mid-air jump on top of `MAX_EXTRA_JUMPS`.
+    ExtraJump,
+    /// Reduces the player's gravity for a while.
+    SlowFall { seconds: f32 },
+    /// Slows the worm down for a while.
+    SlowWorm { seconds: f32 },
+}
+
+impl PowerupKind {
+    pub fn color(&self) -> Color {
+        match self {
+            Self::CableLength { .. } => Color::from_srgb_hex(0xFFD700, 1.0),
+            Self::RocketAmmo { .. } => Color::YELLOW,
+            Self::ExtraJump => Color::AZURE,
+            Self::SlowFall { .. } => Color::WHITE,
+            Self::SlowWorm { .. } => Color::RED,
+        }
+    }
+
+    fn notification(&self) -> &'static str {
+        match self {
+            Self::CableLength { .. } => "Your cable grows longer",
+            Self::RocketAmmo { .. } => "Rockets! Right-click to fire",
+            Self::ExtraJump => "You feel lighter on your feet. +1 jump",
+            Self::SlowFall { .. } => "The wind holds you up",
+            Self::SlowWorm { .. } => "The worm grows sluggish",
+        }
+    }
+
+    fn apply(&self, character_controller: &mut CharacterController, worm: &mut WormBehavior) {
+        match *self {
+            Self::CableLength { meters } => character_controller.max_cable_length += meters,
+            Self::RocketAmmo { rockets, homing } => {
+                character_controller.can_shoot = true;
+                character_controller.rocket_ammo += rockets;
+                character_controller.homing_rockets |= homing;
+            }
+            Self::ExtraJump => character_controller.bonus_jumps += 1,
+            Self::SlowFall { seconds } => {
+                character_controller.slow_fall_seconds =
+                    character_controller.slow_fall_seconds.max(seconds)
+            }
+            Self::SlowWorm { seconds } => worm.slow(seconds),
+        }
+    }
+}
+
+/// A pickup. It's collected when the player overlaps its sensor collider.
+/// Collected powerups are hidden with `RenderFlags::NONE` and lose their collider
+/// instead of being despawned so checkpoints can restore them.
+/// Powerups need a `RenderFlags` component.
+#[derive(Component, Clone)]
+pub struct Powerup {
+    pub kind: PowerupKind,
+    collected: bool,
+}
+
+impl Powerup {
+    /// The sensor the player overlaps to collect a powerup.
+    pub const COLLIDER: Collider = Collider::Sphere(0.85);
+
+    pub fn new(kind: PowerupKind) -> Self {
+        Self {
+            kind,
+            collected: false,
+        }
+    }
+
+    pub fn collected(&self) -> bool {
+        self.collected
+    }
+
+    /// Collected powerups have no collider so their sensor stops producing collision events.
+    /// `add_rapier_colliders` creates it again when the powerup is restored.
+    pub fn set_collected(
+        &mut self,
+        entity: Entity,
+        collected: bool,
+        render_flags: &mut RenderFlags,
+        rapier_physics: &mut RapierPhysicsManager,
+        commands: &mut Commands,
+    ) {
+        if collected == self.collected {
+            return;
+        }
+        self.collected = collected;
+        if collected {
+            *render_flags = RenderFlags::NONE;
+            if let Some(collider) = rapier_physics.collider_for_entity(entity) {
+                rapier_physics.remove_collider(entity, &RapierCollider(collider));
+            }
+            commands.remove_component::<RapierCollider>(entity);
+            commands.remove_component::<Collider>(entity);
+        } else {
+            *render_flags = RenderFlags::DEFAULT;
+            commands.add_component(entity, Self::COLLIDER);
+        }
+    }
+
+    /// Powerups are collected by overlapping a sensor instead of colliding with it.
+    pub fn sensor_properties() -> ColliderProperties {
+        ColliderProperties::new(ColliderPropertiesInner {
+            sensor: true,
+            ..Default::default()
+        })
+    }
+}
+
+/// Plays the pickup sound and shows what the last powerup did.
+#[derive(Component, Clone)]
+pub struct PowerupManager {
+    pickup_sound: Handle<Sound>,
+    /// A message for the UI to show and how many more seconds to show it for.
+    pub notification: Option<(&'static str, f32)>,
+}
+
+impl PowerupManager {
+    pub fn setup_system(world: &mut World) {
+        let pickup_sound = world
+            .get_singleton::<Assets<Sound>>()
+            .load("assets/pickup.wav");
+        world.spawn(Self {
+            pickup_sound,
+            notification: None,
+        });
+    }
+
+    pub fn collect_powerups(
+        &mut self,
+        rapier_physics: &mut RapierPhysicsManager,
+        commands: &mut Commands,
+        mut player: Query<(&mut CharacterController, &mut AudioSource)>,
+        mut powerups: Query<(&mut Powerup, &mut RenderFlags)>,
+        worm: &mut WormBehavior,
+        time: &Time,
+    ) {
+        if let Some((_, seconds_remaining)) = &mut self.notification {
+            *seconds_remaining -= time.fixed_time_step as f32;
+            if *seconds_remaining <= 0.0 {
+                self.notification = None;
+            }
+        }
+
+        for (player_entity, (character_controller, audio_source)) in
+            player.entities_and_components_mut()
+        {
+            let touched: Vec<Entity> = rapier_physics
+                .collision_events_with(*player_entity)
+                .filter(|(_, kind)| *kind == CollisionEventKind::Started)
+                .map(|(other, _)| other)
+                .collect();
+            for other in touched {
+                if let Some((powerup, render_flags)) = powerups.get_entity_components_mut(other) {
+                    if powerup.collected {
+                        continue;
+                    }
+                    powerup.set_collected(other, true, render_flags, rapier_physics, commands);
+                    powerup.kind.apply(character_controller, worm);
+                    audio_source.play(&self.pickup_sound, false);
+                    self.notification = Some((powerup.kind.notification(), 3.0));
+                }
+            }
+        }
+    }
+}
+
+pub fn reset_powerups(
+    rapier_physics: &mut RapierPhysicsManager,
+    commands: &mut Commands,
+    mut powerups: Query<(&mut Powerup, &mut RenderFlags)>,
+) {
+    for (entity, (powerup, render_flags)) in powerups.entities_and_components_mut() {
+        powerup.set_collected(*entity, false, render_flags, rapier_physics, commands);
+    }
+}
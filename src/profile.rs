@@ -0,0 +1,197 @@
+use crate::*;
+
+/// Where the profile is saved. On the web this is the key in the browser's IndexedDB.
+const PROFILE_PATH: &str = "profile.json";
+
+/// Statistics for a single run, from a reset until game over or victory.
+#[derive(Clone, Copy, Default, SerializeDeserialize)]
+pub struct RunStats {
+    pub max_height: f32,
+    pub seconds: f32,
+    pub rockets_fired: u32,
+    pub victory: bool,
+}
+
+/// The player's records. These persist between sessions.
+#[derive(Component, Clone, Default, SerializeDeserialize)]
+pub struct Profile {
+    pub runs: u32,
+    pub deaths: u32,
+    pub victories: u32,
+    pub rockets_fired: u32,
+    /// The highest the player has ever climbed.
+    pub best_height: f32,
+    /// The fastest victory in seconds. Only meaningful if `victories` isn't zero.
+    pub fastest_summit_seconds: f32,
+    /// The run that climbed highest. Only meaningful if `runs` isn't zero.
+    pub best_run: RunStats,
+    /// Only meaningful if `runs` isn't zero.
+    pub last_run: RunStats,
+}
+
+impl Profile {
+    pub fn record_run(&mut self, run: RunStats) {
+        self.runs += 1;
+        self.rockets_fired += run.rockets_fired;
+        if run.victory {
+            if self.victories == 0 || run.seconds < self.fastest_summit_seconds {
+                self.fastest_summit_seconds = run.seconds;
+            }
+            self.victories += 1;
+        } else {
+            self.deaths += 1;
+        }
+        if run.max_height > self.best_height {
+            self.best_height = run.max_height;
+            self.best_run = run;
+        }
+        self.last_run = run;
+    }
+
+    /// The best and last runs as shown on the title and game over screens.
+    pub fn summary(&self) -> String {
+        if self.runs == 0 {
+            return String::new();
+        }
+        let mut summary = format!("Best: {}", describe_run(&self.best_run));
+        if self.victories > 0 {
+            summary += &format!(
+                "\nFastest summit: {}",
+                format_run_time(self.fastest_summit_seconds)
+            );
+        }
+        summary += &format!("\nLast: {}", describe_run(&self.last_run));
+        summary += &format!(
+            "\n{} runs, {} deaths, {} victories",
+            self.runs, self.deaths, self.victories
+        );
+        summary
+    }
+
+    pub fn save(&self) {
+        save_to_storage(PROFILE_PATH, &self.to_json());
+    }
+}
+
+/// Saves to a file, or to the browser's IndexedDB on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_to_storage(path: &str, contents: &str) {
+    if let Err(error) = std::fs::write(path, contents) {
+        println!("Could not save {}: {:?}", path, error);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_to_storage(path: &str, contents: &str) {
+    koi::kwasm::libraries::save_bytes_with_key(path, contents.as_bytes());
+}
+
+/// Loads the saved [Profile] and controls. On the web loading is asynchronous so it's polled each update.
+#[derive(NotCloneComponent)]
+pub struct ProfileLoader {
+    #[cfg(target_arch = "wasm32")]
+    loading: Option<ktasks::JoinHandle<'static, (Option<Vec<u8>>, Option<Vec<u8>>)>>,
+}
+
+impl ProfileLoader {
+    /// Spawns the [Profile], sets up the default controls, and starts loading the saved ones.
+    pub fn setup(world: &mut World) {
+        let mut input_actions = default_input_actions();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let profile = std::fs::read_to_string(PROFILE_PATH)
+            .ok()
+            .and_then(|json| Profile::from_json(&json))
+            .unwrap_or_default();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            match std::fs::read_to_string(CONTROLS_PATH) {
+                Ok(json) => apply_saved_controls(&mut input_actions, &json),
+                // Write the default bindings out so players can edit them.
+                Err(_) => save_controls(&input_actions),
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let profile = Profile::default();
+
+        world.spawn(profile);
+        *world.get_singleton::<InputActions>() = input_actions;
+        world.spawn(Self {
+            #[cfg(target_arch = "wasm32")]
+            loading: Some(ktasks::spawn_local(async {
+                (
+                    koi::kwasm::libraries::load_bytes_with_key(PROFILE_PATH).await,
+                    koi::kwasm::libraries::load_bytes_with_key(CONTROLS_PATH).await,
+                )
+            })),
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn update(&mut self, _profile: &mut Profile, _input_actions: &mut InputActions) {}
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn update(&mut self, profile: &mut Profile, input_actions: &mut InputActions) {
+        let (bytes, controls_bytes) = match self
+            .loading
+            .as_ref()
+            .and_then(|loading| loading.get_result())
+        {
+            Some(bytes) => bytes,
+            None => return,
+        };
+        self.loading = None;
+        match controls_bytes.and_then(|bytes| String::from_utf8(bytes).ok()) {
+            Some(json) => apply_saved_controls(input_actions, &json),
+            None => save_controls(input_actions),
+        }
+        if let Some(mut loaded) = bytes
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|json| Profile::from_json(&json))
+        {
+            // Keep the run that finished before loading did, if any.
+            if profile.runs > 0 {
+                let last_run = profile.last_run;
+                loaded.record_run(last_run);
+            }
+            *profile = loaded;
+        }
+    }
+}
+
+/// Records the run in the [Profile] once it ends in game over or victory.
+pub fn record_finished_run(game_state: &mut GameState, profile: &mut Profile) {
+    let finished = game_state.game_mode == GameMode::GameOver || game_state.victory;
+    if !finished || game_state.run_recorded {
+        return;
+    }
+    game_state.run_recorded = true;
+    profile.record_run(RunStats {
+        max_height: game_state.player_max_height,
+        seconds: game_state.run_seconds,
+        rockets_fired: game_state.rockets_fired,
+        victory: game_state.victory,
+    });
+    // Rockets fired before respawning at a checkpoint were counted when the player died.
+    profile.rockets_fired = profile
+        .rockets_fired
+        .saturating_sub(game_state.rockets_recorded);
+    game_state.rockets_recorded = game_state.rockets_fired;
+    profile.save();
+}
+
+/// Formats seconds as minutes and seconds for the UI.
+pub fn format_run_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn describe_run(run: &RunStats) -> String {
+    format!(
+        "{} m in {} with {} rockets{}",
+        run.max_height.floor() as i32,
+        format_run_time(run.seconds),
+        run.rockets_fired,
+        if run.victory { ", summited!" } else { "" }
+    )
+}
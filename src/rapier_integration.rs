@@ -1,13 +1,18 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
+    sync::Mutex,
 };
 
 use koi::*;
 use rapier3d::{
     math::Isometry,
     na::UnitQuaternion,
-    prelude::{ActiveEvents, QueryPipeline, SharedShape},
+    prelude::{
+        ActiveEvents, ColliderBuilder, ColliderHandle, ContactEvent, ContactPair, EventHandler,
+        InteractionGroups, IntersectionEvent, Point, QueryPipeline, RigidBodyActivation,
+        RigidBodyBuilder, RigidBodyHandle, SharedShape, Vector,
+    },
 };
 
 #[derive(Component, Clone)]
@@ -16,21 +21,36 @@ pub struct ToDespawn;
 #[derive(Component, Clone)]
 struct Controlled;
 
-#[derive(Clone)]
+#[derive(Clone, Reflect)]
 pub struct RigidBodyInner {
     pub kinematic: bool,
     pub velocity: Vec3,
+    /// Radians per second around each axis.
+    pub angular_velocity: Vec3,
+    /// Whether the body can rotate around the world x, y, and z axes.
     pub can_rotate: (bool, bool, bool),
     pub gravity_scale: f32,
     pub linear_damping: f32,
     pub angular_damping: f32,
+    /// Mass added on top of the mass from the colliders' densities.
+    /// This is only applied when the rigid body is created, so it isn't reflected.
+    #[reflect(skip)]
+    pub additional_mass: f32,
+    /// Continuous collision detection keeps fast bodies from passing through thin colliders.
+    pub ccd_enabled: bool,
+    pub can_sleep: bool,
+    /// Set this to put the body to sleep or wake it up.
+    /// Updated when Rapier puts the body to sleep or wakes it.
+    pub sleeping: bool,
 }
 #[derive(Component, Clone)]
 pub struct RigidBody {
     rigid_body_inner: RigidBodyInner,
     mutated: bool,
+    /// Set this after changing `velocity` or `angular_velocity`.
     pub mutated_velocity: bool,
     pub mutated_position: bool,
+    mass: f32,
 }
 
 impl RigidBody {
@@ -40,8 +60,14 @@ impl RigidBody {
             mutated: true,
             mutated_velocity: true,
             mutated_position: true,
+            mass: 0.0,
         }
     }
+
+    /// The total mass of the body and its colliders, as of the last physics step.
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
 }
 
 impl Deref for RigidBody {
@@ -57,19 +83,135 @@ impl DerefMut for RigidBody {
         &mut self.rigid_body_inner
     }
 }
+
+/// Reflects the [RigidBodyInner] fields.
+/// Setting a field marks the body as mutated so the change is applied to Rapier.
+impl Reflect for RigidBody {
+    fn type_name(&self) -> &'static str {
+        "RigidBody"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        self.rigid_body_inner.fields()
+    }
+
+    fn get_field(&self, name: &str) -> Option<ReflectValue> {
+        self.rigid_body_inner.get_field(name)
+    }
+
+    fn set_field(&mut self, name: &str, value: ReflectValue) -> Result<(), ReflectError> {
+        self.deref_mut().set_field(name, value)?;
+        if name == "velocity" || name == "angular_velocity" {
+            self.mutated_velocity = true;
+        }
+        Ok(())
+    }
+}
 impl Default for RigidBodyInner {
     fn default() -> Self {
         Self {
             kinematic: false,
             velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
             can_rotate: (true, true, true),
             gravity_scale: 1.0,
             linear_damping: 0.0,
             angular_damping: 0.0,
+            additional_mass: 0.0,
+            ccd_enabled: false,
+            can_sleep: true,
+            sleeping: false,
+        }
+    }
+}
+
+/// Which collision groups a collider is in and which groups it collides with.
+/// Colliders only interact if each is in a group the other's filter includes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+impl CollisionGroups {
+    pub const ALL: Self = Self {
+        memberships: u32::MAX,
+        filter: u32::MAX,
+    };
+    /// Collides with nothing and isn't found by queries.
+    pub const NONE: Self = Self {
+        memberships: 0,
+        filter: 0,
+    };
+
+    pub const fn new(memberships: u32, filter: u32) -> Self {
+        Self {
+            memberships,
+            filter,
+        }
+    }
+}
+
+impl From<CollisionGroups> for InteractionGroups {
+    fn from(groups: CollisionGroups) -> Self {
+        InteractionGroups::new(groups.memberships, groups.filter)
+    }
+}
+
+/// Surface and filtering properties for a `Collider`.
+/// Colliders without this component use the defaults.
+#[derive(Clone)]
+pub struct ColliderPropertiesInner {
+    /// Only applied when the collider is created.
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    /// Sensors detect overlaps but don't collide.
+    pub sensor: bool,
+    pub collision_groups: CollisionGroups,
+}
+
+impl Default for ColliderPropertiesInner {
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            sensor: false,
+            collision_groups: CollisionGroups::ALL,
         }
     }
 }
 
+#[derive(Component, Clone)]
+pub struct ColliderProperties {
+    inner: ColliderPropertiesInner,
+    mutated: bool,
+}
+
+impl ColliderProperties {
+    pub fn new(inner: ColliderPropertiesInner) -> Self {
+        Self {
+            inner,
+            mutated: true,
+        }
+    }
+}
+
+impl Deref for ColliderProperties {
+    type Target = ColliderPropertiesInner;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for ColliderProperties {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.mutated = true;
+        &mut self.inner
+    }
+}
+
 #[derive(Component, Clone)]
 pub enum Collider {
     Cuboid(Vec3),
@@ -82,12 +224,148 @@ pub enum Collider {
     AttachedMeshConvexDecomposition,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MeshColliderKind {
+    TriMesh,
+    ConvexHull,
+    ConvexDecomposition,
+}
+
+impl MeshColliderKind {
+    fn from_collider(collider: &Collider) -> Option<Self> {
+        match collider {
+            Collider::AttachedMesh => Some(Self::TriMesh),
+            Collider::AttachedMeshConvex => Some(Self::ConvexHull),
+            Collider::AttachedMeshConvexDecomposition => Some(Self::ConvexDecomposition),
+            Collider::Cuboid(_) | Collider::Sphere(_) => None,
+        }
+    }
+}
+
+/// Rapier doesn't support scaling mesh colliders so the scale is baked into the shape.
+/// Scales are quantized so tiny floating point differences still share a shape.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct MeshColliderKey {
+    pub mesh: Handle<Mesh>,
+    /// The mesh's `Assets::generation`, so a mesh that's edited in place gets a new shape.
+    pub generation: usize,
+    pub kind: MeshColliderKind,
+    pub scale: [i32; 3],
+}
+
+#[derive(Debug)]
+pub enum MeshColliderError {
+    /// The entity has a mesh collider but no `Handle<Mesh>`.
+    MissingMesh,
+    /// The mesh is still loading.
+    NotLoaded,
+    /// The mesh's `MeshData` was dropped after it was uploaded to the GPU.
+    MissingMeshData,
+    /// The mesh is flat or too small to have a convex hull.
+    InvalidConvexHull,
+}
+
+fn quantize_scale(scale: Vec3) -> [i32; 3] {
+    [
+        (scale.x * 1000.0).round() as i32,
+        (scale.y * 1000.0).round() as i32,
+        (scale.z * 1000.0).round() as i32,
+    ]
+}
+
+#[derive(Clone)]
+struct ColliderSource {
+    scale: [i32; 3],
+    mesh: Option<MeshColliderKey>,
+}
+
 #[derive(Component, Clone)]
 pub struct RapierRigidBody(pub rapier3d::prelude::RigidBodyHandle);
 
 #[derive(Component, Clone)]
 pub struct RapierCollider(pub rapier3d::prelude::ColliderHandle);
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionEventKind {
+    Started,
+    Stopped,
+}
+
+/// Two entities' colliders started or stopped touching during the last physics step.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub entity0: Entity,
+    pub entity1: Entity,
+    pub kind: CollisionEventKind,
+    /// True if one of the colliders is a sensor, so they overlapped instead of colliding.
+    pub intersection: bool,
+}
+
+impl CollisionEvent {
+    /// If `entity` is part of this event returns the other entity.
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        if self.entity0 == entity {
+            Some(self.entity1)
+        } else if self.entity1 == entity {
+            Some(self.entity0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Rapier reports events through a shared reference, so they're collected behind a lock
+/// and converted to `CollisionEvent`s after the step.
+#[derive(Default)]
+struct CollisionEventCollector {
+    events: Mutex<Vec<(ColliderHandle, ColliderHandle, CollisionEventKind, bool)>>,
+}
+
+impl EventHandler for CollisionEventCollector {
+    fn handle_intersection_event(&self, event: IntersectionEvent) {
+        let kind = if event.intersecting {
+            CollisionEventKind::Started
+        } else {
+            CollisionEventKind::Stopped
+        };
+        self.events
+            .lock()
+            .unwrap()
+            .push((event.collider1, event.collider2, kind, true));
+    }
+
+    fn handle_contact_event(&self, event: ContactEvent, _contact_pair: &ContactPair) {
+        let event = match event {
+            ContactEvent::Started(collider1, collider2) => {
+                (collider1, collider2, CollisionEventKind::Started, false)
+            }
+            ContactEvent::Stopped(collider1, collider2) => {
+                (collider1, collider2, CollisionEventKind::Stopped, false)
+            }
+        };
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+/// A cable that keeps a rigid body from getting farther than its length from a point on a collider.
+/// This isn't a Rapier joint. Before each step `RapierPhysicsManager` applies an impulse
+/// along the cable that cancels the body's velocity away from the anchor once the cable is taut.
+/// The rope goes slack when the body is closer than its length,
+/// and pulls on the anchor's rigid body too if it's dynamic.
+#[derive(Clone)]
+pub struct Rope(usize);
+
+struct RopeConstraint {
+    anchor: ColliderHandle,
+    /// The anchor point relative to the anchor collider.
+    local_anchor: Point<f32>,
+    body: RigidBodyHandle,
+    length: f32,
+}
+
+/// How much of a rope's stretch is pulled back in each step.
+const ROPE_CORRECTION: f32 = 0.2;
+
 #[derive(NotCloneComponent)]
 pub struct RapierPhysicsManager {
     pub gravity: Vec3,
@@ -100,9 +378,23 @@ pub struct RapierPhysicsManager {
     pub narrow_phase: rapier3d::prelude::NarrowPhase,
     pub joint_set: rapier3d::prelude::JointSet,
     pub ccd_solver: rapier3d::prelude::CCDSolver,
-    pub cached_mesh_colliders: HashMap<Handle<Mesh>, SharedShape>,
+    pub cached_mesh_colliders: HashMap<MeshColliderKey, SharedShape>,
+    /// How many colliders use each cached mesh shape.
+    mesh_collider_users: HashMap<MeshColliderKey, usize>,
+    /// What each collider was built from, so it can be rebuilt when that changes.
+    collider_sources: HashMap<Entity, ColliderSource>,
+    /// Entities whose collider couldn't be created. Used to only report each failure once.
+    reported_collider_errors: HashSet<Entity>,
     pub query_pipeline: QueryPipeline,
     user_data_to_entity: Vec<Entity>,
+    entity_to_collider: HashMap<Entity, ColliderHandle>,
+    event_collector: CollisionEventCollector,
+    /// Collisions that started or stopped during the last step.
+    collision_events: Vec<CollisionEvent>,
+    /// Collisions stopped by removing a collider, reported with the next step's events.
+    removed_collision_events: Vec<CollisionEvent>,
+    ropes: HashMap<usize, RopeConstraint>,
+    next_rope: usize,
 }
 
 impl RapierPhysicsManager {
@@ -119,19 +411,149 @@ impl RapierPhysicsManager {
             joint_set: rapier3d::prelude::JointSet::new(),
             ccd_solver: rapier3d::prelude::CCDSolver::new(),
             cached_mesh_colliders: HashMap::new(),
+            mesh_collider_users: HashMap::new(),
+            collider_sources: HashMap::new(),
+            reported_collider_errors: HashSet::new(),
             query_pipeline: QueryPipeline::new(),
             user_data_to_entity: Vec::new(),
+            entity_to_collider: HashMap::new(),
+            event_collector: CollisionEventCollector::default(),
+            collision_events: Vec::new(),
+            removed_collision_events: Vec::new(),
+            ropes: HashMap::new(),
+            next_rope: 0,
+        }
+    }
+
+    /// Returns the entity a collider was created for.
+    pub fn entity_for_collider(&self, collider: ColliderHandle) -> Option<Entity> {
+        let user_data = self.collider_set.get(collider)?.user_data;
+        self.user_data_to_entity.get(user_data as usize).copied()
+    }
+
+    pub fn collider_for_entity(&self, entity: Entity) -> Option<ColliderHandle> {
+        self.entity_to_collider.get(&entity).copied()
+    }
+
+    /// Collisions that started or stopped during the last step.
+    pub fn collision_events(&self) -> &[CollisionEvent] {
+        &self.collision_events
+    }
+
+    /// Collisions involving `entity` during the last step, paired with the other entity.
+    pub fn collision_events_with(
+        &self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (Entity, CollisionEventKind)> + '_ {
+        self.collision_events
+            .iter()
+            .filter_map(move |event| Some((event.other(entity)?, event.kind)))
+    }
+
+    /// Rapier doesn't report collisions that stop because a collider was removed,
+    /// so this queues a `Stopped` event for everything `collider` is touching.
+    /// Call it before removing the collider.
+    fn stop_collisions(&mut self, collider: ColliderHandle) {
+        let mut stopped = Vec::new();
+        if let Some(contacts) = self.narrow_phase.contacts_with(collider) {
+            stopped.extend(
+                contacts
+                    .filter(|(_, _, contact_pair)| contact_pair.has_any_active_contact)
+                    .map(|(collider0, collider1, _)| (collider0, collider1, false)),
+            );
+        }
+        if let Some(intersections) = self.narrow_phase.intersections_with(collider) {
+            stopped.extend(
+                intersections
+                    .filter(|(_, _, intersecting)| *intersecting)
+                    .map(|(collider0, collider1, _)| (collider0, collider1, true)),
+            );
+        }
+        for (collider0, collider1, intersection) in stopped {
+            if let (Some(entity0), Some(entity1)) = (
+                self.entity_for_collider(collider0),
+                self.entity_for_collider(collider1),
+            ) {
+                self.removed_collision_events.push(CollisionEvent {
+                    entity0,
+                    entity1,
+                    kind: CollisionEventKind::Stopped,
+                    intersection,
+                });
+            }
+        }
+    }
+
+    /// Like `stop_collisions` for every collider attached to `body`.
+    fn stop_body_collisions(&mut self, body: RigidBodyHandle) {
+        let colliders = match self.rigid_body_set.get(body) {
+            Some(body) => body.colliders().to_vec(),
+            None => return,
+        };
+        for collider in colliders {
+            self.stop_collisions(collider);
+        }
+    }
+
+    fn forget_collider(&mut self, entity: Entity, collider: ColliderHandle) {
+        if self.entity_to_collider.get(&entity) == Some(&collider) {
+            self.entity_to_collider.remove(&entity);
         }
     }
 
     pub fn fixed_update(world: &mut World) {
+        (Self::remove_despawned_colliders).run(world);
         (Self::add_rapier_rigid_bodies).run(world);
         apply_commands(world);
+        (Self::remove_outdated_colliders).run(world);
+        apply_commands(world);
         (Self::add_rapier_colliders).run(world);
         apply_commands(world);
         (Self::step).run(world);
     }
 
+    fn set_rotation_locks(
+        rigid_body: &mut rapier3d::prelude::RigidBody,
+        (x, y, z): (bool, bool, bool),
+    ) {
+        // This doesn't work in the Rapier on crates.io. It was fixed in September.
+        /*
+        rigid_body.restrict_rotations(x, y, z, false);
+        */
+        rigid_body.lock_rotations(!x && !y && !z, false);
+    }
+
+    /// Until `restrict_rotations` works, individually locked axes are enforced
+    /// by clearing their angular velocity before and after each step.
+    fn clear_locked_angular_velocity(
+        rigid_body: &mut rapier3d::prelude::RigidBody,
+        (x, y, z): (bool, bool, bool),
+    ) {
+        let angvel = *rigid_body.angvel();
+        let locked_angvel = rapier3d::prelude::nalgebra::Vector3::new(
+            if x { angvel.x } else { 0.0 },
+            if y { angvel.y } else { 0.0 },
+            if z { angvel.z } else { 0.0 },
+        );
+        if locked_angvel != angvel {
+            rigid_body.set_angvel(locked_angvel, false);
+        }
+    }
+
+    /// The groups a collider is in, for queries that should only find what it could hit.
+    pub fn collision_groups(&self, collider: ColliderHandle) -> InteractionGroups {
+        self.collider_set
+            .get(collider)
+            .map(|collider| collider.collision_groups())
+            .unwrap_or_else(InteractionGroups::all)
+    }
+
+    pub fn is_sensor(&self, collider: ColliderHandle) -> bool {
+        self.collider_set
+            .get(collider)
+            .map_or(false, |collider| collider.is_sensor())
+    }
+
     pub fn add_rapier_rigid_bodies(
         &mut self,
         commands: &mut Commands,
@@ -142,31 +564,27 @@ impl RapierPhysicsManager {
             let position = transform.position;
             let position: [f32; 3] = position.into();
 
-            let mut new_rigid_body = if rigid_body.kinematic {
-                rapier3d::prelude::RigidBodyBuilder::new_kinematic_position_based()
-                    .translation(position.into())
-                    .build()
+            let velocity: [f32; 3] = rigid_body.velocity.into();
+            let angular_velocity: [f32; 3] = rigid_body.angular_velocity.into();
+
+            let builder = if rigid_body.kinematic {
+                RigidBodyBuilder::new_kinematic_position_based()
             } else {
-                rapier3d::prelude::RigidBodyBuilder::new_dynamic()
-                    .translation(position.into())
-                    .build()
+                RigidBodyBuilder::new_dynamic()
             };
+            let mut new_rigid_body = builder
+                .translation(position.into())
+                .linvel(velocity.into())
+                .angvel(angular_velocity.into())
+                .additional_mass(rigid_body.additional_mass)
+                .ccd_enabled(rigid_body.ccd_enabled)
+                .can_sleep(rigid_body.can_sleep)
+                .sleeping(rigid_body.sleeping)
+                .build();
 
-            // This doesn't work in the Rapier on crates.io. It was fixed in September.
-            /*
-            new_rigid_body.restrict_rotations(
-                rigid_body.can_rotate.0,
-                rigid_body.can_rotate.1,
-                rigid_body.can_rotate.2,
-                false,
-            );
-            */
             new_rigid_body.set_linear_damping(rigid_body.linear_damping);
             new_rigid_body.set_angular_damping(rigid_body.angular_damping);
-
-            if !rigid_body.can_rotate.0 || !rigid_body.can_rotate.1 || !rigid_body.can_rotate.2 {
-                new_rigid_body.lock_rotations(true, false);
-            }
+            Self::set_rotation_locks(&mut new_rigid_body, rigid_body.can_rotate);
 
             new_rigid_body.user_data = self.user_data_to_entity.len() as u128;
             self.user_data_to_entity.push(*entity);
@@ -188,12 +606,13 @@ impl RapierPhysicsManager {
                 &Collider,
                 Option<&RapierRigidBody>,
                 Option<&Handle<Mesh>>,
+                Option<&ColliderProperties>,
             ),
             (Without<RapierCollider>, With<Transform>),
         >,
     ) {
         // Add colliders to entities that need them.
-        for (entity, (transform, collider, rapier_rigid_body, mesh_handle)) in
+        for (entity, (transform, collider, rapier_rigid_body, mesh_handle, properties)) in
             needs_collider_query.entities_and_components()
         {
             // This is a standalone collider without a position.
@@ -203,87 +622,52 @@ impl RapierPhysicsManager {
                 Isometry::from_parts(p.into(), UnitQuaternion::from_quaternion(rotation.into()));
 
             let scale = transform.scale;
-            let mut collider = match collider {
-                Collider::Cuboid(extents) => rapier3d::prelude::ColliderBuilder::cuboid(
+            let mut mesh_key = None;
+            let collider_builder = match collider {
+                Collider::Cuboid(extents) => ColliderBuilder::cuboid(
                     extents.x * scale.x,
                     extents.y * scale.y,
                     extents.z * scale.z,
-                )
-                .build(),
-                Collider::Sphere(radius) => {
-                    rapier3d::prelude::ColliderBuilder::ball(*radius * scale.x).build()
-                }
-                Collider::AttachedMeshConvex => {
-                    let mesh_handle = mesh_handle.unwrap();
-                    let entry = self.cached_mesh_colliders.entry(mesh_handle.clone());
-                    let shared_shape = entry.or_insert_with(|| {
-                        let mesh = meshes.get(mesh_handle);
-                        let mesh_data = mesh.mesh_data.as_ref().unwrap();
-
-                        // Rapier does not support scaling mesh colliders, so create a new one each time.
-                        let mut vertex_positions = Vec::new();
-                        for vertex in mesh_data.positions.iter() {
-                            let p = transform.scale.mul_by_component(*vertex);
-                            let p: [f32; 3] = p.into();
-                            vertex_positions.push(p.into());
+                ),
+                Collider::Sphere(radius) => ColliderBuilder::ball(*radius * scale.x),
+                Collider::AttachedMesh
+                | Collider::AttachedMeshConvex
+                | Collider::AttachedMeshConvexDecomposition => {
+                    let kind = MeshColliderKind::from_collider(collider).unwrap();
+                    match self.mesh_collider_shape(meshes, mesh_handle, kind, scale) {
+                        Ok((shape, key)) => {
+                            mesh_key = Some(key);
+                            let builder = ColliderBuilder::new(shape);
+                            if kind == MeshColliderKind::TriMesh {
+                                builder.position(position_isometry)
+                            } else {
+                                builder
+                            }
                         }
-                        SharedShape::convex_hull(
-                            // This is safe as long as rapier3d is in f32 mode.
-                            &vertex_positions,
-                        )
-                        .unwrap()
-                    });
-                    rapier3d::prelude::ColliderBuilder::new(shared_shape.clone()).build()
-                }
-                Collider::AttachedMesh => {
-                    let mesh_handle = mesh_handle.unwrap();
-                    let entry = self.cached_mesh_colliders.entry(mesh_handle.clone());
-                    let shared_shape = entry.or_insert_with(|| {
-                        let mesh = meshes.get(mesh_handle);
-                        let mesh_data = mesh.mesh_data.as_ref().unwrap();
-
-                        // Rapier does not support scaling mesh colliders, so create a new one each time.
-                        let mut vertex_positions = Vec::new();
-                        for vertex in mesh_data.positions.iter() {
-                            let p = transform.scale.mul_by_component(*vertex);
-                            let p: [f32; 3] = p.into();
-                            vertex_positions.push(p.into());
+                        // Try again once the mesh has loaded.
+                        Err(MeshColliderError::NotLoaded) => continue,
+                        Err(error) => {
+                            if self.reported_collider_errors.insert(*entity) {
+                                println!(
+                                    "Could not create a collider for {:?}: {:?}",
+                                    entity, error
+                                );
+                            }
+                            continue;
                         }
-                        SharedShape::trimesh(
-                            // This is safe as long as rapier3d is in f32 mode.
-                            vertex_positions,
-                            mesh_data.indices.clone(),
-                        )
-                    });
-                    rapier3d::prelude::ColliderBuilder::new(shared_shape.clone())
-                        .position(position_isometry)
-                        .build()
-                }
-                Collider::AttachedMeshConvexDecomposition => {
-                    let mesh_handle = mesh_handle.unwrap();
-                    let entry = self.cached_mesh_colliders.entry(mesh_handle.clone());
-                    let shared_shape = entry.or_insert_with(|| {
-                        let mesh = meshes.get(mesh_handle);
-                        let mesh_data = mesh.mesh_data.as_ref().unwrap();
-
-                        // Rapier does not support scaling mesh colliders, so create a new one each time.
-                        let mut vertex_positions = Vec::new();
-                        for vertex in mesh_data.positions.iter() {
-                            let p = transform.scale.mul_by_component(*vertex);
-                            let p: [f32; 3] = p.into();
-                            vertex_positions.push(p.into());
-                        }
-
-                        SharedShape::convex_decomposition(
-                            // This is safe as long as rapier3d is in f32 mode.
-                            &vertex_positions,
-                            &mesh_data.indices.clone(),
-                        )
-                    });
-                    rapier3d::prelude::ColliderBuilder::new(shared_shape.clone()).build()
+                    }
                 }
             };
-            collider.set_active_events(ActiveEvents::CONTACT_EVENTS);
+            let properties = properties.map(|p| (**p).clone()).unwrap_or_default();
+            let mut collider = collider_builder
+                .density(properties.density)
+                .friction(properties.friction)
+                .restitution(properties.restitution)
+                .sensor(properties.sensor)
+                .collision_groups(properties.collision_groups.into())
+                .solver_groups(properties.collision_groups.into())
+                .active_events(ActiveEvents::CONTACT_EVENTS | ActiveEvents::INTERSECTION_EVENTS)
+                .build();
             collider.user_data = self.user_data_to_entity.len() as u128;
             self.user_data_to_entity.push(*entity);
 
@@ -305,10 +689,301 @@ impl RapierPhysicsManager {
                 self.collider_set.insert(collider)
             };
 
+            self.entity_to_collider.insert(*entity, collider_handle);
+            self.reported_collider_errors.remove(entity);
+            if let Some(key) = &mesh_key {
+                *self.mesh_collider_users.entry(key.clone()).or_insert(0) += 1;
+            }
+            self.release_collider_source(*entity);
+            self.collider_sources.insert(
+                *entity,
+                ColliderSource {
+                    scale: quantize_scale(scale),
+                    mesh: mesh_key,
+                },
+            );
             commands.add_component(*entity, RapierCollider(collider_handle))
         }
     }
 
+    /// Returns a shape for a mesh at a scale, creating it if no other collider uses it yet.
+    fn mesh_collider_shape(
+        &mut self,
+        meshes: &Assets<Mesh>,
+        mesh_handle: Option<&Handle<Mesh>>,
+        kind: MeshColliderKind,
+        scale: Vec3,
+    ) -> Result<(SharedShape, MeshColliderKey), MeshColliderError> {
+        let mesh_handle = mesh_handle.ok_or(MeshColliderError::MissingMesh)?;
+        let mesh = meshes
+            .get_if_loaded(mesh_handle)
+            .ok_or(MeshColliderError::NotLoaded)?;
+        let mesh_data = mesh
+            .mesh_data
+            .as_ref()
+            .ok_or(MeshColliderError::MissingMeshData)?;
+
+        let key = MeshColliderKey {
+            mesh: mesh_handle.clone(),
+            generation: meshes.generation(mesh_handle),
+            kind,
+            scale: quantize_scale(scale),
+        };
+        if let Some(shape) = self.cached_mesh_colliders.get(&key) {
+            return Ok((shape.clone(), key));
+        }
+
+        // Use the quantized scale so the shape doesn't depend on which entity created it.
+        let scale = Vec3::new(
+            key.scale[0] as f32,
+            key.scale[1] as f32,
+            key.scale[2] as f32,
+        ) / 1000.0;
+        let vertex_positions: Vec<Point<f32>> = mesh_data
+            .positions
+            .iter()
+            .map(|vertex| {
+                let p: [f32; 3] = scale.mul_by_component(*vertex).into();
+                p.into()
+            })
+            .collect();
+
+        // This is safe as long as rapier3d is in f32 mode.
+        let shape = match kind {
+            MeshColliderKind::TriMesh => {
+                SharedShape::trimesh(vertex_positions, mesh_data.indices.clone())
+            }
+            MeshColliderKind::ConvexHull => SharedShape::convex_hull(&vertex_positions)
+                .ok_or(MeshColliderError::InvalidConvexHull)?,
+            MeshColliderKind::ConvexDecomposition => {
+                SharedShape::convex_decomposition(&vertex_positions, &mesh_data.indices)
+            }
+        };
+        self.cached_mesh_colliders
+            .insert(key.clone(), shape.clone());
+        Ok((shape, key))
+    }
+
+    /// Removes colliders whose scale or mesh changed so `add_rapier_colliders` rebuilds them.
+    pub fn remove_outdated_colliders(
+        &mut self,
+        meshes: &Assets<Mesh>,
+        commands: &mut Commands,
+        collider_query: Query<(
+            &GlobalTransform,
+            &Collider,
+            &RapierCollider,
+            Option<&Handle<Mesh>>,
+        )>,
+    ) {
+        for (entity, (transform, collider, rapier_collider, mesh_handle)) in
+            collider_query.entities_and_components()
+        {
+            let source = match self.collider_sources.get(entity) {
+                Some(source) => source,
+                None => continue,
+            };
+
+            let mesh_changed = match (MeshColliderKind::from_collider(collider), &source.mesh) {
+                (None, None) => false,
+                (Some(kind), Some(key)) => match mesh_handle {
+                    Some(mesh_handle) => {
+                        key.mesh != *mesh_handle
+                            || key.kind != kind
+                            || key.generation != meshes.generation(mesh_handle)
+                    }
+                    None => true,
+                },
+                // The collider's kind changed.
+                _ => true,
+            };
+
+            if mesh_changed || source.scale != quantize_scale(transform.scale) {
+                self.remove_collider(*entity, rapier_collider);
+                commands.remove_component::<RapierCollider>(*entity);
+            }
+        }
+    }
+
+    /// Removes the Rapier colliders of entities that lost their [RapierCollider]
+    /// without going through [RapierPhysicsManager::remove_collider], like entities despawned directly.
+    pub fn remove_despawned_colliders(
+        &mut self,
+        removed: RemovedComponents<RapierCollider>,
+        colliders: Query<&RapierCollider>,
+    ) {
+        for entity in &removed {
+            // The entity may have been given a new collider since.
+            if colliders.get_entity_components(*entity).is_some() {
+                continue;
+            }
+            if let Some(collider) = self.collider_for_entity(*entity) {
+                self.remove_collider(*entity, &RapierCollider(collider));
+            }
+        }
+    }
+
+    /// Forgets what a collider was built from.
+    /// Cached mesh shapes are dropped once no other collider uses them.
+    fn release_collider_source(&mut self, entity: Entity) {
+        if let Some(ColliderSource {
+            mesh: Some(key), ..
+        }) = self.collider_sources.remove(&entity)
+        {
+            if let Some(users) = self.mesh_collider_users.get_mut(&key) {
+                *users -= 1;
+                if *users == 0 {
+                    self.mesh_collider_users.remove(&key);
+                    self.cached_mesh_colliders.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Attaches `body` to `point` on `collider` with a rope as long as the distance between them.
+    /// The rope is enforced by `apply_rope_constraints` rather than a Rapier joint.
+    /// Returns `None` if the body or collider has been removed.
+    pub fn attach_rope(
+        &mut self,
+        body: RigidBodyHandle,
+        collider: ColliderHandle,
+        point: Vec3,
+    ) -> Option<Rope> {
+        let point: [f32; 3] = point.into();
+        let point: Point<f32> = point.into();
+        let body_position = self.rigid_body_set.get(body)?.position().translation.vector;
+        let local_anchor = self
+            .collider_set
+            .get(collider)?
+            .position()
+            .inverse_transform_point(&point);
+
+        let rope = self.next_rope;
+        self.next_rope += 1;
+        self.ropes.insert(
+            rope,
+            RopeConstraint {
+                anchor: collider,
+                local_anchor,
+                body,
+                length: (body_position - point.coords).norm(),
+            },
+        );
+        Some(Rope(rope))
+    }
+
+    pub fn remove_rope(&mut self, rope: &Rope) {
+        self.ropes.remove(&rope.0);
+    }
+
+    pub fn rope_length(&self, rope: &Rope) -> f32 {
+        self.ropes.get(&rope.0).map_or(0.0, |rope| rope.length)
+    }
+
+    /// Changes how far the rope lets its body get from the anchor.
+    pub fn set_rope_length(&mut self, rope: &Rope, length: f32) {
+        if let Some(rope) = self.ropes.get_mut(&rope.0) {
+            rope.length = length;
+            if let Some(body) = self.rigid_body_set.get_mut(rope.body) {
+                body.wake_up(true);
+            }
+        }
+    }
+
+    /// Returns the world positions of the rope's ends, starting with the anchor.
+    /// Returns `None` if the anchor's collider or the body has been removed.
+    pub fn rope_anchors(&self, rope: &Rope) -> Option<(Vec3, Vec3)> {
+        let rope = self.ropes.get(&rope.0)?;
+        let anchor = self.collider_set.get(rope.anchor)?.position() * rope.local_anchor;
+        let end = self
+            .rigid_body_set
+            .get(rope.body)?
+            .position()
+            .translation
+            .vector;
+        let anchor: [f32; 3] = anchor.coords.into();
+        let end: [f32; 3] = end.into();
+        Some((anchor.into(), end.into()))
+    }
+
+    /// A hand-written velocity constraint for each rope, solved once before Rapier's step.
+    /// Applies an impulse that removes the velocity that would stretch the rope past its length,
+    /// and a bit more to pull back in any stretch that has built up.
+    /// The impulse is split with the anchor's rigid body by inverse mass if it's dynamic.
+    fn apply_rope_constraints(&mut self) {
+        fn inverse_mass(body: &rapier3d::prelude::RigidBody) -> f32 {
+            if body.is_dynamic() && body.mass() > 0.0 {
+                1.0 / body.mass()
+            } else {
+                0.0
+            }
+        }
+
+        let dt = self.integration_parameters.dt;
+        for rope in self.ropes.values() {
+            let anchor_collider = match self.collider_set.get(rope.anchor) {
+                Some(collider) => collider,
+                None => continue,
+            };
+            let anchor = anchor_collider.position() * rope.local_anchor;
+            let anchor_body = anchor_collider.parent();
+            let (anchor_velocity, anchor_inverse_mass) =
+                match anchor_body.and_then(|body| self.rigid_body_set.get(body)) {
+                    Some(body) => (*body.linvel(), inverse_mass(body)),
+                    None => (Vector::zeros(), 0.0),
+                };
+            let (body_position, body_velocity, body_inverse_mass) =
+                match self.rigid_body_set.get(rope.body) {
+                    Some(body) => (
+                        body.position().translation.vector,
+                        *body.linvel(),
+                        inverse_mass(body),
+                    ),
+                    None => continue,
+                };
+
+            let offset = body_position - anchor.coords;
+            let distance = offset.norm();
+            let inverse_mass = body_inverse_mass + anchor_inverse_mass;
+            if distance <= rope.length || inverse_mass == 0.0 {
+                continue;
+            }
+            let direction = offset / distance;
+
+            // Negative, so a stretched rope pulls the body back in.
+            let allowed_speed = -(distance - rope.length) * ROPE_CORRECTION / dt;
+            let speed = (body_velocity - anchor_velocity).dot(&direction);
+            if speed <= allowed_speed {
+                continue;
+            }
+            let impulse = direction * ((speed - allowed_speed) / inverse_mass);
+
+            if let Some(body) = self.rigid_body_set.get_mut(rope.body) {
+                body.apply_impulse(-impulse, true);
+            }
+            if let Some(body) = anchor_body.and_then(|body| self.rigid_body_set.get_mut(body)) {
+                if body.is_dynamic() {
+                    body.apply_impulse_at_point(impulse, anchor, true);
+                }
+            }
+        }
+    }
+
+    /// Removes an entity's collider from Rapier.
+    /// The cached shape for its mesh is dropped if nothing else uses it.
+    pub fn remove_collider(&mut self, entity: Entity, collider: &RapierCollider) {
+        self.forget_collider(entity, collider.0);
+        self.release_collider_source(entity);
+        self.reported_collider_errors.remove(&entity);
+        self.stop_collisions(collider.0);
+        self.collider_set.remove(
+            collider.0,
+            &mut self.island_manager,
+            &mut self.rigid_body_set,
+            true,
+        );
+    }
+
     pub fn despawn(
         &mut self,
         commands: &mut Commands,
@@ -319,6 +994,7 @@ impl RapierPhysicsManager {
     ) {
         for (entity, (body, collider)) in rigid_body_query.entities_and_components() {
             if let Some(body) = body {
+                self.stop_body_collisions(body.0);
                 self.rigid_body_set.remove(
                     body.0,
                     &mut self.island_manager,
@@ -327,13 +1003,9 @@ impl RapierPhysicsManager {
                 );
             }
             if let Some(collider) = collider {
-                self.collider_set.remove(
-                    collider.0,
-                    &mut self.island_manager,
-                    &mut self.rigid_body_set,
-                    false,
-                );
+                self.remove_collider(*entity, collider);
             }
+            self.reported_collider_errors.remove(entity);
             commands.despawn(*entity)
         }
     }
@@ -341,12 +1013,44 @@ impl RapierPhysicsManager {
     pub fn step(
         &mut self,
         mut rigid_body_query: Query<(&mut Transform, &RapierRigidBody, &mut RigidBody)>,
+        mut collider_properties_query: Query<(&RapierCollider, &mut ColliderProperties)>,
+        standalone_colliders: Query<(&GlobalTransform, &RapierCollider), Without<RapierRigidBody>>,
     ) {
+        for (collider, properties) in collider_properties_query.iter_mut() {
+            if !properties.mutated {
+                continue;
+            }
+            if let Some(collider) = self.collider_set.get_mut(collider.0) {
+                collider.set_friction(properties.friction);
+                collider.set_restitution(properties.restitution);
+                collider.set_sensor(properties.sensor);
+                collider.set_collision_groups(properties.collision_groups.into());
+                collider.set_solver_groups(properties.collision_groups.into());
+            }
+            properties.mutated = false;
+        }
+
+        // Colliders without rigid bodies follow their entity's transform.
+        for (transform, collider) in standalone_colliders.iter() {
+            if let Some(collider) = self.collider_set.get_mut(collider.0) {
+                let p: [f32; 3] = transform.position.into();
+                let rotation: [f32; 4] = transform.rotation.into();
+                let position = Isometry::from_parts(
+                    p.into(),
+                    UnitQuaternion::from_quaternion(rotation.into()),
+                );
+                if *collider.position() != position {
+                    collider.set_position(position);
+                }
+            }
+        }
+
         // Update the transform of rigid bodies that have moved.
         for (transform, rigid_body, rigid_body_koi) in rigid_body_query.iter_mut() {
             if self.rigid_body_set.contains(rigid_body.0) {
                 if transform.position.length() > 100_000.0 {
                     println!("DESPAWNING RAPIER RIGID BODY");
+                    self.stop_body_collisions(rigid_body.0);
                     self.rigid_body_set.remove(
                         rigid_body.0,
                         &mut self.island_manager,
@@ -358,14 +1062,23 @@ impl RapierPhysicsManager {
 
                 let velocity = rigid_body_koi.velocity;
                 let velocity: [f32; 3] = velocity.into();
+                let angular_velocity: [f32; 3] = rigid_body_koi.angular_velocity.into();
 
                 let position = transform.position;
                 let position: [f32; 3] = position.into();
 
                 let rigid_body_ref = self.rigid_body_set.get_mut(rigid_body.0).unwrap();
 
+                if rigid_body_koi.mutated {
+                    if rigid_body_koi.sleeping && !rigid_body_ref.is_sleeping() {
+                        rigid_body_ref.sleep();
+                    } else if !rigid_body_koi.sleeping && rigid_body_ref.is_sleeping() {
+                        rigid_body_ref.wake_up(true);
+                    }
+                }
                 if rigid_body_koi.mutated_velocity {
                     rigid_body_ref.set_linvel(velocity.into(), true);
+                    rigid_body_ref.set_angvel(angular_velocity.into(), true);
                 }
                 if rigid_body_koi.mutated_position {
                     let [x, y, z, w] = transform.rotation.as_array();
@@ -389,12 +1102,25 @@ impl RapierPhysicsManager {
                     // to_angle_axis might not work correctly.
                     // it definitely fails for the identity rotation.
                     rigid_body_ref.set_gravity_scale(rigid_body_koi.gravity_scale, true);
+                    rigid_body_ref.set_linear_damping(rigid_body_koi.linear_damping);
+                    rigid_body_ref.set_angular_damping(rigid_body_koi.angular_damping);
+                    rigid_body_ref.enable_ccd(rigid_body_koi.ccd_enabled);
+                    rigid_body_ref.activation_mut().threshold = if rigid_body_koi.can_sleep {
+                        RigidBodyActivation::default_threshold()
+                    } else {
+                        -1.0
+                    };
+                    Self::set_rotation_locks(rigid_body_ref, rigid_body_koi.can_rotate);
                 }
+                Self::clear_locked_angular_velocity(rigid_body_ref, rigid_body_koi.can_rotate);
             }
         }
 
+        self.apply_rope_constraints();
+
         let gravity: [f32; 3] = self.gravity.into();
         let gravity = gravity.into();
+        self.collision_events = std::mem::take(&mut self.removed_collision_events);
         self.physics_pipeline.step(
             &gravity,
             &self.integration_parameters,
@@ -406,15 +1132,36 @@ impl RapierPhysicsManager {
             &mut self.joint_set,
             &mut self.ccd_solver,
             &(),
-            &(),
+            &self.event_collector,
         );
 
+        let events = std::mem::take(&mut *self.event_collector.events.lock().unwrap());
+        for (collider0, collider1, kind, intersection) in events {
+            // Colliders that were removed can no longer be mapped back to their entities.
+            if let (Some(entity0), Some(entity1)) = (
+                self.entity_for_collider(collider0),
+                self.entity_for_collider(collider1),
+            ) {
+                self.collision_events.push(CollisionEvent {
+                    entity0,
+                    entity1,
+                    kind,
+                    intersection,
+                });
+            }
+        }
+
         for (transform, rigid_body, r) in rigid_body_query.iter_mut() {
             // Don't update the position of kinematic rigid bodies.
             if r.kinematic {
                 continue;
             }
 
+            if r.can_rotate != (true, true, true) {
+                if let Some(rigid_body_ref) = self.rigid_body_set.get_mut(rigid_body.0) {
+                    Self::clear_locked_angular_velocity(rigid_body_ref, r.can_rotate);
+                }
+            }
             if let Some(rigid_body_ref) = self.rigid_body_set.get(rigid_body.0) {
                 let current_position: [f32; 3] = rigid_body_ref
                     .position()
@@ -432,7 +1179,11 @@ impl RapierPhysicsManager {
                     );
                 }
                 let linvel: [f32; 3] = (*rigid_body_ref.linvel()).into();
+                let angvel: [f32; 3] = (*rigid_body_ref.angvel()).into();
                 r.velocity = linvel.into();
+                r.angular_velocity = angvel.into();
+                r.sleeping = rigid_body_ref.is_sleeping();
+                r.mass = rigid_body_ref.mass();
                 r.mutated = false;
                 r.mutated_velocity = false;
                 r.mutated_position = false;
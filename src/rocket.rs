@@ -2,12 +2,24 @@ use rapier3d::math::Isometry;
 
 use crate::*;
 
+const ROCKET_SPEED: f32 = 120.0;
+/// Rockets that don't hit anything are despawned after this many seconds.
+const ROCKET_LIFETIME_SECONDS: f32 = 5.0;
+/// How quickly homing rockets turn towards the worm's mouth, in radians per second.
+const ROCKET_HOMING_TURN_SPEED: f32 = 1.5;
+/// The minimum time between rockets, in seconds.
+pub const ROCKET_COOLDOWN_SECONDS: f32 = 0.4;
+
 #[derive(Component, Clone)]
 pub struct Rocket {
     velocity: Vec3,
+    /// Steer towards the worm's mouth.
+    homing: bool,
+    /// Counted down each fixed update. The rocket is despawned when it runs out.
+    lifetime_seconds: f32,
 }
 
-pub fn spawn_rocket(commands: &mut Commands, start: Vec3, direction: Vec3) {
+pub fn spawn_rocket(commands: &mut Commands, start: Vec3, direction: Vec3, homing: bool) {
     commands.spawn((
         Mesh::SPHERE,
         // RigidBody::new(RigidBodyInner {
@@ -20,47 +32,64 @@ pub fn spawn_rocket(commands: &mut Commands, start: Vec3, direction: Vec3) {
         Transform::new()
             .with_position(start + direction * 2.0)
             .with_scale(Vec3::fill(0.2)),
-        Collider::Sphere(0.5),
         Rocket {
-            velocity: direction * 120.0,
+            velocity: direction * ROCKET_SPEED,
+            homing,
+            lifetime_seconds: ROCKET_LIFETIME_SECONDS,
         },
     ))
 }
 
+/// Turns `velocity` towards `target` by at most `max_angle` radians, keeping its speed.
+/// `velocity` is returned unchanged if it or `target` is too short to have a direction.
+fn steer_towards(velocity: Vec3, target: Vec3, max_angle: f32) -> Vec3 {
+    let speed = velocity.length();
+    if speed < 0.0001 || target.length() < 0.0001 {
+        return velocity;
+    }
+    let current = velocity / speed;
+    let desired = target.normalized();
+    let angle = current.dot(desired).clamp(-1.0, 1.0).acos();
+    if angle <= max_angle {
+        return desired * speed;
+    }
+    let t = max_angle / angle;
+    (current + (desired - current) * t).normalized() * speed
+}
+
+/// Moves rockets and explodes them when they hit the terrain or the worm.
+/// Rockets that run out of lifetime are despawned without exploding.
+/// Rockets hit the worm's body collider or are swallowed by flying into its mouth.
 pub fn check_rocket_collisions_system(
-    (worm_position, worm): (&Transform, &mut Worm),
+    worm: Query<(&Transform, &WormBehavior)>,
     commands: &mut Commands,
     rapier_physics: &RapierPhysicsManager,
     explosion_manager: &mut ExplosionManager,
-    mut rockets: Query<(&mut Transform, &Rocket, &RapierCollider)>,
-    sounds: &Assets<Sound>,
+    mut rockets: Query<(&mut Transform, &mut Rocket)>,
     time: &Time,
+    mut rocket_hits: EventWriter<RocketHit>,
 ) {
-    for (entity, (transform, rocket, rapier_collider)) in rockets.entities_and_components_mut() {
-        transform.position += rocket.velocity * time.fixed_time_step as f32;
-        // rigid_body.mutated_position = true;
+    let delta_seconds = time.fixed_time_step as f32;
+    let (worm_entity, (worm_transform, worm)) = match worm.entities_and_components().next() {
+        Some(worm) => worm,
+        None => return,
+    };
+    let worm_collider = rapier_physics.collider_for_entity(*worm_entity);
+    let mouth = worm_transform.position - Vec3::Y * worm.description.mouth_depth;
 
-        /*
-        for contact in rapier_physics.narrow_phase.contacts_with(rapier_collider.0) {
-            println!("COLLIDER: {:?}", rapier_collider.0);
-
-            println!("CONTACT: {:?}", (contact.collider1, contact.collider2));
-            commands.add_component(*entity, ToDespawn);
-            explosion_manager.new_explosion(transform.position, 5.0);
-            break;
+    for (entity, (transform, rocket)) in rockets.entities_and_components_mut() {
+        if rocket.homing {
+            rocket.velocity = steer_towards(
+                rocket.velocity,
+                mouth - transform.position,
+                ROCKET_HOMING_TURN_SPEED * delta_seconds,
+            );
         }
-        */
-        let worm_diff = transform.position.y - worm_position.position.y;
-        let on_worms_level = worm_diff < 0.0;
-        let hit_worm = worm_diff < -150.0
-            || (on_worms_level
-                && transform.position.xz().length_squared() > (250. * 250.)
-                && transform.position.xz().length_squared() < (270. * 270.));
+        transform.position += rocket.velocity * delta_seconds;
 
         let velocity: [f32; 3] = rocket.velocity.into();
-
         let v = velocity.into();
-        let hit_something = rapier_physics
+        let hit_collider = rapier_physics
             .query_pipeline
             .cast_shape(
                 &rapier_physics.collider_set,
@@ -73,17 +102,25 @@ pub fn check_rocket_collisions_system(
                 &rapier3d::prelude::Ball::new(transform.scale.x * 0.5),
                 0.1,
                 rapier3d::prelude::InteractionGroups::all(),
-                Some(&|c| c != rapier_collider.0),
+                // The worm's collider is a sensor so it doesn't push the player around.
+                Some(&|c| Some(c) == worm_collider || !rapier_physics.is_sensor(c)),
             )
-            .is_some()
-            || hit_worm;
+            .map(|(collider, _)| collider);
+
+        let swallowed = transform.position.y < mouth.y;
+        let hit_worm = swallowed || (hit_collider.is_some() && hit_collider == worm_collider);
 
         if hit_worm {
-            worm.rockets_hit += 1;
+            rocket_hits.send(RocketHit {
+                position: transform.position,
+            });
         }
-        if hit_something {
+        rocket.lifetime_seconds -= delta_seconds;
+        if hit_worm || hit_collider.is_some() {
+            commands.add_component(*entity, ToDespawn);
+            explosion_manager.new_explosion_with_crater(transform.position, 100.0, 15.0);
+        } else if rocket.lifetime_seconds <= 0.0 {
             commands.add_component(*entity, ToDespawn);
-            explosion_manager.new_explosion(transform.position, 100.0);
         }
     }
 }
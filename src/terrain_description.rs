@@ -0,0 +1,157 @@
+use crate::*;
+
+/// Describes the shape and colors of a tower.
+/// This can be loaded from JSON so new towers can be made without editing code.
+/// Colors are sRGB values from 0.0 to 1.0.
+#[derive(Clone, SerializeDeserialize)]
+pub struct TerrainDescription {
+    /// The width of the terrain in meters.
+    pub scale: f32,
+    pub noise: NoiseDescription,
+    pub shape: TowerShapeDescription,
+    /// Faces with a normal that points up more than this use a biome's `top_color`.
+    pub top_normal_threshold: f32,
+    /// Sorted from lowest to highest.
+    pub biomes: Vec<Biome>,
+}
+
+#[derive(Clone, SerializeDeserialize)]
+pub struct NoiseDescription {
+    /// Only 1, 2, 4, 8, and 16 octaves are supported.
+    pub octaves: usize,
+    pub persistence: f32,
+    /// The size in meters of the largest noise features.
+    pub feature_size: f32,
+    /// Where in the noise field the terrain is sampled. Change this to get a different tower.
+    pub offset: f32,
+    pub amplitude: f32,
+}
+
+/// The tower has no top, so nothing in here depends on the terrain's height.
+#[derive(Clone, SerializeDeserialize)]
+pub struct TowerShapeDescription {
+    /// How far from the center, as a fraction of the radius squared, the tower starts fading out.
+    pub edge_falloff_start: f32,
+    /// The height in meters of each thick and thin band. The bands repeat all the way up.
+    pub band_height: f32,
+    /// The density of the thinnest part of a band.
+    pub min_band_density: f32,
+    /// Shrinks all terrain a little so there aren't surfaces right on the noise's zero crossings.
+    pub surface_offset: f32,
+}
+
+/// Colors for terrain above `min_height` meters.
+#[derive(Clone, SerializeDeserialize)]
+pub struct Biome {
+    pub min_height: f32,
+    pub top_color: [f32; 3],
+    pub side_color: [f32; 3],
+}
+
+fn srgb(color: Color) -> [f32; 3] {
+    let color = color.to_srgb();
+    [color.x, color.y, color.z]
+}
+
+impl Default for TerrainDescription {
+    fn default() -> Self {
+        Self {
+            scale: 450.,
+            noise: NoiseDescription {
+                octaves: 8,
+                persistence: 0.5,
+                feature_size: 300.0,
+                offset: 2000.0,
+                amplitude: 1.3,
+            },
+            shape: TowerShapeDescription {
+                edge_falloff_start: 0.7,
+                band_height: 1000.0,
+                min_band_density: 0.4,
+                surface_offset: 0.02,
+            },
+            top_normal_threshold: 0.5,
+            biomes: vec![
+                Biome {
+                    min_height: 0.0,
+                    top_color: srgb(Color::from_srgb_hex(0x51c43f, 1.0)),
+                    side_color: srgb(
+                        Color::interpolate(Color::OCHRE, Color::BROWN, 0.7)
+                            .with_chroma(0.1)
+                            .with_lightness(0.7),
+                    ),
+                },
+                Biome {
+                    min_height: 1000.0,
+                    top_color: srgb(Color::MINT),
+                    side_color: srgb(Color::YELLOW.with_chroma(0.2).with_lightness(0.8)),
+                },
+                Biome {
+                    min_height: 2000.0,
+                    top_color: srgb(Color::OCHRE),
+                    side_color: srgb(Color::PURPLE.with_lightness(0.8)),
+                },
+                Biome {
+                    min_height: 2800.0,
+                    top_color: srgb(Color::WHITE),
+                    side_color: srgb(Color::from_srgb_hex(0xFFD700, 1.0)),
+                },
+            ],
+        }
+    }
+}
+
+impl TerrainDescription {
+    /// Loads a description from a JSON file.
+    /// Falls back to the default tower if the file is missing or invalid.
+    pub fn load(path: &str) -> Self {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(_) => return Self::default(),
+        };
+        match Self::from_json(&source) {
+            Some(description) => match description.validate() {
+                Ok(()) => description,
+                Err(error) => {
+                    println!("Invalid terrain description {:?}: {}", path, error);
+                    Self::default()
+                }
+            },
+            None => {
+                println!("Could not parse terrain description: {:?}", path);
+                Self::default()
+            }
+        }
+    }
+
+    /// Checks for values that parse but that the terrain can't be generated with.
+    pub fn validate(&self) -> Result<(), String> {
+        if ![1, 2, 4, 8, 16].contains(&self.noise.octaves) {
+            return Err(format!(
+                "{} noise octaves isn't supported. Use 1, 2, 4, 8, or 16.",
+                self.noise.octaves
+            ));
+        }
+        if self.shape.band_height <= 0.0 {
+            return Err("The band height must be more than zero.".to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns the linear sRGB color of a face at `height` meters.
+    pub fn color(&self, height: f32, normal: Vec3) -> Vec4 {
+        let biome = self
+            .biomes
+            .iter()
+            .rev()
+            .find(|biome| height > biome.min_height)
+            .or_else(|| self.biomes.first());
+
+        let [r, g, b] = match biome {
+            Some(biome) if normal.y > self.top_normal_threshold => biome.top_color,
+            Some(biome) => biome.side_color,
+            None => [1.0, 1.0, 1.0],
+        };
+        Color::new(r, g, b, 1.0).to_linear_srgb()
+    }
+}
@@ -0,0 +1,568 @@
+//! A compact binary format for terrain density fields.
+//!
+//! All numbers are little endian. The file starts with a header:
+//!
+//! | Field             | Type          |
+//! |-------------------|---------------|
+//! | magic             | `b"TWRT"`     |
+//! | version           | `u32`         |
+//! | scale             | `f32`         |
+//! | size_xz           | `u32`         |
+//! | size_y            | `u32`         |
+//! | chunk_size        | `u32`         |
+//! | world_offset      | `[f32; 3]`    |
+//! | description size  | `u32`         |
+//! | description       | UTF-8 JSON    |
+//!
+//! The density values follow, one block per chunk ordered by x, then y, then z.
+//! Each block covers the chunk's samples in the same order as `Terrain`'s values.
+//! Densities are quantized to `i16`s spanning `-DENSITY_RANGE..=DENSITY_RANGE`
+//! and each is stored as the difference from the previous sample in the block,
+//! zigzag encoded into a LEB128 varint. Neighboring densities are close, so most take 2 bytes.
+//!
+//! Streamed terrain has no top and only keeps the samples that were edited, so its `size_y` is 0
+//! and only the edits follow the header: a `u32` count of edited chunks, then for each chunk
+//! its coordinates as three `u32`s and a `u32` count of its edited samples.
+//! The samples are ordered by their index within the chunk. Each stores that index
+//! as the difference from the previous sample's as a LEB128 varint,
+//! followed by its density encoded the same way as above.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+use crate::*;
+
+const MAGIC: &[u8; 4] = b"TWRT";
+const VERSION: u32 = 3;
+
+/// Densities further from zero than this are clamped.
+/// That far from the surface only their sign affects the mesh.
+const DENSITY_RANGE: f32 = 4.0;
+
+/// Larger terrain is assumed to be a corrupt header.
+const MAX_SIZE: usize = 1 << 14;
+
+fn quantize(value: f32) -> i16 {
+    (value.clamp(-DENSITY_RANGE, DENSITY_RANGE) / DENSITY_RANGE * i16::MAX as f32).round() as i16
+}
+
+fn dequantize(value: i16) -> f32 {
+    value as f32 / i16::MAX as f32 * DENSITY_RANGE
+}
+
+/// The densities stored in a [TerrainFile].
+pub(crate) enum TerrainDensity {
+    /// Every sample, in the same order as `Terrain`'s values.
+    Values(Vec<f32>),
+    /// The edited samples of streamed terrain, grouped by the chunk each sample is in.
+    /// Everything else is sampled from the description again.
+    Edits(HashMap<Vec3u, HashMap<Vec3u, f32>>),
+}
+
+pub(crate) struct TerrainFile {
+    pub scale: f32,
+    pub size_xz: usize,
+    /// Ignored for [TerrainDensity::Edits] because streamed terrain has no top.
+    pub size_y: usize,
+    pub chunk_size: usize,
+    pub world_offset: Vec3,
+    /// The `TerrainDescription` as JSON.
+    pub description: String,
+    pub density: TerrainDensity,
+}
+
+impl TerrainFile {
+    /// Calls `f` with the index of every sample, one chunk at a time.
+    fn for_each_chunk(&self, mut f: impl FnMut(&mut dyn Iterator<Item = usize>)) {
+        let chunk_size = self.chunk_size.max(1);
+        for chunk_i in (0..self.size_xz).step_by(chunk_size) {
+            for chunk_j in (0..self.size_y).step_by(chunk_size) {
+                for chunk_k in (0..self.size_xz).step_by(chunk_size) {
+                    let i_range = chunk_i..(chunk_i + chunk_size).min(self.size_xz);
+                    let j_range = chunk_j..(chunk_j + chunk_size).min(self.size_y);
+                    let k_range = chunk_k..(chunk_k + chunk_size).min(self.size_xz);
+
+                    let (size_xz, size_y) = (self.size_xz, self.size_y);
+                    let mut indices = i_range.flat_map(move |i| {
+                        let k_range = k_range.clone();
+                        j_range.clone().flat_map(move |j| {
+                            k_range
+                                .clone()
+                                .map(move |k| i * size_xz * size_y + j * size_xz + k)
+                        })
+                    });
+                    f(&mut indices);
+                }
+            }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        write_u32(&mut bytes, VERSION);
+        write_f32(&mut bytes, self.scale);
+        write_u32(&mut bytes, self.size_xz as u32);
+        write_u32(
+            &mut bytes,
+            match self.density {
+                TerrainDensity::Values(_) => self.size_y as u32,
+                TerrainDensity::Edits(_) => 0,
+            },
+        );
+        write_u32(&mut bytes, self.chunk_size as u32);
+        write_f32(&mut bytes, self.world_offset.x);
+        write_f32(&mut bytes, self.world_offset.y);
+        write_f32(&mut bytes, self.world_offset.z);
+        write_u32(&mut bytes, self.description.len() as u32);
+        bytes.extend_from_slice(self.description.as_bytes());
+
+        match &self.density {
+            TerrainDensity::Values(values) => self.for_each_chunk(|indices| {
+                let mut previous = 0;
+                for index in indices {
+                    let value = quantize(values[index]) as i32;
+                    write_varint(&mut bytes, zigzag_encode(value - previous));
+                    previous = value;
+                }
+            }),
+            TerrainDensity::Edits(edits) => self.write_edits(&mut bytes, edits),
+        }
+        bytes
+    }
+
+    fn write_edits(&self, bytes: &mut Vec<u8>, edits: &HashMap<Vec3u, HashMap<Vec3u, f32>>) {
+        let chunk_size = self.chunk_size;
+        // Sorted so the same edits always produce the same file.
+        let mut chunks: Vec<_> = edits.iter().collect();
+        chunks.sort_by_key(|(chunk, _)| (chunk.x, chunk.y, chunk.z));
+
+        write_u32(bytes, chunks.len() as u32);
+        for (chunk, samples) in chunks {
+            write_u32(bytes, chunk.x as u32);
+            write_u32(bytes, chunk.y as u32);
+            write_u32(bytes, chunk.z as u32);
+            write_u32(bytes, samples.len() as u32);
+
+            let start = *chunk * chunk_size;
+            let mut samples: Vec<_> = samples
+                .iter()
+                .map(|(sample, value)| {
+                    let local = *sample - start;
+                    let index = local.x * chunk_size * chunk_size + local.y * chunk_size + local.z;
+                    (index, *value)
+                })
+                .collect();
+            samples.sort_by_key(|(index, _)| *index);
+
+            let mut previous_index = 0;
+            let mut previous = 0;
+            for (index, value) in samples {
+                write_varint(bytes, (index - previous_index) as u32);
+                previous_index = index;
+
+                let value = quantize(value) as i32;
+                write_varint(bytes, zigzag_encode(value - previous));
+                previous = value;
+            }
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.read_bytes(4)? != MAGIC {
+            return Err(invalid_data("not a terrain file"));
+        }
+        let version = reader.read_u32()?;
+        if version != VERSION {
+            return Err(invalid_data("unsupported terrain file version"));
+        }
+
+        let scale = reader.read_f32()?;
+        let size_xz = reader.read_u32()? as usize;
+        let size_y = reader.read_u32()? as usize;
+        let chunk_size = reader.read_u32()? as usize;
+        // Streamed terrain has no top.
+        let streamed = size_y == 0;
+        if size_xz == 0 || size_xz > MAX_SIZE || size_y > MAX_SIZE {
+            return Err(invalid_data("terrain size is out of range"));
+        }
+        if chunk_size == 0 || size_xz % chunk_size != 0 || size_y % chunk_size != 0 {
+            return Err(invalid_data("chunk size doesn't divide the terrain size"));
+        }
+        let world_offset = Vec3::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+        let description_length = reader.read_u32()? as usize;
+        let description = std::str::from_utf8(reader.read_bytes(description_length)?)
+            .map_err(|_| invalid_data("terrain description is not UTF-8"))?
+            .to_string();
+
+        if streamed {
+            let edits = read_edits(&mut reader, size_xz, chunk_size)?;
+            if reader.remaining() != 0 {
+                return Err(invalid_data("terrain file has trailing bytes"));
+            }
+            return Ok(Self {
+                scale,
+                size_xz,
+                size_y,
+                chunk_size,
+                world_offset,
+                description,
+                density: TerrainDensity::Edits(edits),
+            });
+        }
+
+        // Every sample takes at least one byte, so a file that's too short can be rejected
+        // before allocating anything.
+        let sample_count = size_xz
+            .checked_mul(size_xz)
+            .and_then(|v| v.checked_mul(size_y))
+            .ok_or_else(|| invalid_data("terrain is too large"))?;
+        if sample_count > reader.remaining() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "terrain file is truncated",
+            ));
+        }
+
+        let mut terrain_file = Self {
+            scale,
+            size_xz,
+            size_y,
+            chunk_size,
+            world_offset,
+            description,
+            density: TerrainDensity::Values(Vec::new()),
+        };
+
+        // Decode into a separate buffer because `for_each_chunk` borrows the file.
+        let mut values = vec![0.0; sample_count];
+        let mut result: Result<(), Error> = Ok(());
+        terrain_file.for_each_chunk(|indices| {
+            if result.is_err() {
+                return;
+            }
+            result = (|| -> Result<(), Error> {
+                let mut previous: i32 = 0;
+                for index in indices {
+                    let value = previous + zigzag_decode(reader.read_varint()?);
+                    let value = i16::try_from(value)
+                        .map_err(|_| invalid_data("density is out of range"))?;
+                    values[index] = dequantize(value);
+                    previous = value as i32;
+                }
+                Ok(())
+            })();
+        });
+        result?;
+        if reader.remaining() != 0 {
+            return Err(invalid_data("terrain file has trailing bytes"));
+        }
+
+        terrain_file.density = TerrainDensity::Values(values);
+        Ok(terrain_file)
+    }
+}
+
+/// Reads the edits of streamed terrain as written by `TerrainFile::write_edits`.
+fn read_edits(
+    reader: &mut Reader,
+    size_xz: usize,
+    chunk_size: usize,
+) -> Result<HashMap<Vec3u, HashMap<Vec3u, f32>>, Error> {
+    let chunks_xz = size_xz / chunk_size;
+    let samples_per_chunk = chunk_size * chunk_size * chunk_size;
+
+    let mut edits = HashMap::new();
+    for _ in 0..reader.read_u32()? {
+        let chunk = Vec3u::new(
+            reader.read_u32()? as usize,
+            reader.read_u32()? as usize,
+            reader.read_u32()? as usize,
+        );
+        // Streamed terrain has no top, but the samples still need to fit in a `usize`.
+        let fits_y = chunk
+            .y
+            .checked_add(1)
+            .and_then(|y| y.checked_mul(chunk_size))
+            .is_some();
+        if chunk.x >= chunks_xz || chunk.z >= chunks_xz || !fits_y {
+            return Err(invalid_data("edited chunk is outside the terrain"));
+        }
+        let start = chunk * chunk_size;
+
+        let mut samples = HashMap::new();
+        let mut index = 0;
+        let mut previous: i32 = 0;
+        for _ in 0..reader.read_u32()? {
+            index += reader.read_varint()? as usize;
+            if index >= samples_per_chunk {
+                return Err(invalid_data("edited sample is outside its chunk"));
+            }
+            let value = previous + zigzag_decode(reader.read_varint()?);
+            let value =
+                i16::try_from(value).map_err(|_| invalid_data("density is out of range"))?;
+            previous = value as i32;
+
+            let local = Vec3u::new(
+                index / (chunk_size * chunk_size),
+                index / chunk_size % chunk_size,
+                index % chunk_size,
+            );
+            samples.insert(start + local, dequantize(value));
+        }
+        edits.insert(chunk, samples);
+    }
+    Ok(edits)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn write_u32(bytes: &mut Vec<u8>, v: u32) {
+    bytes.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_f32(bytes: &mut Vec<u8>, v: f32) {
+    bytes.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut v: u32) {
+    while v >= 0x80 {
+        bytes.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    bytes.push(v as u8);
+}
+
+/// Maps signed values to unsigned ones so small negative values stay small.
+fn zigzag_encode(v: i32) -> u32 {
+    ((v << 1) ^ (v >> 31)) as u32
+}
+
+fn zigzag_decode(v: u32) -> i32 {
+    (v >> 1) as i32 ^ -((v & 1) as i32)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "terrain file is truncated"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_varint(&mut self) -> Result<u32, Error> {
+        let mut v = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.read_bytes(1)?[0];
+            v |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(invalid_data("varint is too long"))
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_terrain() -> Terrain {
+        let description = TerrainDescription {
+            scale: 100.0,
+            ..Default::default()
+        };
+        Terrain::new(64, 64, description)
+    }
+
+    fn assert_meshes_equal(a: &MeshData, b: &MeshData) {
+        assert_eq!(a.positions, b.positions);
+        assert_eq!(a.normals, b.normals);
+        assert_eq!(a.colors, b.colors);
+        assert_eq!(a.indices, b.indices);
+    }
+
+    /// Saving quantizes the densities, but saving a loaded terrain again doesn't change it.
+    fn assert_round_trip(terrain: Terrain) {
+        let bytes = terrain.to_bytes();
+        let sample_count = 64 * 64 * 64;
+        assert!(bytes.len() < sample_count * std::mem::size_of::<f32>());
+
+        let mut loaded = Terrain::from_bytes(&bytes).unwrap();
+        let mut reloaded = Terrain::from_bytes(&loaded.to_bytes()).unwrap();
+        for style in [TerrainMeshStyle::Flat, TerrainMeshStyle::Smooth] {
+            loaded.mesh_style = style;
+            reloaded.mesh_style = style;
+            for chunk in loaded.chunk_coordinates() {
+                for step in [1, 2] {
+                    assert_meshes_equal(
+                        &loaded.mesh_chunk(chunk, step),
+                        &reloaded.mesh_chunk(chunk, step),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_round_trip() {
+        assert_round_trip(small_terrain());
+    }
+
+    #[test]
+    fn edited_round_trip() {
+        let mut terrain = small_terrain();
+        let dirty_chunks = terrain.edit_sphere_values(Vec3::new(0.0, 20.0, 0.0), 15.0, -2.0);
+        assert!(!dirty_chunks.is_empty());
+        terrain.edit_sphere_values(Vec3::new(10.0, 40.0, 5.0), 8.0, 1.0);
+        assert_round_trip(terrain);
+    }
+
+    #[test]
+    fn streamed_terrain_can_be_edited() {
+        let description = TerrainDescription {
+            scale: 100.0,
+            ..Default::default()
+        };
+        let mut unedited = Terrain::new_streaming(64, description.clone());
+        let mut terrain = Terrain::new_streaming(64, description);
+        let dirty_chunks = terrain.edit_sphere_values(Vec3::new(0.0, 20.0, 0.0), 15.0, -2.0);
+        assert!(!dirty_chunks.is_empty());
+        assert!(dirty_chunks.iter().any(|chunk| {
+            terrain.mesh_chunk(*chunk, 1).positions != unedited.mesh_chunk(*chunk, 1).positions
+        }));
+    }
+
+    #[test]
+    fn streamed_round_trip() {
+        let description = TerrainDescription {
+            scale: 100.0,
+            ..Default::default()
+        };
+        let mut unedited = Terrain::new_streaming(64, description.clone());
+        let mut terrain = Terrain::new_streaming(64, description);
+        let mut dirty_chunks = terrain.edit_sphere_values(Vec3::new(0.0, 20.0, 0.0), 15.0, -2.0);
+        dirty_chunks.extend(terrain.edit_sphere_values(Vec3::new(10.0, 40.0, 5.0), 8.0, 1.0));
+
+        let mut loaded = Terrain::from_bytes(&terrain.to_bytes()).unwrap();
+        let mut reloaded = Terrain::from_bytes(&loaded.to_bytes()).unwrap();
+        assert!(dirty_chunks.iter().any(|chunk| {
+            loaded.mesh_chunk(*chunk, 1).positions != unedited.mesh_chunk(*chunk, 1).positions
+        }));
+        for style in [TerrainMeshStyle::Flat, TerrainMeshStyle::Smooth] {
+            loaded.mesh_style = style;
+            reloaded.mesh_style = style;
+            for chunk in &dirty_chunks {
+                for step in [1, 2] {
+                    assert_meshes_equal(
+                        &loaded.mesh_chunk(*chunk, step),
+                        &reloaded.mesh_chunk(*chunk, step),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let terrain_file = TerrainFile {
+            scale: 3.5,
+            size_xz: 3,
+            size_y: 5,
+            chunk_size: 1,
+            world_offset: Vec3::new(1.0, -2.0, 3.0),
+            description: "{}".to_string(),
+            density: TerrainDensity::Values(
+                (0..45).map(|i| (i as f32 * 0.3).sin() * 1.5).collect(),
+            ),
+        };
+        let loaded = TerrainFile::from_bytes(&terrain_file.to_bytes()).unwrap();
+        assert_eq!(loaded.scale, terrain_file.scale);
+        assert_eq!(loaded.size_xz, terrain_file.size_xz);
+        assert_eq!(loaded.size_y, terrain_file.size_y);
+        assert_eq!(loaded.chunk_size, terrain_file.chunk_size);
+        assert_eq!(loaded.world_offset, terrain_file.world_offset);
+        assert_eq!(loaded.description, terrain_file.description);
+        match (&loaded.density, &terrain_file.density) {
+            (TerrainDensity::Values(loaded), TerrainDensity::Values(values)) => {
+                assert_eq!(loaded.len(), values.len());
+                for (loaded, value) in loaded.iter().zip(values.iter()) {
+                    assert!((loaded - value).abs() <= DENSITY_RANGE / i16::MAX as f32);
+                }
+            }
+            _ => panic!("expected dense values"),
+        }
+    }
+
+    #[test]
+    fn densities_are_clamped() {
+        let terrain_file = TerrainFile {
+            scale: 1.0,
+            size_xz: 1,
+            size_y: 3,
+            chunk_size: 1,
+            world_offset: Vec3::ZERO,
+            description: "{}".to_string(),
+            density: TerrainDensity::Values(vec![-100.0, 0.0, 100.0]),
+        };
+        let loaded = TerrainFile::from_bytes(&terrain_file.to_bytes()).unwrap();
+        match loaded.density {
+            TerrainDensity::Values(values) => {
+                assert_eq!(values, vec![-DENSITY_RANGE, 0.0, DENSITY_RANGE])
+            }
+            _ => panic!("expected dense values"),
+        }
+    }
+
+    #[test]
+    fn bad_sizes_are_an_error() {
+        let header = |size_xz: u32, size_y: u32, chunk_size: u32| {
+            let mut bytes = MAGIC.to_vec();
+            write_u32(&mut bytes, VERSION);
+            write_f32(&mut bytes, 1.0);
+            write_u32(&mut bytes, size_xz);
+            write_u32(&mut bytes, size_y);
+            write_u32(&mut bytes, chunk_size);
+            bytes.extend_from_slice(&[0; 12]);
+            write_u32(&mut bytes, 0);
+            bytes
+        };
+        // Sizes that would allocate gigabytes.
+        assert!(TerrainFile::from_bytes(&header(1 << 20, 1 << 20, 32)).is_err());
+        assert!(TerrainFile::from_bytes(&header(4096, 4096, 32)).is_err());
+        assert!(TerrainFile::from_bytes(&header(0, 64, 32)).is_err());
+        assert!(TerrainFile::from_bytes(&header(64, 64, 0)).is_err());
+        assert!(TerrainFile::from_bytes(&header(64, 60, 32)).is_err());
+        assert!(TerrainFile::from_bytes(&header(64, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let bytes = small_terrain().to_bytes();
+        assert!(TerrainFile::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(TerrainFile::from_bytes(&bytes[..10]).is_err());
+        assert!(TerrainFile::from_bytes(b"nope").is_err());
+    }
+}
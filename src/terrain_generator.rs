@@ -1,16 +1,50 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
 use crate::*;
 
 pub struct Terrain {
     pub scale: f32,
     size_xz: usize,
+    /// Streamed terrain has no top, so this is `usize::MAX` for it.
     size_y: usize,
     values: Vec<f32>,
     mesh_normal_calculator: MeshNormalCalculator,
     chunk_size: usize,
     chunks: HashMap<Vec3u, Entity>,
     world_offset: Vec3,
+    description: Arc<TerrainDescription>,
+    streaming: Option<TerrainStreaming>,
+    pub mesh_style: TerrainMeshStyle,
+    /// Chunks further than each of these distances are meshed at half the resolution.
+    /// Empty means every chunk is meshed at full resolution.
+    pub level_of_detail_distances: Vec<f32>,
+    /// How many samples are skipped for each chunk. Missing chunks are at full resolution.
+    chunk_steps: HashMap<Vec3u, usize>,
+}
+
+/// How the marching cubes triangles are turned into a mesh.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TerrainMeshStyle {
+    /// Every triangle has its own vertices and a flat normal.
+    Flat,
+    /// Triangles share vertices and normals are smoothed between them.
+    Smooth,
+}
+
+/// Returns how many samples to step over for a chunk `distance` away from the viewer.
+fn level_of_detail_step(
+    level_of_detail_distances: &[f32],
+    chunk_size: usize,
+    distance: f32,
+) -> usize {
+    let mut step = 1;
+    for level_of_detail_distance in level_of_detail_distances {
+        if distance > *level_of_detail_distance && step * 2 <= chunk_size / 2 {
+            step *= 2;
+        }
+    }
+    step
 }
 
 fn sample_with_octaves<const LANES: usize>(
@@ -50,120 +84,225 @@ where
     sample.value.reduce_sum() / max_value
 }
 
-impl Terrain {
-    pub fn new(size_xz: usize, size_y: usize) -> Self {
-        let scale = 450.;
-        let mut terrain = Self {
-            scale,
-            size_xz,
-            size_y,
-            values: vec![0.0; size_xz * size_xz * size_y],
-            mesh_normal_calculator: MeshNormalCalculator::new(),
-            chunk_size: 32,
-            chunks: HashMap::new(),
-            world_offset: -Vec3::Y * 50.0 - Vec3::XZ * scale / 2.0,
-        };
-        terrain.generate_height_data();
-        terrain
+/// The shape of the tower, shared by the fully generated and the streamed terrain.
+/// The density doesn't depend on the terrain's height so the tower can go up forever.
+#[derive(Clone)]
+struct TowerShape {
+    description: Arc<TerrainDescription>,
+    size_per_tile: f32,
+}
+
+impl TowerShape {
+    fn new(description: Arc<TerrainDescription>, size_xz: usize) -> Self {
+        let size_per_tile = description.scale / size_xz as f32;
+        Self {
+            description,
+            size_per_tile,
+        }
     }
-    pub fn generate_height_data(&mut self) {
-        let scale = self.scale;
 
-        // let noise = noise::Perlin::new();
-        let mut noise = clatter::Simplex3d::new();
+    /// Returns the density of the sample at `i`, `j`, `k`. Positive values are solid.
+    fn density(&self, noise: &mut clatter::Simplex3d, i: usize, j: usize, k: usize) -> f32 {
+        let TerrainDescription {
+            scale,
+            noise: noise_description,
+            shape,
+            ..
+        } = &*self.description;
+        let scale = *scale;
+        let size_per_tile = self.size_per_tile;
 
         let radius_squared = (scale / 2.0) * (scale / 2.0);
         let center = Vec3::fill(scale) / 2.0;
 
         let offset = Vec3::ZERO;
 
-        let size_per_tile = scale / self.size_xz as f32;
-        let mut index = 0;
+        let p = Vec3::new(i as f32, j as f32, k as f32) * size_per_tile + offset;
+        let persistence = noise_description.persistence;
 
-        let top = size_per_tile * self.size_y as f32;
+        let sample;
+        {
+            let p = p / noise_description.feature_size + Vec3::fill(noise_description.offset);
+
+            sample = match noise_description.octaves {
+                1 => sample_with_octaves::<1>(noise, persistence, p.x, p.y, p.z),
+                2 => sample_with_octaves::<2>(noise, persistence, p.x, p.y, p.z),
+                4 => sample_with_octaves::<4>(noise, persistence, p.x, p.y, p.z),
+                8 => sample_with_octaves::<8>(noise, persistence, p.x, p.y, p.z),
+                16 => sample_with_octaves::<16>(noise, persistence, p.x, p.y, p.z),
+                octaves => unreachable!("{} octaves should be rejected by `validate`", octaves),
+            } * noise_description.amplitude;
+        }
 
-        for i in 0..self.size_xz {
-            for j in 0..self.size_y {
-                for k in 0..self.size_xz {
-                    let p = Vec3::new(i as f32, j as f32, k as f32) * size_per_tile + offset;
-                    let persistence = 0.5;
+        let p = p - center;
+        let distance_from_center = p.xz().length_squared();
 
-                    let mut sample = 0.0;
-                    {
-                        let p = p / 300.0 + Vec3::fill(2000.);
+        let v = distance_from_center / radius_squared;
+        let scale_factor = if v > shape.edge_falloff_start {
+            ((v - shape.edge_falloff_start) / (1.0 - shape.edge_falloff_start)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
 
-                        sample = (sample_with_octaves::<8>(&mut noise, persistence, p.x, p.y, p.z)
-                            * 1.3) as f32;
+        // Height density
+        let bands = j as f32 * size_per_tile / shape.band_height;
+        let density = (bands * std::f32::consts::TAU)
+            .sin()
+            .max(shape.min_band_density);
 
-                        // println!("SAMPLE: {:?}", sample);
-                        /*
-                        for _ in 0..5 {
-                            let p = p * frequency;
-                            sample += noise.get([p.x as f64, p.y as f64, p.z as f64]) * amplitude;
+        let sample = sample * density;
 
-                            max_value += amplitude;
-                            amplitude *= persistence;
-                            frequency *= 2.0;
-                        }*/
-                    }
+        sample - scale_factor - shape.surface_offset
+    }
+}
 
-                    // println!("SAMPLE: {:?}", sample);
-                    // println!("P: {:?}", p);
+/// State for terrain that's generated in chunks around a point instead of all at once.
+struct TerrainStreaming {
+    load_distance: f32,
+    unload_distance: f32,
+    /// Chunks closer than this are meshed immediately on the main thread
+    /// so the player never falls through terrain that hasn't streamed in yet.
+    immediate_distance: f32,
+    max_chunks_in_flight: usize,
+    /// Chunks that have been meshed and the step they were meshed with.
+    /// This includes empty chunks that have no entity.
+    loaded: HashMap<Vec3u, usize>,
+    /// Chunks being meshed on worker threads, oldest first.
+    in_flight: VecDeque<StreamedChunkTask>,
+    /// Increased whenever a chunk is edited or meshed on the main thread
+    /// so meshes that were started before then are thrown away.
+    generations: HashMap<Vec3u, usize>,
+    /// Edited densities, grouped by the chunk each sample is in.
+    /// Everything else is recalculated from the [TowerShape] when a chunk is meshed.
+    edits: HashMap<Vec3u, HashMap<Vec3u, f32>>,
+}
 
-                    let p = p - center;
-                    let distance_from_center = p.xz().length_squared();
-                    //  println!("DISTANCE FROM CENTER: {:?}", distance_from_center);
+struct StreamedChunkTask {
+    chunk: Vec3u,
+    step: usize,
+    generation: usize,
+    handle: ktasks::JoinHandle<'static, MeshData>,
+}
 
-                    let v = distance_from_center / radius_squared;
-                    let scale_factor = if v > 0.7 {
-                        // println!("V: {:?}", v);
-                        let clamp = ((v - 0.7) / 0.3).clamp(0.0, 1.0) as f64;
+impl TerrainStreaming {
+    fn new(edits: HashMap<Vec3u, HashMap<Vec3u, f32>>) -> Self {
+        Self {
+            load_distance: 700.0,
+            unload_distance: 900.0,
+            immediate_distance: 60.0,
+            max_chunks_in_flight: 8,
+            loaded: HashMap::new(),
+            in_flight: VecDeque::new(),
+            generations: HashMap::new(),
+            edits,
+        }
+    }
 
-                        // println!("CLAMP: {:?}", clamp);
-                        clamp
-                    } else {
-                        0.0
-                    };
+    fn generation(&self, chunk: Vec3u) -> usize {
+        self.generations.get(&chunk).copied().unwrap_or(0)
+    }
 
-                    // Height density
-                    let j_asf32 = j as f32;
-                    let percent_to_top = j_asf32 / top;
-                    let density = ((1.0 - percent_to_top)
-                        * (percent_to_top * std::f32::consts::TAU * 4.0).sin())
-                    .max(0.4);
-
-                    if (j as f32 * size_per_tile) > top * 0.8 {
-                        sample = -1.0;
-                        if v < 0.003 && (j as f32 * size_per_tile) < top * 0.9 {
-                            sample = 1.0;
-                        }
-                    }
+    fn edited_value(&self, sample: Vec3u, chunk_size: usize) -> Option<f32> {
+        self.edits
+            .get(&(sample / chunk_size))?
+            .get(&sample)
+            .copied()
+    }
 
-                    let sample = sample * density;
+    /// Returns the edited samples in a chunk, including the samples it shares with its neighbors.
+    fn chunk_edits(&self, chunk: Vec3u, chunk_size: usize) -> HashMap<Vec3u, f32> {
+        let start = chunk * chunk_size;
+        let end = start + Vec3u::fill(chunk_size);
+        let in_chunk = |sample: &Vec3u| {
+            (0..3).all(|axis| sample[axis] >= start[axis] && sample[axis] <= end[axis])
+        };
 
-                    //  println!("SCALE FACTOR: {:?}", scale_factor);
-                    let v = sample - scale_factor as f32 - 0.02;
-                    /*
-                    if p.x > 100.0 {
-                        sample = 1.0
-                    } else {
-                        sample = -1.0;
+        // Samples on the far borders of a chunk are stored with the neighboring chunks.
+        let mut edits = HashMap::new();
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    if let Some(chunk_edits) = self.edits.get(&(chunk + Vec3u::new(i, j, k))) {
+                        edits.extend(chunk_edits.iter().filter(|(sample, _)| in_chunk(*sample)));
                     }
-                    */
+                }
+            }
+        }
+        edits
+    }
+}
+
+impl Terrain {
+    pub fn new(size_xz: usize, size_y: usize, description: TerrainDescription) -> Self {
+        let scale = description.scale;
+        let mut terrain = Self {
+            scale,
+            size_xz,
+            size_y,
+            values: vec![0.0; size_xz * size_xz * size_y],
+            mesh_normal_calculator: MeshNormalCalculator::new(),
+            chunk_size: 32,
+            chunks: HashMap::new(),
+            world_offset: -Vec3::Y * 50.0 - Vec3::XZ * scale / 2.0,
+            description: Arc::new(description),
+            streaming: None,
+            mesh_style: TerrainMeshStyle::Flat,
+            level_of_detail_distances: Vec::new(),
+            chunk_steps: HashMap::new(),
+        };
+        terrain.generate_height_data();
+        terrain
+    }
+
+    /// Creates terrain that only samples and meshes the chunks near the point passed to
+    /// `update_streaming`. Chunks are meshed on worker threads.
+    /// Only edited density is kept around and the tower has no top.
+    pub fn new_streaming(size_xz: usize, description: TerrainDescription) -> Self {
+        let scale = description.scale;
+        Self {
+            scale,
+            size_xz,
+            size_y: usize::MAX,
+            values: Vec::new(),
+            mesh_normal_calculator: MeshNormalCalculator::new(),
+            chunk_size: 32,
+            chunks: HashMap::new(),
+            world_offset: -Vec3::Y * 50.0 - Vec3::XZ * scale / 2.0,
+            description: Arc::new(description),
+            streaming: Some(TerrainStreaming::new(HashMap::new())),
+            mesh_style: TerrainMeshStyle::Flat,
+            level_of_detail_distances: Vec::new(),
+            chunk_steps: HashMap::new(),
+        }
+    }
 
-                    self.values[index] = v as f32;
+    pub fn generate_height_data(&mut self) {
+        let mut noise = clatter::Simplex3d::new();
+        let shape = TowerShape::new(self.description.clone(), self.size_xz);
+
+        let mut index = 0;
+        for i in 0..self.size_xz {
+            for j in 0..self.size_y {
+                for k in 0..self.size_xz {
+                    self.values[index] = shape.density(&mut noise, i, j, k);
                     // values[i * (size * size) + j * size + k] = v;
                     index += 1;
                 }
             }
         }
-        //let values = calculate_values(scale, offset, size, center, radius_squared, &noise);
     }
 
+    fn chunk_world_offset(&self, chunk: Vec3u) -> Vec3 {
+        (chunk.as_f32() * self.chunk_size as f32) / self.size_xz as f32 * self.scale
+            + self.world_offset
+    }
+
+    /// Meshes `samples` samples starting at `offset`, using only every `step`th sample.
     pub fn create_chunk_mesh(
         &mut self,
         offset: Vec3u,
         samples: usize,
+        step: usize,
         world_offset: Vec3,
     ) -> MeshData {
         //println!("SIZE Y: {:?}", self.size_y);
@@ -172,73 +311,539 @@ impl Terrain {
             values: &self.values,
             size_xz: self.size_xz,
             size_y: self.size_y,
-            samples,
+            samples: samples / step,
+            step,
         };
 
-        let mut chunk = isosurface::MarchingCubes::new(samples);
+        let mut chunk = isosurface::MarchingCubes::new(samples / step);
         let sampler = isosurface::sampler::Sampler::new(&terrain_sampler);
 
         let scale = (samples as f32 / self.size_xz as f32) * self.scale;
-        let mut extractor = Extractor::new(scale, Vec3::ZERO, world_offset);
+        let mut extractor = Extractor::new(
+            scale,
+            samples / step,
+            Vec3::ZERO,
+            world_offset,
+            self.description.clone(),
+            self.mesh_style,
+        );
 
         chunk.extract(&sampler, &mut extractor);
-        let mesh_data = extractor.mesh_data;
 
-        // println!("NORMALS LEN: {:?}", mesh_data.normals.len());
-        // println!("PSOITIONS LEN: {:?}", mesh_data.positions.len());
+        // Skirts hide the cracks between chunks meshed at different resolutions.
+        let skirt_depth = (step > 1).then(|| scale / (samples / step) as f32);
+        extractor.finish(&mut self.mesh_normal_calculator, skirt_depth)
+    }
 
-        // println!("MESH DATA: {:#?}", mesh_data);
-        // self.mesh_normal_calculator
-        //     .calculate_normals(&mut mesh_data);
-        mesh_data
+    /// Removes density in a sphere around `center` and remeshes the chunks that changed.
+    /// `strength` is how much density is removed at the center of the sphere.
+    pub fn carve_sphere(&mut self, world: &mut World, center: Vec3, radius: f32, strength: f32) {
+        self.edit_sphere(world, center, radius, -strength);
     }
 
-    pub fn regenerate_chunk(&mut self, world: &mut World, chunk: Vec3u) {
-        let mut to_spawn = Vec::new();
-
-        (|graphics: &mut Graphics, meshes: &mut Assets<Mesh>| {
-            let world_offset = (chunk.as_f32() * self.chunk_size as f32) / self.size_xz as f32
-                * self.scale
-                + self.world_offset;
-            let mesh_data =
-                self.create_chunk_mesh(chunk * self.chunk_size, self.chunk_size, world_offset);
-            let has_a_tri = !mesh_data.indices.is_empty();
-            let mesh = meshes.add(Mesh::new(graphics, mesh_data));
-
-            if has_a_tri {
-                to_spawn.push((
-                    chunk,
-                    (
-                        mesh,
-                        Material::DEFAULT,
-                        Transform::new().with_position(world_offset),
-                        Collider::AttachedMesh,
-                    ),
-                ));
+    /// Adds density in a sphere around `center` and remeshes the chunks that changed.
+    pub fn add_sphere(&mut self, world: &mut World, center: Vec3, radius: f32, strength: f32) {
+        self.edit_sphere(world, center, radius, strength);
+    }
+
+    fn edit_sphere(&mut self, world: &mut World, center: Vec3, radius: f32, amount: f32) {
+        for chunk in self.edit_sphere_values(center, radius, amount) {
+            self.regenerate_chunk(world, chunk);
+        }
+    }
+
+    /// Changes the density in a sphere without remeshing. Returns the chunks that need remeshing.
+    pub(crate) fn edit_sphere_values(
+        &mut self,
+        center: Vec3,
+        radius: f32,
+        amount: f32,
+    ) -> HashSet<Vec3u> {
+        let mut dirty_chunks = HashSet::new();
+
+        // Streamed terrain only keeps the density it's edited, the rest is sampled again.
+        let shape = TowerShape::new(self.description.clone(), self.size_xz);
+        let mut noise = clatter::Simplex3d::new();
+
+        let size_per_tile = self.scale / self.size_xz as f32;
+        let local_center = (center - self.world_offset) / size_per_tile;
+        let radius_in_tiles = radius / size_per_tile;
+
+        let min = local_center - Vec3::fill(radius_in_tiles);
+        let max = local_center + Vec3::fill(radius_in_tiles);
+
+        // Nothing to edit if the sphere is entirely outside the terrain.
+        if max.x < 0.0 || max.y < 0.0 || max.z < 0.0 {
+            return dirty_chunks;
+        }
+        let min_i = (min.x.floor().max(0.0) as usize).min(self.size_xz);
+        let max_i = (max.x.ceil().max(0.0) as usize + 1).min(self.size_xz);
+        let min_j = (min.y.floor().max(0.0) as usize).min(self.size_y);
+        let max_j = (max.y.ceil().max(0.0) as usize + 1).min(self.size_y);
+        let min_k = (min.z.floor().max(0.0) as usize).min(self.size_xz);
+        let max_k = (max.z.ceil().max(0.0) as usize + 1).min(self.size_xz);
+
+        // Only used for terrain that isn't streamed. Streamed terrain's `size_y` would overflow.
+        let (size_xz, size_y) = (self.size_xz, self.size_y);
+        let index = |i: usize, j: usize, k: usize| i * size_xz * size_y + j * size_xz + k;
+
+        for i in min_i..max_i {
+            for j in min_j..max_j {
+                for k in min_k..max_k {
+                    let p =
+                        Vec3::new(i as f32, j as f32, k as f32) * size_per_tile + self.world_offset;
+                    let distance = (p - center).length();
+                    if distance > radius {
+                        continue;
+                    }
+
+                    let falloff = 1.0 - distance / radius;
+                    let sample = Vec3u::new(i, j, k);
+                    let old_value = match &self.streaming {
+                        Some(streaming) => streaming
+                            .edited_value(sample, self.chunk_size)
+                            .unwrap_or_else(|| shape.density(&mut noise, i, j, k)),
+                        None => self.values[index(i, j, k)],
+                    };
+                    let new_value = (old_value + amount * falloff).clamp(-1.0, 1.0);
+                    if new_value != old_value {
+                        match &mut self.streaming {
+                            Some(streaming) => {
+                                streaming
+                                    .edits
+                                    .entry(sample / self.chunk_size)
+                                    .or_default()
+                                    .insert(sample, new_value);
+                            }
+                            None => self.values[index(i, j, k)] = new_value,
+                        }
+                        self.mark_chunks_dirty(sample, &mut dirty_chunks);
+                    }
+                }
             }
-        })
-        .run(world);
+        }
+
+        dirty_chunks
+    }
+
+    /// Chunks share the samples on their borders, so a sample on a border dirties both chunks.
+    fn mark_chunks_dirty(&self, sample: Vec3u, dirty_chunks: &mut HashSet<Vec3u>) {
+        let chunks_xz = self.size_xz / self.chunk_size;
+        let chunks_y = self.size_y / self.chunk_size;
+
+        let chunk_range = |v: usize, chunk_count: usize| {
+            let chunk = v / self.chunk_size;
+            let start = if v % self.chunk_size == 0 && chunk > 0 {
+                chunk - 1
+            } else {
+                chunk
+            };
+            start..(chunk + 1).min(chunk_count)
+        };
 
-        for (key, to_spawn) in to_spawn {
-            if let Some(replacing) = self.chunks.insert(key, world.spawn(to_spawn)) {
-                let _ = world.despawn(replacing);
+        for i in chunk_range(sample.x, chunks_xz) {
+            for j in chunk_range(sample.y, chunks_y) {
+                for k in chunk_range(sample.z, chunks_xz) {
+                    dirty_chunks.insert(Vec3u::new(i, j, k));
+                }
             }
         }
     }
 
-    pub fn create_chunks(&mut self, world: &mut World) {
-        println!("CHUNKS: {:?}", self.size_y as f32 / self.chunk_size as f32);
+    /// Despawns a chunk and removes its collider from Rapier.
+    /// The cached collision shape is dropped as well because the mesh it was built from is gone.
+    fn despawn_chunk(world: &mut World, chunk_entity: Entity) {
+        let collider = world
+            .get_component_mut::<RapierCollider>(chunk_entity)
+            .ok()
+            .cloned();
+        if let Some(collider) = collider {
+            if let Ok(rapier_physics) = world.get_single_component_mut::<RapierPhysicsManager>() {
+                rapier_physics.remove_collider(chunk_entity, &collider);
+            }
+        }
+        let _ = world.despawn(chunk_entity);
+    }
+
+    pub fn regenerate_chunk(&mut self, world: &mut World, chunk: Vec3u) {
+        let mesh_data = if let Some(streaming) = &mut self.streaming {
+            // Meshes already in flight don't include the latest edits.
+            *streaming.generations.entry(chunk).or_insert(0) += 1;
+            // Chunks that aren't loaded pick up edits when they're streamed in.
+            let step = match streaming.loaded.get(&chunk) {
+                Some(step) => *step,
+                None => return,
+            };
+            self.mesh_chunk(chunk, step)
+        } else {
+            let step = self.chunk_steps.get(&chunk).copied().unwrap_or(1);
+            self.mesh_chunk(chunk, step)
+        };
+        self.spawn_chunk(world, chunk, mesh_data);
+    }
+
+    /// Meshes a whole chunk using every `step`th sample.
+    pub fn mesh_chunk(&mut self, chunk: Vec3u, step: usize) -> MeshData {
+        if self.streaming.is_some() {
+            return self.streamed_chunk(chunk, step).mesh();
+        }
+        let world_offset = self.chunk_world_offset(chunk);
+        self.create_chunk_mesh(chunk * self.chunk_size, self.chunk_size, step, world_offset)
+    }
+
+    /// The coordinates of every chunk in non-streamed terrain.
+    pub fn chunk_coordinates(&self) -> Vec<Vec3u> {
         let chunks_y = self.size_y / self.chunk_size;
         let chunks_xz = self.size_xz / self.chunk_size;
 
+        let mut chunks = Vec::with_capacity(chunks_xz * chunks_y * chunks_xz);
         for i in 0..chunks_xz {
             for j in 0..chunks_y {
                 for k in 0..chunks_xz {
-                    let chunk = Vec3u::new(i, j, k);
-                    self.regenerate_chunk(world, chunk);
+                    chunks.push(Vec3u::new(i, j, k));
+                }
+            }
+        }
+        chunks
+    }
+
+    /// Remeshes chunks whose level of detail changed because `focus` moved.
+    /// Only a few chunks are remeshed per call to avoid stalls.
+    /// Streamed terrain handles its level of detail in `update_streaming` instead.
+    pub fn update_level_of_detail(&mut self, world: &mut World, focus: Vec3) {
+        if self.streaming.is_some() || self.level_of_detail_distances.is_empty() {
+            return;
+        }
+
+        let chunk_world_size = self.scale / self.size_xz as f32 * self.chunk_size as f32;
+
+        let mut changed = Vec::new();
+        for chunk in self.chunk_coordinates() {
+            let center = self.chunk_world_offset(chunk) + Vec3::fill(chunk_world_size / 2.0);
+            let distance = (center - focus).length();
+            let step =
+                level_of_detail_step(&self.level_of_detail_distances, self.chunk_size, distance);
+            if self.chunk_steps.get(&chunk).copied().unwrap_or(1) != step {
+                changed.push((distance, chunk, step));
+            }
+        }
+
+        // Closest chunks first because they're the most noticeable.
+        changed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        for (_, chunk, step) in changed.into_iter().take(4) {
+            self.chunk_steps.insert(chunk, step);
+            self.regenerate_chunk(world, chunk);
+        }
+    }
+
+    /// Spawns a chunk's mesh and collider, replacing whatever was there before.
+    fn spawn_chunk(&mut self, world: &mut World, chunk: Vec3u, mesh_data: MeshData) {
+        let replacing = if mesh_data.indices.is_empty() {
+            // The chunk is empty, possibly because it was carved away.
+            self.chunks.remove(&chunk)
+        } else {
+            let mut mesh_data = Some(mesh_data);
+            let mesh = (|graphics: &mut Graphics, meshes: &mut Assets<Mesh>| {
+                meshes.add(Mesh::new(graphics, mesh_data.take().unwrap()))
+            })
+            .run(world);
+
+            let chunk_entity = world.spawn((
+                mesh,
+                Material::DEFAULT,
+                Transform::new().with_position(self.chunk_world_offset(chunk)),
+                Collider::AttachedMesh,
+            ));
+            self.chunks.insert(chunk, chunk_entity)
+        };
+
+        if let Some(replacing) = replacing {
+            Self::despawn_chunk(world, replacing);
+        }
+    }
+
+    pub fn create_chunks(&mut self, world: &mut World) {
+        // Streamed terrain creates its chunks in `update_streaming`.
+        if self.streaming.is_some() {
+            return;
+        }
+        println!("CHUNKS: {:?}", self.size_y as f32 / self.chunk_size as f32);
+        for chunk in self.chunk_coordinates() {
+            self.regenerate_chunk(world, chunk);
+        }
+    }
+
+    /// Encodes the terrain's density in the format described in `terrain_file`.
+    /// Streamed terrain only saves its edits because everything else is sampled again.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        TerrainFile {
+            scale: self.scale,
+            size_xz: self.size_xz,
+            size_y: self.size_y,
+            chunk_size: self.chunk_size,
+            world_offset: self.world_offset,
+            description: self.description.to_json(),
+            density: match &self.streaming {
+                Some(streaming) => TerrainDensity::Edits(streaming.edits.clone()),
+                None => TerrainDensity::Values(self.values.clone()),
+            },
+        }
+        .to_bytes()
+    }
+
+    /// Decodes terrain written by `to_bytes`. The chunks still need to be created with `create_chunks`.
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+        let terrain_file = TerrainFile::from_bytes(bytes)?;
+        let description =
+            TerrainDescription::from_json(&terrain_file.description).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "could not parse terrain description",
+                )
+            })?;
+        description
+            .validate()
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+
+        let (size_y, values, streaming) = match terrain_file.density {
+            TerrainDensity::Values(values) => (terrain_file.size_y, values, None),
+            TerrainDensity::Edits(edits) => {
+                (usize::MAX, Vec::new(), Some(TerrainStreaming::new(edits)))
+            }
+        };
+        Ok(Self {
+            scale: terrain_file.scale,
+            size_xz: terrain_file.size_xz,
+            size_y,
+            values,
+            mesh_normal_calculator: MeshNormalCalculator::new(),
+            chunk_size: terrain_file.chunk_size,
+            chunks: HashMap::new(),
+            world_offset: terrain_file.world_offset,
+            description: Arc::new(description),
+            streaming,
+            mesh_style: TerrainMeshStyle::Flat,
+            level_of_detail_distances: Vec::new(),
+            chunk_steps: HashMap::new(),
+        })
+    }
+
+    /// Saves the terrain, including any edits, so it can be loaded without regenerating it.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Loads chunks within range of `focus` and unloads chunks that are far away.
+    /// Does nothing for terrain that wasn't created with `new_streaming`.
+    pub fn update_streaming(&mut self, world: &mut World, focus: Vec3) {
+        if self.streaming.is_none() {
+            return;
+        }
+
+        let chunk_world_size = self.scale / self.size_xz as f32 * self.chunk_size as f32;
+        let chunks_xz = self.size_xz / self.chunk_size;
+        let world_offset = self.world_offset;
+        let chunk_distance = |chunk: Vec3u| {
+            let center = chunk.as_f32() * chunk_world_size
+                + world_offset
+                + Vec3::fill(chunk_world_size / 2.0);
+            (center - focus).length()
+        };
+        let distance_to_chunk_bounds = |chunk: Vec3u| {
+            let min = chunk.as_f32() * chunk_world_size + world_offset;
+            let max = min + Vec3::fill(chunk_world_size);
+            let closest = Vec3::new(
+                focus.x.clamp(min.x, max.x),
+                focus.y.clamp(min.y, max.y),
+                focus.z.clamp(min.z, max.z),
+            );
+            (closest - focus).length()
+        };
+
+        let streaming = self.streaming.as_mut().unwrap();
+
+        // Spawn chunks that have finished meshing if they're still wanted.
+        // Chunks that are still being meshed are checked again next time.
+        let mut finished = Vec::new();
+        for task in std::mem::take(&mut streaming.in_flight) {
+            match task.handle.get_result() {
+                Some(mesh_data) => {
+                    if task.generation == streaming.generation(task.chunk)
+                        && chunk_distance(task.chunk) < streaming.unload_distance
+                    {
+                        streaming.loaded.insert(task.chunk, task.step);
+                        finished.push((task.chunk, mesh_data));
+                    }
+                }
+                None => streaming.in_flight.push_back(task),
+            }
+        }
+
+        // Unload chunks that are out of range.
+        let unload_distance = streaming.unload_distance;
+        let mut unloading = Vec::new();
+        streaming.loaded.retain(|chunk, _| {
+            let keep = chunk_distance(*chunk) < unload_distance;
+            if !keep {
+                unloading.push(*chunk);
+            }
+            keep
+        });
+
+        // Start meshing chunks that are in range, closest first.
+        // Chunks that are loaded at the wrong level of detail are remeshed.
+        // They stay visible until their replacement is ready.
+        let focus_chunk = (focus - world_offset) / chunk_world_size;
+        let range = (streaming.load_distance / chunk_world_size).ceil() as isize + 1;
+        let mut immediate = Vec::new();
+        let mut wanted = Vec::new();
+        for i in -range..=range {
+            for j in -range..=range {
+                for k in -range..=range {
+                    let i = focus_chunk.x.floor() as isize + i;
+                    let j = focus_chunk.y.floor() as isize + j;
+                    let k = focus_chunk.z.floor() as isize + k;
+                    // The tower has no top, so chunks are only limited below and to the sides.
+                    if i < 0 || j < 0 || k < 0 || i as usize >= chunks_xz || k as usize >= chunks_xz
+                    {
+                        continue;
+                    }
+                    let chunk = Vec3u::new(i as usize, j as usize, k as usize);
+                    let distance = chunk_distance(chunk);
+                    let step = level_of_detail_step(
+                        &self.level_of_detail_distances,
+                        self.chunk_size,
+                        distance,
+                    );
+                    if !streaming.loaded.contains_key(&chunk)
+                        && distance_to_chunk_bounds(chunk) < streaming.immediate_distance
+                    {
+                        immediate.push((chunk, step));
+                    } else if distance < streaming.load_distance
+                        && streaming.loaded.get(&chunk) != Some(&step)
+                        && !streaming.in_flight.iter().any(|task| task.chunk == chunk)
+                    {
+                        wanted.push((distance, chunk, step));
+                    }
+                }
+            }
+        }
+        wanted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        for (_, chunk, step) in wanted {
+            let streaming = self.streaming.as_ref().unwrap();
+            if streaming.in_flight.len() >= streaming.max_chunks_in_flight {
+                break;
+            }
+            let task = StreamedChunkTask {
+                chunk,
+                step,
+                generation: streaming.generation(chunk),
+                handle: {
+                    let streamed_chunk = self.streamed_chunk(chunk, step);
+                    ktasks::spawn(async move { streamed_chunk.mesh() })
+                },
+            };
+            task.handle.run();
+            self.streaming.as_mut().unwrap().in_flight.push_back(task);
+        }
+
+        for chunk in unloading {
+            if let Some(chunk_entity) = self.chunks.remove(&chunk) {
+                Self::despawn_chunk(world, chunk_entity);
+            }
+        }
+
+        for (chunk, mesh_data) in finished {
+            self.spawn_chunk(world, chunk, mesh_data);
+        }
+
+        for (chunk, step) in immediate {
+            let streaming = self.streaming.as_mut().unwrap();
+            *streaming.generations.entry(chunk).or_insert(0) += 1;
+            streaming.loaded.insert(chunk, step);
+            let mesh_data = self.mesh_chunk(chunk, step);
+            self.spawn_chunk(world, chunk, mesh_data);
+        }
+    }
+
+    /// Collects what's needed to mesh a chunk of streamed terrain on another thread.
+    fn streamed_chunk(&self, chunk: Vec3u, step: usize) -> StreamedChunk {
+        let streaming = self.streaming.as_ref().unwrap();
+        StreamedChunk {
+            shape: TowerShape::new(self.description.clone(), self.size_xz),
+            size_xz: self.size_xz,
+            chunk_size: self.chunk_size,
+            step,
+            chunk,
+            extractor_scale: (self.chunk_size as f32 / self.size_xz as f32) * self.scale,
+            world_offset: self.chunk_world_offset(chunk),
+            mesh_style: self.mesh_style,
+            edits: streaming.chunk_edits(chunk, self.chunk_size),
+        }
+    }
+}
+
+/// A single chunk of streamed terrain that can be sampled and meshed
+/// without the rest of the terrain. This runs on worker threads.
+struct StreamedChunk {
+    shape: TowerShape,
+    size_xz: usize,
+    chunk_size: usize,
+    step: usize,
+    chunk: Vec3u,
+    extractor_scale: f32,
+    world_offset: Vec3,
+    mesh_style: TerrainMeshStyle,
+    /// Edited samples, which replace the density from `shape`.
+    edits: HashMap<Vec3u, f32>,
+}
+
+impl StreamedChunk {
+    fn mesh(self) -> MeshData {
+        let mut noise = clatter::Simplex3d::new();
+
+        // Chunks share the samples on their borders.
+        let samples = self.chunk_size / self.step;
+        let samples_per_side = samples + 1;
+        let start = self.chunk * self.chunk_size;
+        let mut values = Vec::with_capacity(samples_per_side * samples_per_side * samples_per_side);
+        for i in (0..samples_per_side).map(|i| start.x + i * self.step) {
+            for j in (0..samples_per_side).map(|j| start.y + j * self.step) {
+                for k in (0..samples_per_side).map(|k| start.z + k * self.step) {
+                    values.push(if i >= self.size_xz || k >= self.size_xz {
+                        -1.0
+                    } else if let Some(value) = self.edits.get(&Vec3u::new(i, j, k)) {
+                        *value
+                    } else {
+                        self.shape.density(&mut noise, i, j, k)
+                    });
                 }
             }
         }
+
+        let chunk_sampler = ChunkSampler {
+            values: &values,
+            samples,
+        };
+        let mut marching_cubes = isosurface::MarchingCubes::new(samples);
+        let sampler = isosurface::sampler::Sampler::new(&chunk_sampler);
+        let mut extractor = Extractor::new(
+            self.extractor_scale,
+            samples,
+            Vec3::ZERO,
+            self.world_offset,
+            self.shape.description.clone(),
+            self.mesh_style,
+        );
+        marching_cubes.extract(&sampler, &mut extractor);
+
+        let skirt_depth = (self.step > 1).then(|| self.extractor_scale / samples as f32);
+        extractor.finish(&mut MeshNormalCalculator::new(), skirt_depth)
     }
 }
 
@@ -284,13 +889,15 @@ struct TerrainSampler<'a> {
     offset: Vec3u,
     values: &'a [f32],
     samples: usize,
+    /// How many samples to move for each step of the marching cubes.
+    step: usize,
 }
 
 impl<'a> isosurface::source::ScalarSource for TerrainSampler<'a> {
     fn sample_scalar(&self, p: isosurface::math::Vec3) -> isosurface::distance::Signed {
-        let i = (p.x * (self.samples) as f32) as usize + self.offset.x;
-        let j = (p.y * (self.samples) as f32) as usize + self.offset.y;
-        let k = (p.z * (self.samples) as f32) as usize + self.offset.z;
+        let i = (p.x * (self.samples) as f32).round() as usize * self.step + self.offset.x;
+        let j = (p.y * (self.samples) as f32).round() as usize * self.step + self.offset.y;
+        let k = (p.z * (self.samples) as f32).round() as usize * self.step + self.offset.z;
 
         let index = i * self.size_xz * self.size_y + j * (self.size_xz) + k;
         if index > self.values.len() - 1 {
@@ -310,39 +917,159 @@ impl<'a> isosurface::source::ScalarSource for TerrainSampler<'a> {
     }
 }
 
+/// Samples the density of a single chunk.
+struct ChunkSampler<'a> {
+    values: &'a [f32],
+    samples: usize,
+}
+
+impl<'a> isosurface::source::ScalarSource for ChunkSampler<'a> {
+    fn sample_scalar(&self, p: isosurface::math::Vec3) -> isosurface::distance::Signed {
+        let samples_per_side = self.samples + 1;
+        let i = ((p.x * self.samples as f32).round() as usize).min(self.samples);
+        let j = ((p.y * self.samples as f32).round() as usize).min(self.samples);
+        let k = ((p.z * self.samples as f32).round() as usize).min(self.samples);
+
+        let index = i * samples_per_side * samples_per_side + j * samples_per_side + k;
+        isosurface::distance::Signed(self.values[index])
+    }
+}
+
+/// Identifies the marching cubes edge a vertex is on by the grid corner it starts at
+/// and the axis it runs along. An axis of 3 means the vertex is exactly on the corner,
+/// which every edge touching that corner shares.
+type EdgeId = ([usize; 3], u8);
+
+/// Finds the edge of a marching cubes grid with `samples` cells per side that `vertex` is on.
+fn edge_id(vertex: isosurface::math::Vec3, samples: usize) -> EdgeId {
+    let grid = [vertex.x, vertex.y, vertex.z].map(|v| v * samples as f32);
+
+    // Two coordinates are on grid lines and the third is interpolated along the edge.
+    let distances_from_grid = grid.map(|v| (v - v.round()).abs());
+    let axis = (0..3)
+        .max_by(|a, b| {
+            distances_from_grid[*a]
+                .partial_cmp(&distances_from_grid[*b])
+                .unwrap()
+        })
+        .unwrap();
+    if distances_from_grid[axis] < 0.0001 {
+        return (grid.map(|v| v.round() as usize), 3);
+    }
+
+    let mut corner = grid.map(|v| v.round() as usize);
+    corner[axis] = grid[axis].floor() as usize;
+    (corner, axis as u8)
+}
+
 struct Extractor {
     mesh_data: MeshData,
     indices: [u32; 3],
     positions: Vec<Vec3>,
     index: usize,
     scale: f32,
+    /// How many marching cubes cells are along each side of the chunk.
+    samples: usize,
     offset: Vec3,
     world_offset: Vec3,
+    description: Arc<TerrainDescription>,
+    style: TerrainMeshStyle,
+    /// Used by the smooth style to merge the vertices marching cubes emits
+    /// once for each cell around an edge.
+    welded_vertices: HashMap<EdgeId, u32>,
+    /// Maps marching cubes vertex indices to indices in `mesh_data`.
+    vertex_remap: Vec<u32>,
 }
 impl Extractor {
-    pub fn new(scale: f32, offset: Vec3, world_offset: Vec3) -> Self {
+    pub fn new(
+        scale: f32,
+        samples: usize,
+        offset: Vec3,
+        world_offset: Vec3,
+        description: Arc<TerrainDescription>,
+        style: TerrainMeshStyle,
+    ) -> Self {
         Self {
             positions: Vec::new(),
             mesh_data: MeshData::new(),
             indices: [0, 0, 0],
             index: 0,
             scale,
+            samples,
             offset,
             world_offset,
+            description,
+            style,
+            welded_vertices: HashMap::new(),
+            vertex_remap: Vec::new(),
         }
     }
+
+    /// Calculates smooth normals and colors if needed and returns the finished mesh.
+    /// If `skirt_depth` is set skirts are added along the chunk's borders.
+    fn finish(
+        mut self,
+        mesh_normal_calculator: &mut MeshNormalCalculator,
+        skirt_depth: Option<f32>,
+    ) -> MeshData {
+        if self.style == TerrainMeshStyle::Smooth {
+            mesh_normal_calculator.calculate_normals(&mut self.mesh_data);
+
+            let MeshData {
+                positions,
+                normals,
+                colors,
+                ..
+            } = &mut self.mesh_data;
+            colors.clear();
+            for (position, normal) in positions.iter().zip(normals.iter()) {
+                colors.push(
+                    self.description
+                        .color(position.y + self.world_offset.y, *normal),
+                );
+            }
+        }
+
+        if let Some(skirt_depth) = skirt_depth {
+            add_skirts(&mut self.mesh_data, self.scale, skirt_depth);
+        }
+        self.mesh_data
+    }
 }
 
 impl isosurface::extractor::Extractor for Extractor {
     fn extract_vertex(&mut self, vertex: isosurface::math::Vec3) {
-        self.positions
-            .push(Vec3::new(vertex.x, vertex.y, vertex.z) * self.scale + self.offset);
+        let position = Vec3::new(vertex.x, vertex.y, vertex.z) * self.scale + self.offset;
+        self.positions.push(position);
+
+        if self.style == TerrainMeshStyle::Smooth {
+            // Marching cubes emits the same vertex for each cell around an edge.
+            let positions = &mut self.mesh_data.positions;
+            let edge = edge_id(vertex, self.samples);
+            let index = *self.welded_vertices.entry(edge).or_insert_with(|| {
+                positions.push(position);
+                positions.len() as u32 - 1
+            });
+            self.vertex_remap.push(index);
+        }
     }
 
     fn extract_index(&mut self, index: usize) {
         self.indices[self.index] = index as u32;
         self.index += 1;
         if self.index == 3 {
+            self.index = 0;
+
+            if self.style == TerrainMeshStyle::Smooth {
+                let [i0, i1, i2] = self.indices.map(|i| self.vertex_remap[i as usize]);
+
+                // Welding can collapse tiny triangles.
+                if i0 != i1 && i1 != i2 && i0 != i2 {
+                    self.mesh_data.indices.push([i0, i1, i2]);
+                }
+                return;
+            }
+
             // Make the mesh flat shaded
             // Normals could be calculated here as
             let offset = self.mesh_data.positions.len() as u32;
@@ -361,40 +1088,8 @@ impl isosurface::extractor::Extractor for Extractor {
             self.mesh_data.normals.push(normal);
             self.mesh_data.normals.push(normal);
 
-            let mut color;
-
             let p0 = p0 + self.world_offset;
-            if p0.y > 2800. {
-                if normal.y > 0.5 {
-                    color = Color::WHITE.to_linear_srgb()
-                } else {
-                    color = Color::from_srgb_hex(0xFFD700, 1.0).to_linear_srgb();
-                }
-            } else if p0.y > 2000. {
-                if normal.y > 0.5 {
-                    color = Color::OCHRE.to_linear_srgb()
-                } else {
-                    color = Color::PURPLE.with_lightness(0.8).to_linear_srgb()
-                }
-            } else if p0.y > 1000. {
-                if normal.y > 0.5 {
-                    color = Color::MINT.to_linear_srgb();
-                } else {
-                    color = Color::YELLOW
-                        .with_chroma(0.2)
-                        .with_lightness(0.8)
-                        .to_linear_srgb()
-                }
-            } else {
-                if normal.y > 0.5 {
-                    color = Color::from_srgb_hex(0x51c43f, 1.0).to_linear_srgb();
-                } else {
-                    color = Color::interpolate(Color::OCHRE, Color::BROWN, 0.7)
-                        .with_chroma(0.1)
-                        .with_lightness(0.7)
-                        .to_linear_srgb()
-                }
-            }
+            let color = self.description.color(p0.y, normal);
 
             self.mesh_data.colors.push(color);
             self.mesh_data.colors.push(color);
@@ -403,11 +1098,80 @@ impl isosurface::extractor::Extractor for Extractor {
             self.mesh_data
                 .indices
                 .push([offset, offset + 1, offset + 2]);
-            self.index = 0;
         }
     }
 }
 
+/// Adds a strip of triangles hanging below every edge on the border of a chunk.
+/// Neighboring chunks meshed at different resolutions don't line up exactly,
+/// and skirts cover the gaps between them.
+fn add_skirts(mesh_data: &mut MeshData, chunk_extent: f32, depth: f32) {
+    let key = |p: Vec3| {
+        [
+            (p.x * 4096.0).round() as i32,
+            (p.y * 4096.0).round() as i32,
+            (p.z * 4096.0).round() as i32,
+        ]
+    };
+
+    // Edges used by only one triangle are on the mesh's boundary.
+    // Positions are used instead of indices so flat shaded meshes work too.
+    let mut edge_use_count: HashMap<([i32; 3], [i32; 3]), usize> = HashMap::new();
+    for [i0, i1, i2] in mesh_data.indices.iter().cloned() {
+        for (a, b) in [(i0, i1), (i1, i2), (i2, i0)] {
+            let a = key(mesh_data.positions[a as usize]);
+            let b = key(mesh_data.positions[b as usize]);
+            let edge = if a < b { (a, b) } else { (b, a) };
+            *edge_use_count.entry(edge).or_insert(0) += 1;
+        }
+    }
+
+    let epsilon = chunk_extent * 0.001;
+    let on_same_border = |a: Vec3, b: Vec3| {
+        (0..3).any(|axis| {
+            (a[axis] < epsilon && b[axis] < epsilon)
+                || (a[axis] > chunk_extent - epsilon && b[axis] > chunk_extent - epsilon)
+        })
+    };
+
+    let has_colors = mesh_data.colors.len() == mesh_data.positions.len();
+    let mut skirt_indices = Vec::new();
+    for [i0, i1, i2] in mesh_data.indices.iter().cloned() {
+        for (a, b) in [(i0, i1), (i1, i2), (i2, i0)] {
+            let (a, b) = (a as usize, b as usize);
+            let (p0, p1) = (mesh_data.positions[a], mesh_data.positions[b]);
+            let (key0, key1) = (key(p0), key(p1));
+            let edge = if key0 < key1 {
+                (key0, key1)
+            } else {
+                (key1, key0)
+            };
+            if edge_use_count[&edge] != 1 || !on_same_border(p0, p1) {
+                continue;
+            }
+
+            let offset = mesh_data.positions.len() as u32;
+            let (n0, n1) = (mesh_data.normals[a], mesh_data.normals[b]);
+            mesh_data.positions.push(p0);
+            mesh_data.positions.push(p1);
+            mesh_data.positions.push(p1 - n1 * depth);
+            mesh_data.positions.push(p0 - n0 * depth);
+            mesh_data.normals.extend_from_slice(&[n0, n1, n1, n0]);
+            if has_colors {
+                let (c0, c1) = (mesh_data.colors[a], mesh_data.colors[b]);
+                mesh_data.colors.extend_from_slice(&[c0, c1, c1, c0]);
+            }
+
+            // Skirts are visible from both sides.
+            skirt_indices.push([offset, offset + 1, offset + 2]);
+            skirt_indices.push([offset, offset + 2, offset + 3]);
+            skirt_indices.push([offset + 2, offset + 1, offset]);
+            skirt_indices.push([offset + 3, offset + 2, offset]);
+        }
+    }
+    mesh_data.indices.extend(skirt_indices);
+}
+
 struct MeshNormalCalculator {
     normal_use_count: Vec<i32>,
 }
@@ -429,7 +1193,9 @@ impl MeshNormalCalculator {
         for [p0, p1, p2] in mesh_data.indices.iter().cloned() {
             let dir0 = mesh_data.positions[p1 as usize] - mesh_data.positions[p0 as usize];
             let dir1 = mesh_data.positions[p2 as usize] - mesh_data.positions[p1 as usize];
-            let normal = dir0.cross(dir1).normalized();
+
+            // Not normalized so larger triangles contribute more.
+            let normal = dir0.cross(dir1);
             self.normal_use_count[p0 as usize] += 1;
             self.normal_use_count[p1 as usize] += 1;
             self.normal_use_count[p2 as usize] += 1;
@@ -444,7 +1210,11 @@ impl MeshNormalCalculator {
             .iter_mut()
             .zip(self.normal_use_count.iter())
         {
-            *normal = *normal / normal_use_count as f32;
+            *normal = if normal_use_count == 0 || normal.length_squared() == 0.0 {
+                Vec3::Y
+            } else {
+                normal.normalized()
+            };
         }
     }
 }
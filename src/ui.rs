@@ -12,6 +12,8 @@ pub fn get_ui() -> impl Widget<World, StandardContext<World>> {
                     text("The ancient worm gnaws your bones for infinity... \nBut perhaps you can try again...?")
                         .with_size(|_, _, _| 50.),
                 ),
+                align(Alignment::Start, Alignment::End, text(profile_summary).with_size(|_, _, _| 36.)),
+                align(Alignment::End, Alignment::End, text(game_over_prompt).with_size(|_, _, _| 36.)),
             ))),
         ),
          conditional(
@@ -30,7 +32,14 @@ pub fn get_ui() -> impl Widget<World, StandardContext<World>> {
             |world: &mut World, _| {
                 world.get_singleton::<GameState>().game_mode == GameMode::Title
             },
-            center(text("Last of the Sky Folk").with_size(|_, _, _| 100.).with_color(|_, _, _| Color::BLACK)),
+            stack((
+                center(text("Last of the Sky Folk").with_size(|_, _, _| 100.).with_color(|_, _, _| Color::BLACK)),
+                align(Alignment::Start, Alignment::End, padding(
+                    text(profile_summary)
+                        .with_size(|_, _, _| 36.)
+                        .with_color(|_, _, _| Color::BLACK.with_lightness(0.15)),
+                )),
+            )),
         ),
         conditional(
             |world: &mut World, _| {
@@ -101,13 +110,19 @@ pub fn get_ui() -> impl Widget<World, StandardContext<World>> {
                             use num_format::{Locale, WriteFormatted};
                             // Cargo fmt, why don't you work? :( 
 
-                                if let Ok(player_position) =
+                                if let Ok((player_position, rocket_ammo)) =
                                     (|player_transform: (&Transform, &CharacterController)| {
-                                        player_transform.0.position
+                                        let character_controller = player_transform.1;
+                                        let rocket_ammo = if character_controller.can_shoot {
+                                            format!("{} rockets\n", character_controller.rocket_ammo)
+                                        } else {
+                                            String::new()
+                                        };
+                                        (player_transform.0.position, rocket_ammo)
                                     })
                                     .try_run(world) {
                                         if player_position.y > 90_000.0 {
-                                            "∞ m".into()
+                                            format!("{}∞ m", rocket_ammo)
                                         } else {
                             // if player_position.y > 0.0 {
                                 let mut writer = String::new();
@@ -115,7 +130,7 @@ pub fn get_ui() -> impl Widget<World, StandardContext<World>> {
                                     &(player_position.y.floor() as i32),
                                     &Locale::en,
                                 );
-                                format!("{} m", writer)
+                                format!("{}{} m", rocket_ammo, writer)
                             }
                             } else {
                                 String::new()
@@ -125,8 +140,79 @@ pub fn get_ui() -> impl Widget<World, StandardContext<World>> {
                         .with_color(|_, _, _| Color::BLACK.with_lightness(0.15)),
                     ),
                 ),
+                align(
+                    Alignment::Start,
+                    Alignment::Start,
+                    padding(
+                        text(|world: &mut World| {
+                            (|worm_events: &WormEvents| {
+                                worm_events
+                                    .announcement
+                                    .map(|(message, _)| message.to_string())
+                                    .unwrap_or_default()
+                            })
+                            .try_run(world)
+                            .unwrap_or_default()
+                        })
+                        .with_size(|_, _, _| 60.)
+                        .with_color(|_, _, _| Color::RED.with_lightness(0.3)),
+                    ),
+                ),
+                align(
+                    Alignment::End,
+                    Alignment::Start,
+                    padding(
+                        text(|world: &mut World| {
+                            (|powerup_manager: &PowerupManager| {
+                                powerup_manager
+                                    .notification
+                                    .map(|(message, _)| message.to_string())
+                                    .unwrap_or_default()
+                            })
+                            .try_run(world)
+                            .unwrap_or_default()
+                        })
+                        .with_size(|_, _, _| 50.)
+                        .with_color(|_, _, _| Color::BLACK.with_lightness(0.15)),
+                    ),
+                ),
             )),
         ),
     ));
     ui
 }
+
+/// Tells the player how to continue from their checkpoint or restart the climb.
+fn game_over_prompt(world: &mut World) -> String {
+    let reached_checkpoint = (|checkpoints: Query<&Checkpoint>| {
+        checkpoints.iter().any(|checkpoint| checkpoint.save.is_some())
+    })
+    .try_run(world)
+    .unwrap_or(false);
+    if !reached_checkpoint {
+        return String::new();
+    }
+    let restart_keys: Vec<String> = world
+        .get_singleton::<InputActions>()
+        .bindings(RESTART)
+        .iter()
+        .filter_map(|binding| match binding {
+            InputBinding::Key(key) => Some(format!("{:?}", key)),
+            _ => None,
+        })
+        .collect();
+    if restart_keys.is_empty() {
+        return String::new();
+    }
+    format!(
+        "Press {} to restart from the bottom\nor any other key to continue from your checkpoint",
+        restart_keys.join(" or ")
+    )
+}
+
+/// The player's best and last runs from their [Profile].
+fn profile_summary(world: &mut World) -> String {
+    (|profile: &Profile| profile.summary())
+        .try_run(world)
+        .unwrap_or_default()
+}
@@ -1,9 +1,353 @@
+use std::sync::Arc;
+
 use crate::*;
 
+const VOICE_VOLUME: f32 = 20.0;
+const ENRAGED_VOICE_VOLUME: f32 = 40.0;
+
+/// Marks the worm's body.
 #[derive(Component, Clone)]
 pub struct Worm {
-    pub lerp_target: Option<f32>,
+    /// The entity that plays the worm's looping sound.
+    pub voice: Entity,
+}
+
+/// Tunable numbers for a worm boss.
+/// This can be loaded from JSON so the difficulty can be changed without editing code.
+/// Speeds are in meters per fixed update.
+#[derive(Clone, SerializeDeserialize)]
+pub struct WormDescription {
+    /// The worm stays dormant until this many seconds have passed or the player climbs above
+    /// `wake_player_height`.
+    pub wake_seconds: f32,
+    pub wake_player_height: f32,
+    pub rise_speed: f32,
+    /// Above `high_altitude` the worm rises at `high_altitude_rise_speed` instead.
+    pub high_altitude: f32,
+    pub high_altitude_rise_speed: f32,
+    /// The fraction of the remaining distance covered each fixed update when catching up.
+    pub catch_up_rate: f32,
+    /// Sorted from lowest to highest.
+    pub catch_up_phases: Vec<WormCatchUpPhase>,
+    /// Sorted from fewest to most rockets.
+    pub rage_phases: Vec<WormRagePhase>,
+    /// How long a rocket hit stops the worm. 0 disables stunning.
+    pub stun_seconds: f32,
+    /// Once this many rockets hit the worm it's defeated and rises to `defeated_height`.
+    pub defeat_rockets_hit: usize,
+    pub defeated_height: f32,
+    /// How far below the worm's origin its mouth is.
+    pub mouth_depth: f32,
+    /// The worm moves this much slower while slowed by a powerup.
+    pub slowed_speed_scale: f32,
+}
+
+/// When the player climbs above `player_height` the worm quickly rises to `worm_height`.
+#[derive(Clone, SerializeDeserialize)]
+pub struct WormCatchUpPhase {
+    pub player_height: f32,
+    pub worm_height: f32,
+}
+
+/// After `min_rockets_hit` rockets hit the worm it rises `extra_rise_speed` faster.
+/// The first rage phase enrages the worm.
+#[derive(Clone, SerializeDeserialize)]
+pub struct WormRagePhase {
+    pub min_rockets_hit: usize,
+    pub extra_rise_speed: f32,
+}
+
+impl Default for WormDescription {
+    fn default() -> Self {
+        Self {
+            wake_seconds: 0.0,
+            wake_player_height: 0.0,
+            rise_speed: 0.13,
+            high_altitude: 3000.0,
+            high_altitude_rise_speed: 0.05,
+            catch_up_rate: 0.02,
+            catch_up_phases: vec![
+                WormCatchUpPhase {
+                    player_height: 1300.0,
+                    worm_height: 1000.0,
+                },
+                WormCatchUpPhase {
+                    player_height: 2300.0,
+                    worm_height: 2000.0,
+                },
+                WormCatchUpPhase {
+                    player_height: 3000.0,
+                    worm_height: 3000.0,
+                },
+            ],
+            rage_phases: vec![
+                WormRagePhase {
+                    min_rockets_hit: 1,
+                    extra_rise_speed: 0.13,
+                },
+                WormRagePhase {
+                    min_rockets_hit: 11,
+                    extra_rise_speed: 0.13,
+                },
+            ],
+            stun_seconds: 2.0,
+            defeat_rockets_hit: 21,
+            defeated_height: 3800.0,
+            mouth_depth: 120.0,
+            slowed_speed_scale: 0.25,
+        }
+    }
+}
+
+impl WormDescription {
+    /// Loads a description from a JSON file.
+    /// Falls back to the default worm if the file is missing or invalid.
+    pub fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(source) => Self::from_json(&source).unwrap_or_else(|| {
+                println!("Could not parse worm description: {:?}", path);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WormState {
+    /// Waiting for the player to start climbing.
+    Dormant,
+    Chasing,
+    /// Hit by a rocket. The worm rises faster and spits the player out instead of eating them.
+    Enraged,
+    /// Briefly stopped by a rocket hit.
+    Stunned {
+        seconds_remaining: f32,
+    },
+    /// Hit by enough rockets. The worm rises to its defeated height.
+    Defeated,
+}
+
+/// Sent when a rocket hits the worm or is swallowed by it.
+#[derive(Clone, Copy, Debug)]
+pub struct RocketHit {
+    pub position: Vec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WormEvent {
+    Woke,
+    RocketHit {
+        rockets_hit: usize,
+    },
+    Enraged,
+    Stunned,
+    Recovered,
+    /// The worm entered the rage phase at this index in `WormDescription::rage_phases`.
+    RagePhase(usize),
+    /// The worm is rushing up to catch the player.
+    CatchingUp {
+        worm_height: f32,
+    },
+    Defeated,
+    /// The player fell into the worm's mouth.
+    ReachedPlayer {
+        enraged: bool,
+    },
+}
+
+/// Events the worm emitted during the most recent fixed update.
+/// The UI and audio read these instead of inspecting the worm directly.
+#[derive(Component, Clone, Default)]
+pub struct WormEvents {
+    pub events: Vec<WormEvent>,
+    /// A message for the UI to show and how many more seconds to show it for.
+    pub announcement: Option<(&'static str, f32)>,
+}
+
+#[derive(Component, Clone)]
+pub struct WormBehavior {
+    pub state: WormState,
+    pub description: Arc<WormDescription>,
     pub rockets_hit: usize,
+    lerp_target: Option<f32>,
+    elapsed_seconds: f32,
+    rage_phase: Option<usize>,
+    reached_player: bool,
+    slowed_seconds: f32,
+}
+
+impl WormBehavior {
+    pub fn new(description: Arc<WormDescription>) -> Self {
+        Self {
+            state: WormState::Dormant,
+            description,
+            rockets_hit: 0,
+            lerp_target: None,
+            elapsed_seconds: 0.0,
+            rage_phase: None,
+            reached_player: false,
+            slowed_seconds: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.description.clone());
+    }
+
+    /// Resumes the chase after the player respawns at a checkpoint.
+    /// Rocket hits and rage are kept.
+    pub fn respawn(&mut self) {
+        self.lerp_target = None;
+        self.reached_player = false;
+    }
+
+    /// Scales the worm's movement by `WormDescription::slowed_speed_scale` for `seconds`.
+    pub fn slow(&mut self, seconds: f32) {
+        self.slowed_seconds = self.slowed_seconds.max(seconds);
+    }
+
+    pub fn is_enraged(&self) -> bool {
+        self.rage_phase.is_some()
+    }
+
+    /// Runs the state machine for one fixed update and returns the worm's new height.
+    /// `rocket_hits` is how many rockets hit the worm since the last update.
+    pub fn update(
+        &mut self,
+        worm_height: f32,
+        player_height: f32,
+        delta_seconds: f32,
+        rocket_hits: usize,
+        events: &mut Vec<WormEvent>,
+    ) -> f32 {
+        let description = self.description.clone();
+        self.elapsed_seconds += delta_seconds;
+
+        // Rocket hits
+        for _ in 0..rocket_hits {
+            if self.state == WormState::Defeated {
+                break;
+            }
+            self.rockets_hit += 1;
+            events.push(WormEvent::RocketHit {
+                rockets_hit: self.rockets_hit,
+            });
+
+            let rage_phase = description
+                .rage_phases
+                .iter()
+                .rposition(|phase| self.rockets_hit >= phase.min_rockets_hit);
+            if rage_phase != self.rage_phase {
+                if self.rage_phase.is_none() {
+                    events.push(WormEvent::Enraged);
+                }
+                if let Some(rage_phase) = rage_phase {
+                    events.push(WormEvent::RagePhase(rage_phase));
+                }
+                self.rage_phase = rage_phase;
+                if self.state == WormState::Chasing {
+                    self.state = WormState::Enraged;
+                }
+            }
+
+            if self.rockets_hit >= description.defeat_rockets_hit {
+                self.state = WormState::Defeated;
+                self.lerp_target = Some(description.defeated_height);
+                events.push(WormEvent::Defeated);
+            } else if description.stun_seconds > 0.0 {
+                if !matches!(self.state, WormState::Stunned { .. }) {
+                    events.push(WormEvent::Stunned);
+                }
+                self.state = WormState::Stunned {
+                    seconds_remaining: description.stun_seconds,
+                };
+            }
+        }
+
+        // State transitions over time and altitude
+        match self.state {
+            WormState::Dormant => {
+                if self.elapsed_seconds >= description.wake_seconds
+                    || player_height >= description.wake_player_height
+                {
+                    self.state = WormState::Chasing;
+                    events.push(WormEvent::Woke);
+                }
+            }
+            WormState::Stunned { seconds_remaining } => {
+                let seconds_remaining = seconds_remaining - delta_seconds;
+                self.state = if seconds_remaining > 0.0 {
+                    WormState::Stunned { seconds_remaining }
+                } else {
+                    events.push(WormEvent::Recovered);
+                    if self.is_enraged() {
+                        WormState::Enraged
+                    } else {
+                        WormState::Chasing
+                    }
+                };
+            }
+            WormState::Chasing | WormState::Enraged | WormState::Defeated => {}
+        }
+
+        let start_height = worm_height;
+        let mut worm_height = worm_height;
+        match self.state {
+            WormState::Dormant | WormState::Stunned { .. } => {}
+            WormState::Chasing | WormState::Enraged | WormState::Defeated => {
+                if self.state != WormState::Defeated {
+                    if let Some(phase) = description
+                        .catch_up_phases
+                        .iter()
+                        .rev()
+                        .find(|phase| player_height > phase.player_height)
+                    {
+                        if self.lerp_target.is_none() && worm_height < phase.worm_height - 1.0 {
+                            events.push(WormEvent::CatchingUp {
+                                worm_height: phase.worm_height,
+                            });
+                        }
+                        self.lerp_target = Some(phase.worm_height);
+                    }
+                }
+
+                if let Some(lerp_target) = self.lerp_target {
+                    let diff = lerp_target - worm_height;
+                    if diff < 1.0 {
+                        self.lerp_target = None;
+                    } else {
+                        worm_height += diff * description.catch_up_rate;
+                    }
+                } else if worm_height > description.high_altitude {
+                    worm_height += description.high_altitude_rise_speed;
+                } else {
+                    worm_height += description.rise_speed;
+                }
+
+                if let Some(rage_phase) = self.rage_phase {
+                    for phase in &description.rage_phases[..=rage_phase] {
+                        worm_height += phase.extra_rise_speed;
+                    }
+                }
+            }
+        }
+
+        if self.slowed_seconds > 0.0 {
+            self.slowed_seconds -= delta_seconds;
+            worm_height =
+                start_height + (worm_height - start_height) * description.slowed_speed_scale;
+        }
+
+        if !self.reached_player && player_height < worm_height - description.mouth_depth {
+            self.reached_player = true;
+            events.push(WormEvent::ReachedPlayer {
+                enraged: self.is_enraged(),
+            });
+        }
+
+        worm_height
+    }
 }
 
 pub fn setup_worm(world: &mut World) {
@@ -75,10 +419,13 @@ pub fn setup_worm(world: &mut World) {
         Transform::new().with_position(Vec3::Y * -100.0),
         Material::DEFAULT,
         Color::from_srgb_hex(0x4B0082, 1.0),
-        Worm {
-            lerp_target: None,
-            rockets_hit: 0,
-        },
+        WormBehavior::new(Arc::new(WormDescription::load("assets/worm.json"))),
+        // Rockets hit this. It's a sensor so the worm doesn't collide with the player or terrain.
+        Collider::AttachedMesh,
+        ColliderProperties::new(ColliderPropertiesInner {
+            sensor: true,
+            ..Default::default()
+        }),
     ));
 
     let teeth = world.spawn((
@@ -88,7 +435,7 @@ pub fn setup_worm(world: &mut World) {
         Color::from_srgb_hex(0x4B0082, 1.0),
     ));
 
-    let mut audio_source = AudioSource::new().with_volume(20.0);
+    let mut audio_source = AudioSource::new().with_volume(VOICE_VOLUME);
     audio_source.play(&worm_sound, true);
 
     let inner_teeth = world.spawn((
@@ -104,87 +451,102 @@ pub fn setup_worm(world: &mut World) {
         //Collider::Sphere(400.0),
     ));
 
+    world
+        .add_component(body, Worm { voice: inner_teeth })
+        .unwrap();
+    world.spawn(WormEvents::default());
+
     set_parent(world, Some(body), teeth);
     set_parent(world, Some(body), inner_teeth);
 }
 
 pub fn run_worm(world: &mut World) {
     // Move the worm
+    (|(transform, behavior): (&mut Transform, &mut WormBehavior),
+      player: (&GlobalTransform, &CharacterController),
+      worm_events: &mut WormEvents,
+      game_state: &GameState,
+      time: &Time,
+      rocket_hits: EventReader<RocketHit>| {
+        worm_events.events.clear();
+        if game_state.game_mode != GameMode::Game {
+            return;
+        }
+        transform.position.y = behavior.update(
+            transform.position.y,
+            player.0.position.y,
+            time.fixed_time_step as f32,
+            rocket_hits.iter().count(),
+            &mut worm_events.events,
+        );
+    })
+    .run(world);
 
-    (|(transform, worm): (&mut Transform, &mut Worm),
-      player: (
-        &GlobalTransform,
-        &CharacterController,
-        &mut RigidBody,
-        &mut AudioSource,
-    ),
-      game_state: &mut GameState,
-      audio_assets: &mut Assets<Sound>| {
-        match game_state.game_mode {
-            GameMode::Game => {
-                if let Some(lerp_target) = worm.lerp_target {
-                    let diff = lerp_target - transform.position.y;
-                    if diff < 1.0 {
-                        worm.lerp_target = None;
-                    } else {
-                        transform.position.y += diff * 0.02;
-                    }
-                } else {
-                    if transform.position.y > 3000. {
-                        transform.position.y += 0.05;
-                    } else {
-                        transform.position.y += 0.13;
-                    }
-                }
-                let worm_angry = worm.rockets_hit > 0;
-
-                if worm_angry {
-                    transform.position.y += 0.13;
-                }
-
-                if worm.rockets_hit > 10 {
-                    transform.position.y += 0.13;
-                }
-
-                if worm.rockets_hit > 20 {
-                    worm.lerp_target = Some(3800.);
-                }
+    handle_worm_events.run(world);
 
-                if player.0.position.y > 1300. {
-                    worm.lerp_target = Some(1000.);
-                }
+    // The worm gets louder when it's angry.
+    let (voice, enraged) = (|worm: &Worm, worm_events: &WormEvents| {
+        (worm.voice, worm_events.events.contains(&WormEvent::Enraged))
+    })
+    .run(world);
+    if enraged {
+        if let Ok(voice) = world.get_component_mut::<AudioSource>(voice) {
+            voice.set_volume(ENRAGED_VOICE_VOLUME);
+        }
+    }
+}
 
-                if player.0.position.y > 2300. {
-                    worm.lerp_target = Some(2000.);
-                }
+/// Quiets the worm's voice back down after it was enraged.
+pub fn reset_worm_voice(world: &mut World) {
+    let voice = (|worm: &Worm| worm.voice).run(world);
+    if let Ok(voice) = world.get_component_mut::<AudioSource>(voice) {
+        voice.set_volume(VOICE_VOLUME);
+    }
+}
 
-                if player.0.position.y > 3000. {
-                    worm.lerp_target = Some(3000.);
-                }
+/// Reacts to the worm's events with announcements and the end of the game.
+fn handle_worm_events(
+    worm_events: &mut WormEvents,
+    player: (&CharacterController, &mut RigidBody, &mut AudioSource),
+    game_state: &mut GameState,
+    audio_assets: &mut Assets<Sound>,
+    time: &Time,
+) {
+    if let Some((_, seconds_remaining)) = &mut worm_events.announcement {
+        *seconds_remaining -= time.fixed_time_step as f32;
+        if *seconds_remaining <= 0.0 {
+            worm_events.announcement = None;
+        }
+    }
 
-                let player_diff = player.0.position.y - (transform.position.y - 120.0);
-
-                // transform.position.y = player.0.position.y - 100.;
-                //println!("PLAYER DIFF: {:?}", player_diff);
-                if player_diff < 0.0 {
-                    if worm_angry {
-                        player.2.velocity = Vec3::Y * 10_000.0 + Vec3::X * 2000.0;
-                        player.2.mutated_velocity = true;
-                        game_state.victory = true;
-                        player
-                            .3
-                            .play(&audio_assets.load("assets/upbeat_vibes.wav"), true);
-                        println!("PLAYER WINS?");
-                    } else {
-                        game_state.game_mode = GameMode::GameOver;
-                        println!("PLAYER LOSES");
-                    }
+    for event in worm_events.events.clone() {
+        match event {
+            WormEvent::Enraged => {
+                worm_events.announcement = Some(("The worm is enraged!", 3.0));
+            }
+            WormEvent::Stunned => {
+                worm_events.announcement = Some(("The worm reels!", 1.0));
+            }
+            WormEvent::Defeated => {
+                worm_events.announcement = Some(("The worm has had enough...", 3.0));
+            }
+            WormEvent::ReachedPlayer { enraged } => {
+                if enraged {
+                    player.1.velocity = Vec3::Y * 10_000.0 + Vec3::X * 2000.0;
+                    player.1.mutated_velocity = true;
+                    game_state.victory = true;
+                    player
+                        .2
+                        .play(&audio_assets.load("assets/upbeat_vibes.wav"), true);
+                    println!("PLAYER WINS?");
+                } else {
+                    game_state.game_mode = GameMode::GameOver;
+                    println!("PLAYER LOSES");
                 }
             }
             _ => {}
         }
-    })
-    .run(world);
+    }
 }
 
 pub fn create_worm_teeth(